@@ -0,0 +1,27 @@
+use collections::directory::{ Command, Directory, Outcome, HELP };
+use std::io::{ self, BufRead, Write };
+
+fn main() {
+    let mut directory: Directory = Directory::new();
+    let stdin = io::stdin();
+
+    println!("{}", HELP);
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let mut line: String = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match line.parse::<Command>().and_then(|command| directory.run(command)) {
+            Ok(Outcome::Done(message)) | Ok(Outcome::Listing(message)) => println!("{}", message),
+            Ok(Outcome::Quit) => break,
+            Err(error) => println!("{}", error)
+        }
+    }
+}
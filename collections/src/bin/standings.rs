@@ -0,0 +1,31 @@
+use collections::standings::{ self, Fixture, League };
+use std::env;
+use std::fs::File;
+use std::io::{ self, BufReader };
+use std::process;
+
+fn main() {
+    let path: Option<String> = env::args().nth(1);
+
+    let fixtures: Result<Vec<Fixture>, standings::FixtureError> = match &path {
+        Some(path) if path != "-" => match File::open(path) {
+            Ok(file) => standings::read_fixtures(BufReader::new(file)),
+            Err(error) => Err(error.into())
+        },
+        _ => standings::read_fixtures(io::stdin().lock())
+    };
+
+    let fixtures: Vec<Fixture> = fixtures.unwrap_or_else(|error| {
+        eprintln!("standings: {}: {}", path.as_deref().unwrap_or("stdin"), error);
+        process::exit(1);
+    });
+
+    let mut league: League = League::new(20);
+    for fixture in &fixtures {
+        league.record(fixture);
+    }
+
+    let played: usize = fixtures.iter().filter(|fixture| fixture.outcome.is_some()).count();
+    println!("{} of {} fixtures played\n", played, fixtures.len());
+    league.write_table(&mut io::stdout().lock()).unwrap();
+}
@@ -0,0 +1,63 @@
+use collections::stats::{ self, ModeCounter, OnlineStats, QuantileEstimator };
+use std::env;
+use std::fs::File;
+use std::io::{ self, BufRead, BufReader };
+use std::process;
+
+const PERCENTILES: [f64; 5] = [25.0, 50.0, 75.0, 90.0, 99.0];
+
+/// Summarises a file of numbers in one pass, without loading it into memory.
+fn main() {
+    let path: Option<String> = env::args().nth(1);
+    let reader: Box<dyn BufRead> = match &path {
+        Some(path) if path != "-" => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(error) => {
+                eprintln!("stats: {}: {}", path, error);
+                process::exit(1);
+            }
+        },
+        _ => Box::new(BufReader::new(io::stdin()))
+    };
+
+    let mut online: OnlineStats = OnlineStats::new();
+    let mut estimators: Vec<QuantileEstimator> = PERCENTILES.iter().map(|&p| QuantileEstimator::new(p)).collect();
+    let mut modes: ModeCounter = ModeCounter::new();
+    let mut all_integers: bool = true;
+
+    let result: Result<(), stats::ReadError> = stats::for_each_number(reader, |value| {
+        online.add(value);
+        estimators.iter_mut().for_each(|estimator| estimator.add(value));
+        if value.fract() == 0.0 && all_integers {
+            modes.add(value as i64);
+        } else {
+            all_integers = false;
+        }
+    });
+
+    if let Err(error) = result {
+        eprintln!("stats: {}: {}", path.as_deref().unwrap_or("stdin"), error);
+        process::exit(1);
+    }
+    if online.count() == 0 {
+        eprintln!("stats: no numbers found");
+        process::exit(1);
+    }
+
+    println!("count    {}", online.count());
+    println!("min      {}", online.min().unwrap());
+    println!("max      {}", online.max().unwrap());
+    println!("mean     {:.4}", online.mean().unwrap());
+    println!("std dev  {:.4}", online.std_dev().unwrap());
+    for (p, estimator) in PERCENTILES.iter().zip(&estimators) {
+        println!("p{:<7} ~{:.4}", p, estimator.estimate().unwrap());
+    }
+    if all_integers {
+        let modes: Vec<i64> = modes.modes();
+        if modes.len() <= 10 {
+            println!("modes    {:?}", modes);
+        } else {
+            println!("modes    {} values tie", modes.len());
+        }
+    }
+}
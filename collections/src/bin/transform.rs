@@ -0,0 +1,73 @@
+use collections::transform::Pipeline;
+use std::env;
+use std::fs;
+use std::io::{ self, Read, Write };
+use std::process;
+
+const USAGE: &str = "\
+Usage: transform [--in-place] TRANSFORMS [FILE]...
+
+Applies TRANSFORMS, a list of names separated by ',' or '|', to each FILE
+(or stdin) and prints the result.
+
+Transforms: pig-latin, title, upper, lower, snake, kebab, camel, pascal,
+            normalize, rot13
+
+Options:
+    -i, --in-place    Rewrite each FILE instead of printing it
+    -h, --help        Print this message";
+
+fn main() {
+    let mut in_place: bool = false;
+    let mut positional: Vec<String> = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-i" | "--in-place" => in_place = true,
+            _ => positional.push(arg)
+        }
+    }
+
+    if positional.is_empty() {
+        eprintln!("transform: no transforms given\n\n{}", USAGE);
+        process::exit(2);
+    }
+    let pipeline: Pipeline = positional.remove(0).parse().unwrap_or_else(|error| {
+        eprintln!("transform: {}", error);
+        process::exit(2);
+    });
+
+    if positional.is_empty() {
+        if in_place {
+            eprintln!("transform: --in-place needs at least one file");
+            process::exit(2);
+        }
+        let mut input: String = String::new();
+        if let Err(error) = io::stdin().read_to_string(&mut input) {
+            eprintln!("transform: stdin: {}", error);
+            process::exit(1);
+        }
+        print!("{}", pipeline.apply(&input));
+        io::stdout().flush().unwrap();
+        return;
+    }
+
+    for path in &positional {
+        let result: io::Result<()> = fs::read_to_string(path).and_then(|input| {
+            let output: String = pipeline.apply(&input);
+            if in_place {
+                fs::write(path, output)
+            } else {
+                io::stdout().write_all(output.as_bytes())
+            }
+        });
+
+        if let Err(error) = result {
+            eprintln!("transform: {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
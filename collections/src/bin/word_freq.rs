@@ -0,0 +1,112 @@
+use collections::word_freq::{ self, Config, Format, WordCounter, ENGLISH_STOP_WORDS };
+use std::env;
+use std::fs::File;
+use std::io::{ self, BufReader };
+use std::process;
+
+const USAGE: &str = "\
+Usage: word_freq [OPTIONS] [FILE]...
+
+Counts words in the given files, or stdin if there are none or FILE is -.
+
+Options:
+    --top K              Only print the K most frequent terms
+    --ngram N            Count runs of N words instead of single words
+    --stop-words FILE    Skip the words listed in FILE, one per line
+    --english            Skip common English words
+    --keep-case          Count \"Word\" and \"word\" separately
+    --format csv|json    Output format (default csv)
+    -h, --help           Print this message";
+
+struct Args {
+    top: Option<usize>,
+    format: Format,
+    config: Config,
+    files: Vec<String>
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args: Args = Args {
+        top: None,
+        format: Format::Csv,
+        config: Config::default(),
+        files: Vec::new()
+    };
+
+    let mut raw = env::args().skip(1);
+    while let Some(arg) = raw.next() {
+        let mut value = |name: &str| raw.next().ok_or(format!("{} needs a value", name));
+
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--top" => {
+                let k: String = value("--top")?;
+                args.top = Some(k.parse().map_err(|_| format!("invalid --top value '{}'", k))?);
+            }
+            "--ngram" => {
+                let n: String = value("--ngram")?;
+                args.config.ngram = match n.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err(format!("invalid --ngram value '{}'", n))
+                };
+            }
+            "--stop-words" => {
+                let path: String = value("--stop-words")?;
+                let file: File = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+                let words = word_freq::read_stop_words(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?;
+                args.config.stop_words.extend(words);
+            }
+            "--english" => {
+                args.config.stop_words.extend(ENGLISH_STOP_WORDS.iter().map(|word| word.to_string()));
+            }
+            "--keep-case" => args.config.lowercase = false,
+            "--format" => {
+                args.format = value("--format")?.parse().map_err(|e| format!("{}", e))?;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+            file => args.files.push(file.to_string())
+        }
+    }
+
+    Ok(args)
+}
+
+fn main() {
+    let args: Args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("word_freq: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut counter: WordCounter = WordCounter::new(args.config);
+
+    let files: Vec<String> = if args.files.is_empty() { vec![String::from("-")] } else { args.files };
+    for path in &files {
+        let result: io::Result<()> = if path == "-" {
+            counter.add_reader(io::stdin().lock())
+        } else {
+            File::open(path).and_then(|file| counter.add_reader(BufReader::new(file)))
+        };
+
+        if let Err(error) = result {
+            eprintln!("word_freq: {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+
+    let entries: Vec<(&str, usize)> = match args.top {
+        Some(k) => counter.top(k),
+        None => counter.sorted()
+    };
+
+    let stdout = io::stdout();
+    if let Err(error) = word_freq::write(&mut stdout.lock(), args.format, &entries) {
+        eprintln!("word_freq: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,324 @@
+//! Bounded caches that evict the least recently used ([`LruCache`]) or least
+//! frequently used ([`LfuCache`]) entry when full. Every operation is O(1):
+//! a `HashMap` finds the entry and an index-linked list keeps the eviction
+//! order.
+//!
+//! ```
+//! use collections::cache::{ Capacity, LruCache };
+//!
+//! let mut cache: LruCache<&str, i32> = LruCache::new(Capacity::Entries(2));
+//! cache.insert("a", 1);
+//! cache.insert("b", 2);
+//! cache.get("a");
+//! cache.insert("c", 3);
+//!
+//! assert_eq!(cache.get("b"), None);
+//! assert_eq!(cache.get("a"), Some(&1));
+//! assert_eq!(cache.stats().hits, 2);
+//! ```
+
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::time::{ Duration, Instant };
+
+mod lfu;
+mod lru;
+
+pub use self::lfu::LfuCache;
+pub use self::lru::{ Iter, LruCache };
+
+/// Where the caches get the time from, so TTL expiry can be tested without
+/// sleeping.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one and hand the other to a cache.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed_nanos: Arc<AtomicU64>
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock {
+            start: Instant::now(),
+            elapsed_nanos: Arc::new(AtomicU64::new(0))
+        }
+    }
+
+    /// Moves the clock forward, stopping at about 584 years past its start.
+    pub fn advance(&self, by: Duration) {
+        let nanos: u64 = by.as_nanos().min(u128::from(u64::MAX)) as u64;
+        let _ = self.elapsed_nanos.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |elapsed| {
+            Some(elapsed.saturating_add(nanos))
+        });
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> ManualClock {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + Duration::from_nanos(self.elapsed_nanos.load(Ordering::SeqCst))
+    }
+}
+
+/// How much a cache may hold before it starts evicting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capacity {
+    Entries(usize),
+    /// Total weight of the entries, as measured by the cache's weigher.
+    Bytes(usize)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub insertions: u64,
+    /// Entries dropped to make room.
+    pub evictions: u64,
+    /// Entries dropped because their TTL ran out.
+    pub expirations: u64
+}
+
+impl CacheStats {
+    /// Hits as a fraction of lookups, or 0 before any lookups.
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups: u64 = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+fn default_weigher<K, V>(_: &K, _: &V) -> usize {
+    mem::size_of::<K>() + mem::size_of::<V>()
+}
+
+/// The settings both caches share.
+struct Limits<K, V, C> {
+    capacity: Capacity,
+    weigher: fn(&K, &V) -> usize,
+    ttl: Option<Duration>,
+    clock: C
+}
+
+impl<K, V, C: Clock> Limits<K, V, C> {
+    fn new(capacity: Capacity, clock: C) -> Limits<K, V, C> {
+        Limits {
+            capacity,
+            weigher: default_weigher::<K, V>,
+            ttl: None,
+            clock
+        }
+    }
+
+    fn max_weight(&self) -> usize {
+        match self.capacity {
+            Capacity::Entries(max) | Capacity::Bytes(max) => max
+        }
+    }
+
+    fn weigh(&self, key: &K, value: &V) -> usize {
+        match self.capacity {
+            Capacity::Entries(_) => 1,
+            Capacity::Bytes(_) => (self.weigher)(key, value)
+        }
+    }
+
+    /// `None` means the entry never expires, including when `ttl` reaches
+    /// further than an `Instant` can.
+    fn expiry(&self, ttl: Option<Duration>) -> Option<Instant> {
+        ttl.and_then(|ttl| self.clock.now().checked_add(ttl))
+    }
+
+    fn is_expired(&self, expires: Option<Instant>) -> bool {
+        expires.is_some_and(|at| self.clock.now() >= at)
+    }
+}
+
+const NIL: usize = usize::MAX;
+
+struct Node<T> {
+    value: T,
+    prev: usize,
+    next: usize
+}
+
+/// Storage for the nodes of any number of doubly linked lists. Nodes are
+/// addressed by index, so the lists need no pointers, and freed slots are
+/// reused.
+struct Slab<T> {
+    slots: Vec<Option<Node<T>>>,
+    free: Vec<usize>
+}
+
+impl<T> Slab<T> {
+    fn new() -> Slab<T> {
+        Slab { slots: Vec::new(), free: Vec::new() }
+    }
+
+    /// Stores `value` in a node that isn't linked into any list yet.
+    fn insert(&mut self, value: T) -> usize {
+        let node: Node<T> = Node { value, prev: NIL, next: NIL };
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = Some(node);
+                index
+            }
+            None => {
+                self.slots.push(Some(node));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    /// Frees a node, which must already be unlinked.
+    fn remove(&mut self, index: usize) -> T {
+        let node: Node<T> = self.slots[index].take().expect("dangling slab index");
+        self.free.push(index);
+        node.value
+    }
+
+    fn node(&self, index: usize) -> &Node<T> {
+        self.slots[index].as_ref().expect("dangling slab index")
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<T> {
+        self.slots[index].as_mut().expect("dangling slab index")
+    }
+
+    fn get(&self, index: usize) -> &T {
+        &self.node(index).value
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut T {
+        &mut self.node_mut(index).value
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.free.clear();
+    }
+}
+
+/// The ends of one list threaded through a [`Slab`].
+#[derive(Debug, Clone, Copy)]
+struct List {
+    head: usize,
+    tail: usize
+}
+
+impl List {
+    fn new() -> List {
+        List { head: NIL, tail: NIL }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head == NIL
+    }
+
+    fn push_front<T>(&mut self, slab: &mut Slab<T>, index: usize) {
+        let head: usize = self.head;
+        self.link(slab, NIL, head, index);
+    }
+
+    fn insert_after<T>(&mut self, slab: &mut Slab<T>, after: usize, index: usize) {
+        let next: usize = slab.node(after).next;
+        self.link(slab, after, next, index);
+    }
+
+    fn link<T>(&mut self, slab: &mut Slab<T>, prev: usize, next: usize, index: usize) {
+        let node: &mut Node<T> = slab.node_mut(index);
+        node.prev = prev;
+        node.next = next;
+
+        if prev == NIL { self.head = index } else { slab.node_mut(prev).next = index }
+        if next == NIL { self.tail = index } else { slab.node_mut(next).prev = index }
+    }
+
+    fn unlink<T>(&mut self, slab: &mut Slab<T>, index: usize) {
+        let (prev, next) = {
+            let node: &Node<T> = slab.node(index);
+            (node.prev, node.next)
+        };
+
+        if prev == NIL { self.head = next } else { slab.node_mut(prev).next = next }
+        if next == NIL { self.tail = prev } else { slab.node_mut(next).prev = prev }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_links_through_slab() {
+        let mut slab: Slab<char> = Slab::new();
+        let mut list: List = List::new();
+        let a: usize = slab.insert('a');
+        let c: usize = slab.insert('c');
+        list.push_front(&mut slab, c);
+        list.push_front(&mut slab, a);
+        let b: usize = slab.insert('b');
+        list.insert_after(&mut slab, a, b);
+
+        let collect = |slab: &Slab<char>, list: &List| {
+            let mut chars: String = String::new();
+            let mut index: usize = list.head;
+            while index != NIL {
+                chars.push(*slab.get(index));
+                index = slab.node(index).next;
+            }
+            chars
+        };
+        assert_eq!(collect(&slab, &list), "abc");
+
+        list.unlink(&mut slab, a);
+        slab.remove(a);
+        list.unlink(&mut slab, c);
+        slab.remove(c);
+        assert_eq!(collect(&slab, &list), "b");
+        assert_eq!((list.head, list.tail), (b, b));
+
+        // Freed slots are reused.
+        assert_eq!(slab.insert('d'), c);
+        assert_eq!(slab.insert('e'), a);
+    }
+
+    #[test]
+    fn test_manual_clock_clones_share_time() {
+        let clock: ManualClock = ManualClock::new();
+        let other: ManualClock = clock.clone();
+        let before: Instant = other.now();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(other.now() - before, Duration::from_secs(5));
+
+        clock.advance(Duration::MAX);
+        clock.advance(Duration::MAX);
+        assert_eq!(other.now() - before, Duration::from_nanos(u64::MAX));
+    }
+
+    #[test]
+    fn test_hit_ratio() {
+        assert_eq!(CacheStats::default().hit_ratio(), 0.0);
+        let stats: CacheStats = CacheStats { hits: 3, misses: 1, ..CacheStats::default() };
+        assert_eq!(stats.hit_ratio(), 0.75);
+    }
+}
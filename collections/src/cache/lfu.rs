@@ -0,0 +1,368 @@
+use super::{ CacheStats, Capacity, Clock, Limits, List, Slab, SystemClock, NIL };
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{ Duration, Instant };
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    weight: usize,
+    expires: Option<Instant>,
+    bucket: usize
+}
+
+/// The entries that have been used exactly `frequency` times.
+struct Bucket {
+    frequency: u64,
+    entries: List
+}
+
+/// Evicts the least frequently used entry when full, breaking ties by
+/// evicting the least recently used of them.
+///
+/// Entries are grouped into one bucket per use count and the buckets are
+/// kept in a list sorted by count, so finding the victim and bumping a count
+/// are both O(1) (Shah, Mitra and Matani, 2010). Expiry works as it does for
+/// [`LruCache`](super::LruCache).
+pub struct LfuCache<K, V, C = SystemClock> {
+    map: HashMap<K, usize>,
+    entries: Slab<Entry<K, V>>,
+    buckets: Slab<Bucket>,
+    /// Buckets in ascending frequency, so the head holds the next victims.
+    frequencies: List,
+    weight: usize,
+    limits: Limits<K, V, C>,
+    stats: CacheStats
+}
+
+impl<K: Hash + Eq + Clone, V> LfuCache<K, V> {
+    pub fn new(capacity: Capacity) -> LfuCache<K, V> {
+        LfuCache::with_clock(capacity, SystemClock)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, C: Clock> LfuCache<K, V, C> {
+    pub fn with_clock(capacity: Capacity, clock: C) -> LfuCache<K, V, C> {
+        LfuCache {
+            map: HashMap::new(),
+            entries: Slab::new(),
+            buckets: Slab::new(),
+            frequencies: List::new(),
+            weight: 0,
+            limits: Limits::new(capacity, clock),
+            stats: CacheStats::default()
+        }
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> LfuCache<K, V, C> {
+        self.limits.ttl = Some(ttl);
+        self
+    }
+
+    pub fn weigher(mut self, weigher: fn(&K, &V) -> usize) -> LfuCache<K, V, C> {
+        self.limits.weigher = weigher;
+        self
+    }
+
+    /// Inserts or replaces an entry and returns the value it replaced. A
+    /// replaced entry starts counting uses from one again.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let ttl: Option<Duration> = self.limits.ttl;
+        self.insert_entry(key, value, ttl)
+    }
+
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.insert_entry(key, value, Some(ttl))
+    }
+
+    fn insert_entry(&mut self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        let previous: Option<V> = self.remove(&key);
+
+        let weight: usize = self.limits.weigh(&key, &value);
+        if weight > self.limits.max_weight() {
+            return previous;
+        }
+        while self.weight + weight > self.limits.max_weight() {
+            self.evict();
+        }
+
+        let head: usize = self.frequencies.head;
+        let bucket: usize = if head != NIL && self.buckets.get(head).frequency == 1 {
+            head
+        } else {
+            let bucket: usize = self.buckets.insert(Bucket { frequency: 1, entries: List::new() });
+            self.frequencies.push_front(&mut self.buckets, bucket);
+            bucket
+        };
+
+        let entry: Entry<K, V> = Entry {
+            key: key.clone(),
+            value,
+            weight,
+            expires: self.limits.expiry(ttl),
+            bucket
+        };
+        let index: usize = self.entries.insert(entry);
+        self.buckets.get_mut(bucket).entries.push_front(&mut self.entries, index);
+        self.map.insert(key, index);
+        self.weight += weight;
+        self.stats.insertions += 1;
+        previous
+    }
+
+    fn evict(&mut self) {
+        let victim: usize = self.buckets.get(self.frequencies.head).entries.tail;
+        let entry: Entry<K, V> = self.unlink(victim);
+        if self.limits.is_expired(entry.expires) {
+            self.stats.expirations += 1;
+        } else {
+            self.stats.evictions += 1;
+        }
+    }
+
+    /// Takes an entry out of its bucket, dropping the bucket if that leaves
+    /// it empty.
+    fn detach(&mut self, index: usize) {
+        let bucket: usize = self.entries.get(index).bucket;
+        self.buckets.get_mut(bucket).entries.unlink(&mut self.entries, index);
+        if self.buckets.get(bucket).entries.is_empty() {
+            self.frequencies.unlink(&mut self.buckets, bucket);
+            self.buckets.remove(bucket);
+        }
+    }
+
+    fn unlink(&mut self, index: usize) -> Entry<K, V> {
+        self.detach(index);
+        let entry: Entry<K, V> = self.entries.remove(index);
+        self.map.remove(&entry.key);
+        self.weight -= entry.weight;
+        entry
+    }
+
+    /// Moves an entry to the bucket for one more use.
+    fn touch(&mut self, index: usize) {
+        let bucket: usize = self.entries.get(index).bucket;
+        let frequency: u64 = self.buckets.get(bucket).frequency + 1;
+        let next: usize = self.buckets.node(bucket).next;
+
+        let target: usize = if next != NIL && self.buckets.get(next).frequency == frequency {
+            next
+        } else {
+            let target: usize = self.buckets.insert(Bucket { frequency, entries: List::new() });
+            self.frequencies.insert_after(&mut self.buckets, bucket, target);
+            target
+        };
+
+        self.detach(index);
+        self.buckets.get_mut(target).entries.push_front(&mut self.entries, index);
+        self.entries.get_mut(index).bucket = target;
+    }
+
+    /// Looks up an entry and counts a use of it.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = match self.map.get(key) {
+            Some(&index) => index,
+            None => {
+                self.stats.misses += 1;
+                return None;
+            }
+        };
+
+        if self.limits.is_expired(self.entries.get(index).expires) {
+            self.unlink(index);
+            self.stats.expirations += 1;
+            self.stats.misses += 1;
+            return None;
+        }
+
+        self.stats.hits += 1;
+        self.touch(index);
+        Some(&mut self.entries.get_mut(index).value)
+    }
+
+    /// Looks up an entry without counting a use, hit or miss.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let entry: &Entry<K, V> = self.entries.get(*self.map.get(key)?);
+        if self.limits.is_expired(entry.expires) { None } else { Some(&entry.value) }
+    }
+
+    /// How many times an entry has been used, counting its insertion.
+    pub fn frequency<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.peek(key)?;
+        let entry: &Entry<K, V> = self.entries.get(self.map[key]);
+        Some(self.buckets.get(entry.bucket).frequency)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.peek(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = *self.map.get(key)?;
+        let entry: Entry<K, V> = self.unlink(index);
+        if self.limits.is_expired(entry.expires) { None } else { Some(entry.value) }
+    }
+
+    /// Drops every expired entry now, in O(n), and returns how many there
+    /// were.
+    pub fn purge_expired(&mut self) -> usize {
+        let expired: Vec<usize> = self.map
+            .values()
+            .copied()
+            .filter(|&index| self.limits.is_expired(self.entries.get(index).expires))
+            .collect();
+        for &index in &expired {
+            self.unlink(index);
+        }
+        self.stats.expirations += expired.len() as u64;
+        expired.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+        self.buckets.clear();
+        self.frequencies = List::new();
+        self.weight = 0;
+    }
+}
+
+impl<K, V, C> LfuCache<K, V, C> {
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn capacity(&self) -> Capacity {
+        self.limits.capacity
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ManualClock;
+
+    #[test]
+    fn test_evicts_least_frequently_used() {
+        let mut cache: LfuCache<&str, i32> = LfuCache::new(Capacity::Entries(3));
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        cache.get("a");
+        cache.get("a");
+        cache.get("c");
+
+        cache.insert("d", 4);
+        assert!(!cache.contains_key("b"));
+        assert_eq!(cache.frequency("a"), Some(3));
+        assert_eq!(cache.frequency("c"), Some(2));
+        assert_eq!(cache.frequency("d"), Some(1));
+    }
+
+    #[test]
+    fn test_ties_evict_least_recent() {
+        let mut cache: LfuCache<&str, i32> = LfuCache::new(Capacity::Entries(2));
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.get("b");
+        cache.get("a");
+
+        cache.insert("c", 3);
+        assert!(!cache.contains_key("b"));
+        assert!(cache.contains_key("a"));
+        // The newcomer is now the least frequently used.
+        cache.insert("d", 4);
+        assert!(!cache.contains_key("c"));
+        assert_eq!(cache.stats().evictions, 2);
+    }
+
+    #[test]
+    fn test_removal_keeps_buckets_consistent() {
+        let mut cache: LfuCache<i32, i32> = LfuCache::new(Capacity::Entries(3));
+        for key in 0..3 {
+            cache.insert(key, key);
+            for _ in 0..key {
+                cache.get(&key);
+            }
+        }
+        assert_eq!(cache.remove(&0), Some(0));
+        assert_eq!(cache.remove(&1), Some(1));
+        assert_eq!(cache.insert(2, 20), Some(2));
+        assert_eq!(cache.frequency(&2), Some(1));
+
+        cache.insert(3, 3);
+        cache.insert(4, 4);
+        cache.insert(5, 5);
+        assert_eq!(cache.len(), 3);
+        assert!(!cache.contains_key(&2));
+    }
+
+    #[test]
+    fn test_ttl_and_bytes() {
+        let clock: ManualClock = ManualClock::new();
+        let mut cache: LfuCache<String, Vec<u8>, ManualClock> = LfuCache::with_clock(Capacity::Bytes(8), clock.clone())
+            .weigher(|_: &String, value: &Vec<u8>| value.len())
+            .ttl(Duration::from_millis(500));
+        cache.insert(String::from("a"), vec![0; 4]);
+        cache.insert_with_ttl(String::from("b"), vec![0; 4], Duration::from_secs(5));
+        cache.get("a");
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.weight(), 4);
+
+        cache.insert(String::from("c"), vec![0; 2]);
+        assert_eq!(cache.len(), 2);
+        cache.insert(String::from("d"), vec![0; 9]);
+        assert!(!cache.contains_key("d"));
+
+        let stats: CacheStats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.expirations), (1, 1, 1));
+
+        let mut forever: LfuCache<&str, u8, ManualClock> = LfuCache::with_clock(Capacity::Entries(1), clock.clone()).ttl(Duration::MAX);
+        forever.insert("a", 1);
+        clock.advance(Duration::from_secs(1_000_000));
+        assert_eq!(forever.get("a"), Some(&1));
+    }
+}
@@ -0,0 +1,347 @@
+use super::{ CacheStats, Capacity, Clock, Limits, List, Slab, SystemClock, NIL };
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::time::{ Duration, Instant };
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    weight: usize,
+    expires: Option<Instant>
+}
+
+/// Evicts the least recently used entry when full.
+///
+/// Expired entries are dropped lazily, when they are looked up or reach the
+/// end of the eviction order, so `len` can include some until
+/// [`purge_expired`](LruCache::purge_expired) is called.
+pub struct LruCache<K, V, C = SystemClock> {
+    map: HashMap<K, usize>,
+    entries: Slab<Entry<K, V>>,
+    /// Most recently used at the head.
+    order: List,
+    weight: usize,
+    limits: Limits<K, V, C>,
+    stats: CacheStats
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: Capacity) -> LruCache<K, V> {
+        LruCache::with_clock(capacity, SystemClock)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, C: Clock> LruCache<K, V, C> {
+    pub fn with_clock(capacity: Capacity, clock: C) -> LruCache<K, V, C> {
+        LruCache {
+            map: HashMap::new(),
+            entries: Slab::new(),
+            order: List::new(),
+            weight: 0,
+            limits: Limits::new(capacity, clock),
+            stats: CacheStats::default()
+        }
+    }
+
+    /// Expires entries `ttl` after they are inserted, unless
+    /// [`insert_with_ttl`](LruCache::insert_with_ttl) says otherwise.
+    pub fn ttl(mut self, ttl: Duration) -> LruCache<K, V, C> {
+        self.limits.ttl = Some(ttl);
+        self
+    }
+
+    /// Measures entries against a [`Capacity::Bytes`]. The default is the
+    /// size of `K` plus `V`, which leaves out heap data such as the contents
+    /// of a `String`.
+    pub fn weigher(mut self, weigher: fn(&K, &V) -> usize) -> LruCache<K, V, C> {
+        self.limits.weigher = weigher;
+        self
+    }
+
+    /// Inserts or replaces an entry, evicting others as needed to make room,
+    /// and returns the value it replaced. An entry heavier than the whole
+    /// capacity isn't stored.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let ttl: Option<Duration> = self.limits.ttl;
+        self.insert_entry(key, value, ttl)
+    }
+
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.insert_entry(key, value, Some(ttl))
+    }
+
+    fn insert_entry(&mut self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        let previous: Option<V> = self.remove(&key);
+
+        let weight: usize = self.limits.weigh(&key, &value);
+        if weight > self.limits.max_weight() {
+            return previous;
+        }
+        while self.weight + weight > self.limits.max_weight() {
+            self.evict();
+        }
+
+        let entry: Entry<K, V> = Entry {
+            key: key.clone(),
+            value,
+            weight,
+            expires: self.limits.expiry(ttl)
+        };
+        let index: usize = self.entries.insert(entry);
+        self.order.push_front(&mut self.entries, index);
+        self.map.insert(key, index);
+        self.weight += weight;
+        self.stats.insertions += 1;
+        previous
+    }
+
+    fn evict(&mut self) {
+        let entry: Entry<K, V> = self.unlink(self.order.tail);
+        if self.limits.is_expired(entry.expires) {
+            self.stats.expirations += 1;
+        } else {
+            self.stats.evictions += 1;
+        }
+    }
+
+    fn unlink(&mut self, index: usize) -> Entry<K, V> {
+        self.order.unlink(&mut self.entries, index);
+        let entry: Entry<K, V> = self.entries.remove(index);
+        self.map.remove(&entry.key);
+        self.weight -= entry.weight;
+        entry
+    }
+
+    /// Looks up an entry and marks it as the most recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = match self.map.get(key) {
+            Some(&index) => index,
+            None => {
+                self.stats.misses += 1;
+                return None;
+            }
+        };
+
+        if self.limits.is_expired(self.entries.get(index).expires) {
+            self.unlink(index);
+            self.stats.expirations += 1;
+            self.stats.misses += 1;
+            return None;
+        }
+
+        self.stats.hits += 1;
+        self.order.unlink(&mut self.entries, index);
+        self.order.push_front(&mut self.entries, index);
+        Some(&mut self.entries.get_mut(index).value)
+    }
+
+    /// Looks up an entry without marking it used or counting a hit or miss.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let entry: &Entry<K, V> = self.entries.get(*self.map.get(key)?);
+        if self.limits.is_expired(entry.expires) { None } else { Some(&entry.value) }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.peek(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = *self.map.get(key)?;
+        let entry: Entry<K, V> = self.unlink(index);
+        if self.limits.is_expired(entry.expires) { None } else { Some(entry.value) }
+    }
+
+    /// Drops every expired entry now, in O(n), and returns how many there
+    /// were.
+    pub fn purge_expired(&mut self) -> usize {
+        let expired: Vec<usize> = self.map
+            .values()
+            .copied()
+            .filter(|&index| self.limits.is_expired(self.entries.get(index).expires))
+            .collect();
+        for &index in &expired {
+            self.unlink(index);
+        }
+        self.stats.expirations += expired.len() as u64;
+        expired.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+        self.order = List::new();
+        self.weight = 0;
+    }
+
+    /// Entries from most to least recently used.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { entries: &self.entries, next: self.order.head }
+    }
+}
+
+impl<K, V, C> LruCache<K, V, C> {
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Total weight of the entries; the same as `len` for
+    /// [`Capacity::Entries`].
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn capacity(&self) -> Capacity {
+        self.limits.capacity
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+impl<K: Hash + Eq + Clone + fmt::Debug, V: fmt::Debug, C: Clock> fmt::Debug for LruCache<K, V, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    entries: &'a Slab<Entry<K, V>>,
+    next: usize
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        if self.next == NIL {
+            return None;
+        }
+        let entries: &'a Slab<Entry<K, V>> = self.entries;
+        let entry: &'a Entry<K, V> = entries.get(self.next);
+        self.next = entries.node(self.next).next;
+        Some((&entry.key, &entry.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ManualClock;
+
+    fn keys<C: Clock>(cache: &LruCache<String, i32, C>) -> Vec<&str> {
+        cache.iter().map(|(key, _)| key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache: LruCache<String, i32> = LruCache::new(Capacity::Entries(3));
+        for (i, key) in ["a", "b", "c"].iter().enumerate() {
+            cache.insert(key.to_string(), i as i32);
+        }
+        assert_eq!(cache.get("a"), Some(&0));
+        cache.insert(String::from("d"), 3);
+
+        assert_eq!(keys(&cache), vec!["d", "a", "c"]);
+        assert!(!cache.contains_key("b"));
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let mut cache: LruCache<String, i32> = LruCache::new(Capacity::Entries(2));
+        assert_eq!(cache.insert(String::from("a"), 1), None);
+        assert_eq!(cache.insert(String::from("a"), 2), Some(1));
+        assert_eq!(cache.len(), 1);
+
+        *cache.get_mut("a").unwrap() += 10;
+        assert_eq!(cache.peek("a"), Some(&12));
+        assert_eq!(cache.remove("a"), Some(12));
+        assert_eq!(cache.remove("a"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_byte_capacity() {
+        let mut cache: LruCache<String, i32> = LruCache::new(Capacity::Bytes(10))
+            .weigher(|key: &String, _: &i32| key.len());
+        cache.insert(String::from("four"), 1);
+        cache.insert(String::from("three"), 2);
+        assert_eq!(cache.weight(), 9);
+
+        cache.insert(String::from("xy"), 3);
+        assert_eq!(keys(&cache), vec!["xy", "three"]);
+
+        cache.insert(String::from("elevenchars"), 4);
+        assert!(!cache.contains_key("elevenchars"));
+        assert_eq!(cache.weight(), 7);
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let clock: ManualClock = ManualClock::new();
+        let mut cache: LruCache<String, i32, ManualClock> = LruCache::with_clock(Capacity::Entries(4), clock.clone())
+            .ttl(Duration::from_secs(10));
+        cache.insert(String::from("short"), 1);
+        cache.insert_with_ttl(String::from("long"), 2, Duration::from_secs(60));
+        cache.insert(String::from("other"), 3);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.peek("short"), None);
+        assert_eq!(cache.get("short"), None);
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(keys(&cache), vec!["long"]);
+        assert_eq!(cache.stats().expirations, 2);
+        assert_eq!(cache.stats().misses, 1);
+
+        // Too far off for an `Instant`, so it never expires.
+        cache.insert_with_ttl(String::from("forever"), 4, Duration::MAX);
+        clock.advance(Duration::from_secs(1_000_000));
+        assert_eq!(cache.get("forever"), Some(&4));
+    }
+
+    #[test]
+    fn test_stats_and_debug() {
+        let mut cache: LruCache<&str, i32> = LruCache::new(Capacity::Entries(2));
+        cache.insert("a", 1);
+        cache.get("a");
+        cache.get("a");
+        cache.get("z");
+        cache.peek("z");
+
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1, insertions: 1, evictions: 0, expirations: 0 });
+        assert_eq!(format!("{:?}", cache), "{\"a\": 1}");
+    }
+}
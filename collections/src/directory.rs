@@ -0,0 +1,393 @@
+//! A company directory of departments and the people in them, driven by
+//! plain-English commands.
+//!
+//! ```
+//! use collections::directory::{ Command, Directory };
+//!
+//! let mut directory: Directory = Directory::new();
+//! directory.run("Add Sally to Engineering".parse::<Command>().unwrap()).unwrap();
+//! directory.run("Add Amir to Sales".parse::<Command>().unwrap()).unwrap();
+//! assert_eq!(directory.department("Engineering"), Some(&["Sally".to_string()][..]));
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{ self, BufRead, BufReader, BufWriter, Write };
+use std::path::{ Path, PathBuf };
+use std::str::FromStr;
+
+pub const HELP: &str = "\
+Commands:
+    Add <name> to <department>
+    Remove <name> from <department>
+    Move <name> from <department> to <department>
+    List <department>
+    List all
+    Save <file>
+    Load <file>
+    Help
+    Quit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Add { name: String, department: String },
+    Remove { name: String, department: String },
+    Move { name: String, from: String, to: String },
+    List(String),
+    ListAll,
+    Save(PathBuf),
+    Load(PathBuf),
+    Help,
+    Quit
+}
+
+/// Splits `s` at the first ` <keyword> `, ignoring case.
+fn split_keyword<'a>(s: &'a str, keyword: &str) -> Option<(&'a str, &'a str)> {
+    let needle: String = format!(" {} ", keyword);
+    let position: usize = s.to_ascii_lowercase().find(&needle)?;
+    let (left, right) = (s[..position].trim(), s[position + needle.len()..].trim());
+    if left.is_empty() || right.is_empty() {
+        None
+    } else {
+        Some((left, right))
+    }
+}
+
+impl FromStr for Command {
+    type Err = DirectoryError;
+
+    /// Keywords are case-insensitive. Names and departments keep their case
+    /// and may contain spaces.
+    fn from_str(line: &str) -> Result<Command, DirectoryError> {
+        let line: &str = line.trim();
+        let (verb, rest) = match line.split_once(char::is_whitespace) {
+            Some((verb, rest)) => (verb, rest.trim()),
+            None => (line, "")
+        };
+        let invalid = || DirectoryError::InvalidCommand(line.to_string());
+
+        let command: Command = match (verb.to_ascii_lowercase().as_str(), rest) {
+            ("add", rest) => {
+                let (name, department) = split_keyword(rest, "to").ok_or_else(invalid)?;
+                Command::Add { name: name.to_string(), department: department.to_string() }
+            }
+            ("remove", rest) => {
+                let (name, department) = split_keyword(rest, "from").ok_or_else(invalid)?;
+                Command::Remove { name: name.to_string(), department: department.to_string() }
+            }
+            ("move", rest) => {
+                let (name, departments) = split_keyword(rest, "from").ok_or_else(invalid)?;
+                let (from, to) = split_keyword(departments, "to").ok_or_else(invalid)?;
+                Command::Move { name: name.to_string(), from: from.to_string(), to: to.to_string() }
+            }
+            ("list", rest) if rest.eq_ignore_ascii_case("all") => Command::ListAll,
+            ("list", rest) if !rest.is_empty() => Command::List(rest.to_string()),
+            ("save", rest) if !rest.is_empty() => Command::Save(PathBuf::from(rest)),
+            ("load", rest) if !rest.is_empty() => Command::Load(PathBuf::from(rest)),
+            ("help", "") => Command::Help,
+            ("quit", "") | ("exit", "") => Command::Quit,
+            _ => return Err(invalid())
+        };
+
+        Ok(command)
+    }
+}
+
+#[derive(Debug)]
+pub enum DirectoryError {
+    InvalidCommand(String),
+    UnknownDepartment(String),
+    NotInDepartment { name: String, department: String },
+    AlreadyInDepartment { name: String, department: String },
+    Io(io::Error),
+    /// A malformed line in a saved directory, with its 1-based line number.
+    Corrupt { line: usize }
+}
+
+impl fmt::Display for DirectoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirectoryError::InvalidCommand(command) => write!(f, "I don't understand '{}'. Type Help for a list of commands", command),
+            DirectoryError::UnknownDepartment(department) => write!(f, "There is no {} department", department),
+            DirectoryError::NotInDepartment { name, department } => write!(f, "{} isn't in {}", name, department),
+            DirectoryError::AlreadyInDepartment { name, department } => write!(f, "{} is already in {}", name, department),
+            DirectoryError::Io(error) => write!(f, "{}", error),
+            DirectoryError::Corrupt { line } => write!(f, "line {} isn't 'department<TAB>name'", line)
+        }
+    }
+}
+
+impl Error for DirectoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DirectoryError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for DirectoryError {
+    fn from(error: io::Error) -> DirectoryError {
+        DirectoryError::Io(error)
+    }
+}
+
+/// What a command did, for the caller to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Done(String),
+    Listing(String),
+    Quit
+}
+
+/// Departments mapped to the people in them. Each list is kept sorted, and
+/// a department disappears once its last person leaves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directory {
+    departments: HashMap<String, Vec<String>>
+}
+
+impl Directory {
+    pub fn new() -> Directory {
+        Directory::default()
+    }
+
+    pub fn add(&mut self, name: &str, department: &str) -> Result<(), DirectoryError> {
+        let people: &mut Vec<String> = self.departments.entry(department.to_string()).or_default();
+        match people.binary_search_by(|person| person.as_str().cmp(name)) {
+            Ok(_) => Err(DirectoryError::AlreadyInDepartment {
+                name: name.to_string(),
+                department: department.to_string()
+            }),
+            Err(index) => {
+                people.insert(index, name.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str, department: &str) -> Result<(), DirectoryError> {
+        let people: &mut Vec<String> = self.departments
+            .get_mut(department)
+            .ok_or_else(|| DirectoryError::UnknownDepartment(department.to_string()))?;
+        let index: usize = people
+            .binary_search_by(|person| person.as_str().cmp(name))
+            .map_err(|_| DirectoryError::NotInDepartment {
+                name: name.to_string(),
+                department: department.to_string()
+            })?;
+
+        people.remove(index);
+        if people.is_empty() {
+            self.departments.remove(department);
+        }
+        Ok(())
+    }
+
+    /// Moves `name` between departments. Nothing changes if either step
+    /// would fail.
+    pub fn move_person(&mut self, name: &str, from: &str, to: &str) -> Result<(), DirectoryError> {
+        if self.department(to).is_some_and(|people| people.iter().any(|person| person == name)) {
+            return Err(DirectoryError::AlreadyInDepartment {
+                name: name.to_string(),
+                department: to.to_string()
+            });
+        }
+        self.remove(name, from)?;
+        self.add(name, to)
+    }
+
+    /// The people in `department`, sorted.
+    pub fn department(&self, department: &str) -> Option<&[String]> {
+        self.departments.get(department).map(|people| people.as_slice())
+    }
+
+    /// Every department and its people, sorted by department.
+    pub fn all(&self) -> Vec<(&str, &[String])> {
+        let mut all: Vec<(&str, &[String])> = self.departments
+            .iter()
+            .map(|(department, people)| (department.as_str(), people.as_slice()))
+            .collect();
+        all.sort_by(|a, b| a.0.cmp(b.0));
+        all
+    }
+
+    /// Writes one `department<TAB>name` line per person.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (department, people) in self.all() {
+            for person in people {
+                writeln!(writer, "{}\t{}", department, person)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a directory written by `write_to`.
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Directory, DirectoryError> {
+        let mut directory: Directory = Directory::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line: String = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.split_once('\t') {
+                Some((department, name)) if !department.is_empty() && !name.is_empty() => {
+                    // A repeated line is harmless, so don't fail the load over it.
+                    let _ = directory.add(name, department);
+                }
+                _ => return Err(DirectoryError::Corrupt { line: index + 1 })
+            }
+        }
+        Ok(directory)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), DirectoryError> {
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Directory, DirectoryError> {
+        Directory::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Carries out `command`. A failed `Load` leaves the directory as it was.
+    pub fn run(&mut self, command: Command) -> Result<Outcome, DirectoryError> {
+        let outcome: Outcome = match command {
+            Command::Add { name, department } => {
+                self.add(&name, &department)?;
+                Outcome::Done(format!("Added {} to {}", name, department))
+            }
+            Command::Remove { name, department } => {
+                self.remove(&name, &department)?;
+                Outcome::Done(format!("Removed {} from {}", name, department))
+            }
+            Command::Move { name, from, to } => {
+                self.move_person(&name, &from, &to)?;
+                Outcome::Done(format!("Moved {} from {} to {}", name, from, to))
+            }
+            Command::List(department) => {
+                let people: &[String] = self
+                    .department(&department)
+                    .ok_or(DirectoryError::UnknownDepartment(department))?;
+                Outcome::Listing(people.join("\n"))
+            }
+            Command::ListAll => {
+                let listing: Vec<String> = self
+                    .all()
+                    .into_iter()
+                    .map(|(department, people)| format!("{}:\n    {}", department, people.join("\n    ")))
+                    .collect();
+                Outcome::Listing(listing.join("\n"))
+            }
+            Command::Save(path) => {
+                self.save(&path)?;
+                Outcome::Done(format!("Saved to {}", path.display()))
+            }
+            Command::Load(path) => {
+                *self = Directory::load(&path)?;
+                Outcome::Done(format!("Loaded {}", path.display()))
+            }
+            Command::Help => Outcome::Listing(HELP.to_string()),
+            Command::Quit => Outcome::Quit
+        };
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(directory: &mut Directory, line: &str) -> Result<Outcome, DirectoryError> {
+        directory.run(line.parse()?)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            "add Sally Smith TO Research and Development".parse::<Command>().unwrap(),
+            Command::Add { name: "Sally Smith".to_string(), department: "Research and Development".to_string() }
+        );
+        assert_eq!(
+            "Move Amir from Sales to Engineering".parse::<Command>().unwrap(),
+            Command::Move { name: "Amir".to_string(), from: "Sales".to_string(), to: "Engineering".to_string() }
+        );
+        assert_eq!("List all".parse::<Command>().unwrap(), Command::ListAll);
+        assert_eq!("List Sales".parse::<Command>().unwrap(), Command::List("Sales".to_string()));
+
+        for invalid in ["", "Add Sally", "Add to Sales", "List", "Fire Sally", "Help me"].iter() {
+            assert!(invalid.parse::<Command>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_listing_is_sorted() {
+        let mut directory: Directory = Directory::new();
+        for line in ["Add Sally to Engineering", "Add Amir to Sales", "Add Bob to Engineering"].iter() {
+            run(&mut directory, line).unwrap();
+        }
+
+        assert_eq!(
+            run(&mut directory, "List Engineering").unwrap(),
+            Outcome::Listing("Bob\nSally".to_string())
+        );
+        assert_eq!(
+            run(&mut directory, "List all").unwrap(),
+            Outcome::Listing("Engineering:\n    Bob\n    Sally\nSales:\n    Amir".to_string())
+        );
+        assert!(matches!(run(&mut directory, "List Marketing"), Err(DirectoryError::UnknownDepartment(_))));
+    }
+
+    #[test]
+    fn test_remove_and_move() {
+        let mut directory: Directory = Directory::new();
+        directory.add("Sally", "Engineering").unwrap();
+        directory.add("Amir", "Sales").unwrap();
+        assert!(matches!(directory.add("Sally", "Engineering"), Err(DirectoryError::AlreadyInDepartment { .. })));
+
+        directory.move_person("Sally", "Engineering", "Sales").unwrap();
+        assert_eq!(directory.department("Engineering"), None);
+        assert_eq!(directory.department("Sales").unwrap(), ["Amir", "Sally"]);
+
+        // Moving onto a duplicate leaves Sally where she was.
+        directory.add("Sally", "Support").unwrap();
+        assert!(directory.move_person("Sally", "Sales", "Support").is_err());
+        assert_eq!(directory.department("Sales").unwrap(), ["Amir", "Sally"]);
+
+        assert!(matches!(directory.remove("Bob", "Sales"), Err(DirectoryError::NotInDepartment { .. })));
+        directory.remove("Amir", "Sales").unwrap();
+        assert_eq!(directory.department("Sales").unwrap(), ["Sally"]);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let mut directory: Directory = Directory::new();
+        directory.add("Sally", "Engineering").unwrap();
+        directory.add("Amir", "Research and Development").unwrap();
+
+        let mut saved: Vec<u8> = Vec::new();
+        directory.write_to(&mut saved).unwrap();
+        assert_eq!(String::from_utf8(saved.clone()).unwrap(), "Engineering\tSally\nResearch and Development\tAmir\n");
+        assert_eq!(Directory::read_from(saved.as_slice()).unwrap(), directory);
+
+        assert!(matches!(
+            Directory::read_from("Engineering\tSally\nno tab here\n".as_bytes()),
+            Err(DirectoryError::Corrupt { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_failed_load_keeps_directory() {
+        let mut directory: Directory = Directory::new();
+        directory.add("Sally", "Engineering").unwrap();
+
+        let missing: PathBuf = std::env::temp_dir().join("collections-directory-missing.tsv");
+        assert!(matches!(directory.run(Command::Load(missing)), Err(DirectoryError::Io(_))));
+        assert_eq!(directory.department("Engineering").unwrap(), ["Sally"]);
+    }
+}
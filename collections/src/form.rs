@@ -0,0 +1,713 @@
+//! Typed form fields, declared as a schema and validated all at once.
+//!
+//! ```
+//! use collections::form::{ Field, Schema };
+//!
+//! let schema: Schema = Schema::new()
+//!     .field(Field::text("username").required().min_len(3).pattern("[a-z][a-z0-9_]*"))
+//!     .field(Field::integer("age").min(13));
+//!
+//! let record = schema.parse("username=express\nage=30").unwrap();
+//! assert_eq!(record.integer("age"), Some(30));
+//!
+//! let errors = schema.parse("username=X\nage=7").unwrap_err();
+//! assert_eq!(errors.len(), 2);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A value a user entered on a form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Form {
+    Username(String),
+    Age(i32),
+    Status(bool)
+}
+
+impl Form {
+    /// The schema for the three `Form` fields: a required `username`, and an
+    /// optional `age` and `status`.
+    pub fn schema() -> Schema {
+        Schema::new()
+            .field(
+                Field::text("username")
+                    .required()
+                    .min_len(3)
+                    .max_len(32)
+                    .pattern("[A-Za-z][A-Za-z0-9 _]*")
+            )
+            .field(Field::integer("age").min(0).max(150))
+            .field(Field::boolean("status"))
+    }
+
+    /// Parses `key=value` lines into `Form` values, in schema order.
+    pub fn parse_all(input: &str) -> Result<Vec<Form>, ValidationErrors> {
+        let record: Record = Form::schema().parse(input)?;
+        let mut forms: Vec<Form> = Vec::new();
+
+        if let Some(username) = record.text("username") {
+            forms.push(Form::Username(username.to_string()));
+        }
+        if let Some(age) = record.integer("age") {
+            // The schema caps age at 150, so it always fits.
+            forms.push(Form::Age(age as i32));
+        }
+        if let Some(status) = record.boolean("status") {
+            forms.push(Form::Status(status));
+        }
+
+        Ok(forms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Text,
+    Integer,
+    Boolean
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FieldType::Text => "text",
+            FieldType::Integer => "an integer",
+            FieldType::Boolean => "true or false"
+        })
+    }
+}
+
+/// A parsed field value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Integer(i64),
+    Boolean(bool)
+}
+
+/// One field of a schema. Start with `text`, `integer` or `boolean` and chain
+/// constraints onto it.
+#[derive(Debug, Clone)]
+pub struct Field {
+    name: String,
+    field_type: FieldType,
+    required: bool,
+    /// Minimum length in characters for text, minimum value for integers.
+    min: Option<i64>,
+    max: Option<i64>,
+    pattern: Option<Pattern>
+}
+
+impl Field {
+    fn new(name: &str, field_type: FieldType) -> Field {
+        Field {
+            name: name.to_string(),
+            field_type,
+            required: false,
+            min: None,
+            max: None,
+            pattern: None
+        }
+    }
+
+    pub fn text(name: &str) -> Field {
+        Field::new(name, FieldType::Text)
+    }
+
+    pub fn integer(name: &str) -> Field {
+        Field::new(name, FieldType::Integer)
+    }
+
+    /// Accepts `true`/`false`, `yes`/`no`, `on`/`off` and `1`/`0`.
+    pub fn boolean(name: &str) -> Field {
+        Field::new(name, FieldType::Boolean)
+    }
+
+    pub fn required(mut self) -> Field {
+        self.required = true;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the field isn't text.
+    pub fn min_len(mut self, min: usize) -> Field {
+        assert_eq!(self.field_type, FieldType::Text, "min_len only applies to text fields");
+        self.min = Some(min as i64);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the field isn't text.
+    pub fn max_len(mut self, max: usize) -> Field {
+        assert_eq!(self.field_type, FieldType::Text, "max_len only applies to text fields");
+        self.max = Some(max as i64);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the field isn't an integer.
+    pub fn min(mut self, min: i64) -> Field {
+        assert_eq!(self.field_type, FieldType::Integer, "min only applies to integer fields");
+        self.min = Some(min);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the field isn't an integer.
+    pub fn max(mut self, max: i64) -> Field {
+        assert_eq!(self.field_type, FieldType::Integer, "max only applies to integer fields");
+        self.max = Some(max);
+        self
+    }
+
+    /// Requires the whole value to match `pattern`. See [`Pattern`] for the
+    /// syntax.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field isn't text or `pattern` is invalid.
+    pub fn pattern(mut self, pattern: &str) -> Field {
+        assert_eq!(self.field_type, FieldType::Text, "pattern only applies to text fields");
+        self.pattern = Some(Pattern::new(pattern).unwrap_or_else(|error| panic!("{}", error)));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn validate(&self, raw: &str) -> Result<Value, ErrorKind> {
+        match self.field_type {
+            FieldType::Text => {
+                let length: i64 = raw.chars().count() as i64;
+                if let Some(min) = self.min.filter(|&min| length < min) {
+                    return Err(ErrorKind::TooShort { min: min as usize });
+                }
+                if let Some(max) = self.max.filter(|&max| length > max) {
+                    return Err(ErrorKind::TooLong { max: max as usize });
+                }
+                if let Some(pattern) = self.pattern.as_ref().filter(|pattern| !pattern.matches(raw)) {
+                    return Err(ErrorKind::PatternMismatch { pattern: pattern.as_str().to_string() });
+                }
+                Ok(Value::Text(raw.to_string()))
+            }
+            FieldType::Integer => {
+                let value: i64 = raw.parse().map_err(|_| ErrorKind::WrongType { expected: FieldType::Integer })?;
+                if let Some(min) = self.min.filter(|&min| value < min) {
+                    return Err(ErrorKind::TooSmall { min });
+                }
+                if let Some(max) = self.max.filter(|&max| value > max) {
+                    return Err(ErrorKind::TooLarge { max });
+                }
+                Ok(Value::Integer(value))
+            }
+            FieldType::Boolean => match raw.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(Value::Boolean(true)),
+                "false" | "no" | "off" | "0" => Ok(Value::Boolean(false)),
+                _ => Err(ErrorKind::WrongType { expected: FieldType::Boolean })
+            }
+        }
+    }
+}
+
+/// The fields a form accepts.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    fields: Vec<Field>
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the schema already has a field with the same name.
+    pub fn field(mut self, field: Field) -> Schema {
+        assert!(
+            self.fields.iter().all(|existing| existing.name != field.name),
+            "The schema already has a field called {}",
+            field.name
+        );
+        self.fields.push(field);
+        self
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Parses `key=value` lines, one per field, and checks them against the
+    /// schema. Blank lines are ignored and whitespace around keys and values
+    /// is trimmed. Every problem is reported, not just the first.
+    pub fn parse(&self, input: &str) -> Result<Record, ValidationErrors> {
+        let mut values: HashMap<String, Value> = HashMap::new();
+        let mut seen: Vec<&str> = Vec::new();
+        let mut errors: Vec<FieldError> = Vec::new();
+
+        for (index, line) in input.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, raw) = match line.split_once('=') {
+                Some((key, raw)) if !key.trim().is_empty() => (key.trim(), raw.trim()),
+                _ => {
+                    errors.push(FieldError::new(&format!("line {}", index + 1), ErrorKind::Malformed));
+                    continue;
+                }
+            };
+
+            let field: &Field = match self.fields.iter().find(|field| field.name == key) {
+                Some(field) => field,
+                None => {
+                    errors.push(FieldError::new(key, ErrorKind::Unknown));
+                    continue;
+                }
+            };
+
+            if seen.contains(&key) {
+                errors.push(FieldError::new(key, ErrorKind::Duplicate));
+                continue;
+            }
+            seen.push(key);
+
+            if raw.is_empty() {
+                if field.required {
+                    errors.push(FieldError::new(key, ErrorKind::Missing));
+                }
+                continue;
+            }
+
+            match field.validate(raw) {
+                Ok(value) => {
+                    values.insert(key.to_string(), value);
+                }
+                Err(kind) => errors.push(FieldError::new(key, kind))
+            }
+        }
+
+        for field in &self.fields {
+            if field.required && !seen.contains(&field.name.as_str()) {
+                errors.push(FieldError::new(&field.name, ErrorKind::Missing));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Record { values })
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
+
+/// The values of a form that passed validation. Optional fields that were
+/// left out have no value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    values: HashMap<String, Value>
+}
+
+impl Record {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.values.get(name) {
+            Some(Value::Text(text)) => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.values.get(name) {
+            Some(Value::Integer(value)) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn boolean(&self, name: &str) -> Option<bool> {
+        match self.values.get(name) {
+            Some(Value::Boolean(value)) => Some(*value),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Missing,
+    Unknown,
+    Duplicate,
+    /// A line without `key=value`.
+    Malformed,
+    WrongType { expected: FieldType },
+    TooShort { min: usize },
+    TooLong { max: usize },
+    TooSmall { min: i64 },
+    TooLarge { max: i64 },
+    PatternMismatch { pattern: String }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// The field's name, or `line N` for a malformed line.
+    pub field: String,
+    pub kind: ErrorKind
+}
+
+impl FieldError {
+    fn new(field: &str, kind: ErrorKind) -> FieldError {
+        FieldError { field: field.to_string(), kind }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Missing => write!(f, "{} is required", self.field),
+            ErrorKind::Unknown => write!(f, "{} is not a field on this form", self.field),
+            ErrorKind::Duplicate => write!(f, "{} is given more than once", self.field),
+            ErrorKind::Malformed => write!(f, "{} is not in key=value form", self.field),
+            ErrorKind::WrongType { expected } => write!(f, "{} must be {}", self.field, expected),
+            ErrorKind::TooShort { min } => write!(f, "{} must be at least {} characters", self.field, min),
+            ErrorKind::TooLong { max } => write!(f, "{} must be at most {} characters", self.field, max),
+            ErrorKind::TooSmall { min } => write!(f, "{} must be at least {}", self.field, min),
+            ErrorKind::TooLarge { max } => write!(f, "{} must be at most {}", self.field, max),
+            ErrorKind::PatternMismatch { pattern } => write!(f, "{} must match {}", self.field, pattern)
+        }
+    }
+}
+
+/// Every problem found in a form, in input order followed by missing
+/// fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, FieldError> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self.0.iter().map(|error| error.to_string()).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl Error for ValidationErrors {}
+
+/// A small regular expression dialect for field patterns. The pattern must
+/// match the whole value.
+///
+/// Supported: literal characters, `.`, classes such as `[a-z0-9_]` and
+/// `[^,]`, the shorthands `\d`, `\w` and `\s`, and the repeats `*`, `+`,
+/// `?`, `{n}`, `{n,}` and `{n,m}`. Any other character after `\` is taken
+/// literally. There is no alternation or grouping.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    pieces: Vec<Piece>
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(low, high) => low <= c && c <= high,
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_ascii_alphanumeric() || c == '_',
+            ClassItem::Space => c.is_whitespace()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Atom {
+    Any,
+    Class { negated: bool, items: Vec<ClassItem> }
+}
+
+impl Atom {
+    fn literal(c: char) -> Atom {
+        Atom::Class { negated: false, items: vec![ClassItem::Range(c, c)] }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Class { negated, items } => items.iter().any(|item| item.matches(c)) != *negated
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Piece {
+    atom: Atom,
+    min: usize,
+    max: usize
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pub pattern: String,
+    pub message: String
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern '{}': {}", self.pattern, self.message)
+    }
+}
+
+impl Error for PatternError {}
+
+fn shorthand(c: char) -> Option<ClassItem> {
+    match c {
+        'd' => Some(ClassItem::Digit),
+        'w' => Some(ClassItem::Word),
+        's' => Some(ClassItem::Space),
+        _ => None
+    }
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Pattern, PatternError> {
+        let error = |message: &str| PatternError { pattern: source.to_string(), message: message.to_string() };
+        let mut chars = source.chars().peekable();
+        let mut pieces: Vec<Piece> = Vec::new();
+
+        while let Some(c) = chars.next() {
+            let atom: Atom = match c {
+                '.' => Atom::Any,
+                '\\' => {
+                    let escaped: char = chars.next().ok_or_else(|| error("trailing backslash"))?;
+                    match shorthand(escaped) {
+                        Some(item) => Atom::Class { negated: false, items: vec![item] },
+                        None => Atom::literal(escaped)
+                    }
+                }
+                '[' => {
+                    let negated: bool = chars.next_if_eq(&'^').is_some();
+                    let mut items: Vec<ClassItem> = Vec::new();
+                    loop {
+                        let c: char = chars.next().ok_or_else(|| error("unterminated character class"))?;
+                        let low: char = match c {
+                            ']' if !items.is_empty() => break,
+                            '\\' => {
+                                let escaped: char = chars.next().ok_or_else(|| error("trailing backslash"))?;
+                                if let Some(item) = shorthand(escaped) {
+                                    items.push(item);
+                                    continue;
+                                }
+                                escaped
+                            }
+                            c => c
+                        };
+                        let is_range: bool = chars.peek() == Some(&'-') && {
+                            let mut lookahead = chars.clone();
+                            lookahead.next();
+                            lookahead.peek().is_some_and(|&next| next != ']')
+                        };
+                        if is_range {
+                            chars.next();
+                            let high: char = chars.next().ok_or_else(|| error("unterminated character class"))?;
+                            if high < low {
+                                return Err(error("character range is out of order"));
+                            }
+                            items.push(ClassItem::Range(low, high));
+                        } else {
+                            items.push(ClassItem::Range(low, low));
+                        }
+                    }
+                    Atom::Class { negated, items }
+                }
+                '*' | '+' | '?' | '{' => return Err(error("nothing to repeat")),
+                c => Atom::literal(c)
+            };
+
+            let (min, max) = match chars.peek() {
+                Some(&'*') => (0, usize::MAX),
+                Some(&'+') => (1, usize::MAX),
+                Some(&'?') => (0, 1),
+                Some(&'{') => {
+                    chars.next();
+                    let mut bounds: String = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => bounds.push(c),
+                            None => return Err(error("unterminated repeat"))
+                        }
+                    }
+                    let parse = |s: &str| s.trim().parse::<usize>().map_err(|_| error("invalid repeat bounds"));
+                    let (min, max) = match bounds.split_once(',') {
+                        None => {
+                            let n: usize = parse(&bounds)?;
+                            (n, n)
+                        }
+                        Some((min, max)) if max.trim().is_empty() => (parse(min)?, usize::MAX),
+                        Some((min, max)) => (parse(min)?, parse(max)?)
+                    };
+                    if max < min {
+                        return Err(error("invalid repeat bounds"));
+                    }
+                    pieces.push(Piece { atom, min, max });
+                    continue;
+                }
+                _ => (1, 1)
+            };
+            if (min, max) != (1, 1) {
+                chars.next();
+            }
+            pieces.push(Piece { atom, min, max });
+        }
+
+        Ok(Pattern { source: source.to_string(), pieces })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the whole of `s` matches.
+    pub fn matches(&self, s: &str) -> bool {
+        let chars: Vec<char> = s.chars().collect();
+        match_pieces(&self.pieces, &chars)
+    }
+}
+
+/// Greedy matching with backtracking. Field values are short, so the
+/// worst case doesn't matter here.
+fn match_pieces(pieces: &[Piece], input: &[char]) -> bool {
+    let (piece, rest) = match pieces.split_first() {
+        Some(split) => split,
+        None => return input.is_empty()
+    };
+
+    let mut count: usize = 0;
+    while count < piece.max && count < input.len() && piece.atom.matches(input[count]) {
+        count += 1;
+    }
+    if count < piece.min {
+        return false;
+    }
+
+    (piece.min..=count).rev().any(|taken| match_pieces(rest, &input[taken..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_syntax() {
+        let cases: [(&str, &str, bool); 14] = [
+            ("abc", "abc", true),
+            ("abc", "abcd", false),
+            ("a.c", "a-c", true),
+            ("[a-z]+", "hello", true),
+            ("[a-z]+", "Hello", false),
+            ("[^,]*", "no commas", true),
+            ("[^,]*", "a,b", false),
+            ("\\d{3}-\\d{4}", "555-1234", true),
+            ("\\d{3}-\\d{4}", "55-1234", false),
+            ("\\w{2,}", "a", false),
+            ("colou?r", "color", true),
+            ("a*ab", "aaab", true),
+            ("[a-]+", "a-a", true),
+            ("\\.\\s\\[", ". [", true)
+        ];
+
+        for (pattern, input, expected) in cases.iter() {
+            assert_eq!(Pattern::new(pattern).unwrap().matches(input), *expected, "{} on {}", pattern, input);
+        }
+
+        for invalid in ["*a", "[a-z", "a{2", "a{3,1}", "[z-a]", "a\\"].iter() {
+            assert!(Pattern::new(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_input() {
+        let record: Record = Form::schema().parse("  username = Express Gradient\n\nage=23\nstatus=yes\n").unwrap();
+        assert_eq!(record.text("username"), Some("Express Gradient"));
+        assert_eq!(record.integer("age"), Some(23));
+        assert_eq!(record.boolean("status"), Some(true));
+
+        let record: Record = Form::schema().parse("username=express").unwrap();
+        assert_eq!(record.get("age"), None);
+    }
+
+    #[test]
+    fn test_all_errors_are_reported() {
+        let errors: ValidationErrors = Form::schema()
+            .parse("age=two hundred\nstatus=maybe\nnickname=ex\nage=3\njunk")
+            .unwrap_err();
+
+        let kinds: Vec<(&str, &ErrorKind)> = errors.iter().map(|error| (error.field.as_str(), &error.kind)).collect();
+        assert_eq!(kinds, vec![
+            ("age", &ErrorKind::WrongType { expected: FieldType::Integer }),
+            ("status", &ErrorKind::WrongType { expected: FieldType::Boolean }),
+            ("nickname", &ErrorKind::Unknown),
+            ("age", &ErrorKind::Duplicate),
+            ("line 5", &ErrorKind::Malformed),
+            ("username", &ErrorKind::Missing)
+        ]);
+        assert!(errors.to_string().starts_with("age must be an integer; status must be true or false"));
+    }
+
+    #[test]
+    fn test_constraints() {
+        let schema: Schema = Form::schema();
+        let first_error = |input: &str| schema.parse(input).unwrap_err().0.remove(0).kind;
+
+        assert_eq!(first_error("username=ab"), ErrorKind::TooShort { min: 3 });
+        assert_eq!(first_error(&format!("username={}", "a".repeat(33))), ErrorKind::TooLong { max: 32 });
+        assert!(matches!(first_error("username=9lives"), ErrorKind::PatternMismatch { .. }));
+        assert_eq!(first_error("username=express\nage=-1"), ErrorKind::TooSmall { min: 0 });
+        assert_eq!(first_error("username=express\nage=151"), ErrorKind::TooLarge { max: 150 });
+        assert_eq!(first_error("username="), ErrorKind::Missing);
+    }
+
+    #[test]
+    fn test_parse_into_form_values() {
+        assert_eq!(
+            Form::parse_all("status=off\nusername=Express Gradient").unwrap(),
+            vec![Form::Username(String::from("Express Gradient")), Form::Status(false)]
+        );
+        assert!(Form::parse_all("age=23").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "already has a field called age")]
+    fn test_duplicate_schema_field_panics() {
+        let _ = Schema::new().field(Field::integer("age")).field(Field::integer("age"));
+    }
+}
@@ -0,0 +1,10 @@
+pub mod cache;
+pub mod directory;
+pub mod form;
+pub mod maps;
+pub mod rope;
+pub mod standings;
+pub mod stats;
+pub mod strings;
+pub mod transform;
+pub mod word_freq;
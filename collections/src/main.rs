@@ -1,10 +1,6 @@
 // use std::ops::Add;
-
-enum Form {
-    Username(String),
-    Age(i32),
-    Status(bool)
-}
+use collections::form::Form;
+use collections::strings;
 
 fn main() {
     let _vector: Vec<i32> = Vec::new();
@@ -42,9 +38,16 @@ fn main() {
             Form::Username(String::from("Express Gradient")),
             Form::Status(true)
         ];
+
+        match Form::parse_all("username=Express Gradient\nage=twenty\nstatus=maybe") {
+            Ok(form_elements) => println!("Form: {:?}", form_elements),
+            Err(errors) => println!("Form errors: {}", errors)
+        }
     }
 
     {
+        // Shows replacing a String, so the empty one is never read.
+        #[allow(unused_assignments)]
         let mut some_string: String = String::new();
         let data: &str = "Hello World";
         some_string = data.to_string();
@@ -66,6 +69,10 @@ fn main() {
         let some_string: &str = "String Slice";
         let first_char: &str = &some_string[0..1];
         println!("{}", first_char);
+
+        // `[0..1]` would panic here, since "Ü" takes two bytes.
+        let accented: &str = "Über";
+        println!("{}", strings::substring(accented, 0, 1));
     }
 
     {
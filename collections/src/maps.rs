@@ -0,0 +1,170 @@
+//! Maps the standard library doesn't have: [`MultiMap`] keeps many values
+//! per key in key order, [`BiMap`] looks up in both directions and keeps
+//! both sides unique, and [`IndexMap`] remembers insertion order.
+//!
+//! ```
+//! use collections::maps::{ BiMap, IndexMap, MultiMap };
+//!
+//! let mut teams: MultiMap<&str, &str> = MultiMap::new();
+//! teams.entry("RCB").push("Kohli");
+//! teams.entry("CSK").push("Dhoni");
+//! teams.entry("RCB").push("Maxwell");
+//! assert_eq!(teams.get("RCB"), ["Kohli", "Maxwell"]);
+//!
+//! let mut codes: BiMap<&str, u32> = BiMap::new();
+//! codes.insert("India", 91);
+//! assert_eq!(codes.get_by_value(&91), Some(&"India"));
+//!
+//! let mut scores: IndexMap<&str, i32> = IndexMap::new();
+//! scores.insert("SRH", 22);
+//! scores.insert("RCB", 23);
+//! assert_eq!(scores.keys().collect::<Vec<_>>(), [&"SRH", &"RCB"]);
+//! ```
+//!
+//! Each map writes itself as text with `write_to`, one `key<TAB>value` line
+//! per pair using the `Display` impls, and reads it back with `read_from`
+//! using `FromStr`. Tabs, newlines and backslashes inside keys and values
+//! are escaped, so any string survives the round trip.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::str::FromStr;
+
+mod bimap;
+mod index_map;
+mod multimap;
+
+pub use self::bimap::BiMap;
+pub use self::index_map::{ IndexMap, Iter };
+pub use self::multimap::{ Entry, MultiMap, Pairs };
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// A line that isn't `key<TAB>value` or has a bad escape.
+    Malformed { line: usize },
+    Key { line: usize, text: String },
+    Value { line: usize, text: String },
+    /// A key (or for a `BiMap`, a value) that was already read.
+    Duplicate { line: usize }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(error) => write!(f, "{}", error),
+            ReadError::Malformed { line } => write!(f, "line {} isn't 'key<TAB>value'", line),
+            ReadError::Key { line, text } => write!(f, "line {}: '{}' isn't a valid key", line, text),
+            ReadError::Value { line, text } => write!(f, "line {}: '{}' isn't a valid value", line, text),
+            ReadError::Duplicate { line } => write!(f, "line {} repeats an earlier entry", line)
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> ReadError {
+        ReadError::Io(error)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped: String = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped: String = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            't' => unescaped.push('\t'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None
+        }
+    }
+    Some(unescaped)
+}
+
+fn write_pair<W, K, V>(writer: &mut W, key: &K, value: &V) -> io::Result<()>
+where
+    W: Write,
+    K: fmt::Display,
+    V: fmt::Display
+{
+    writeln!(writer, "{}\t{}", escape(&key.to_string()), escape(&value.to_string()))
+}
+
+/// Parses every `key<TAB>value` line, skipping blank ones, and hands each
+/// pair to `f` along with its line number.
+fn read_pairs<R, K, V, F>(reader: R, mut f: F) -> Result<(), ReadError>
+where
+    R: BufRead,
+    K: FromStr,
+    V: FromStr,
+    F: FnMut(usize, K, V) -> Result<(), ReadError>
+{
+    for (index, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let number: usize = index + 1;
+        if line.is_empty() {
+            continue;
+        }
+
+        let (key, value) = line.split_once('\t').ok_or(ReadError::Malformed { line: number })?;
+        let key: String = unescape(key).ok_or(ReadError::Malformed { line: number })?;
+        let value: String = unescape(value).ok_or(ReadError::Malformed { line: number })?;
+
+        let key: K = key.parse().map_err(|_| ReadError::Key { line: number, text: key.clone() })?;
+        let value: V = value.parse().map_err(|_| ReadError::Value { line: number, text: value.clone() })?;
+        f(number, key, value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_round_trip() {
+        let text: &str = "tab\there\nnew\\line\r";
+        assert_eq!(escape(text), "tab\\there\\nnew\\\\line\\r");
+        assert_eq!(unescape(&escape(text)).as_deref(), Some(text));
+        assert_eq!(unescape("bad\\q"), None);
+        assert_eq!(unescape("trailing\\"), None);
+    }
+
+    #[test]
+    fn test_read_pairs_errors() {
+        let read = |text: &str| read_pairs(text.as_bytes(), |_, _: String, _: i32| Ok(()));
+        assert!(read("a\t1\n\nb\t2\n").is_ok());
+        assert!(matches!(read("a\t1\nb 2"), Err(ReadError::Malformed { line: 2 })));
+        assert!(matches!(read("a\tx"), Err(ReadError::Value { line: 1, .. })));
+        assert!(matches!(read("a\\x\t1"), Err(ReadError::Malformed { line: 1 })));
+    }
+}
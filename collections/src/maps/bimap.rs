@@ -0,0 +1,211 @@
+use super::ReadError;
+use std::collections::btree_map::{ self, BTreeMap };
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+/// A one-to-one map: every key has one value and every value one key, so
+/// either can be looked up from the other. Iterates in key order.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BiMap<K, V> {
+    forward: BTreeMap<K, V>,
+    backward: BTreeMap<V, K>
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> BiMap<K, V> {
+    pub fn new() -> BiMap<K, V> {
+        BiMap { forward: BTreeMap::new(), backward: BTreeMap::new() }
+    }
+
+    /// Inserts the pair, first removing any pair that has the same key or
+    /// the same value. Returns the removed pairs; there can be two.
+    pub fn insert(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let mut removed: Vec<(K, V)> = Vec::new();
+        removed.extend(self.remove_by_key(&key));
+        removed.extend(self.remove_by_value(&value));
+
+        self.forward.insert(key.clone(), value.clone());
+        self.backward.insert(value, key);
+        removed
+    }
+
+    /// Inserts the pair only if neither the key nor the value is taken,
+    /// handing it back otherwise.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if self.forward.contains_key(&key) || self.backward.contains_key(&value) {
+            return Err((key, value));
+        }
+        self.forward.insert(key.clone(), value.clone());
+        self.backward.insert(value, key);
+        Ok(())
+    }
+
+    pub fn get_by_key(&self, key: &K) -> Option<&V> {
+        self.forward.get(key)
+    }
+
+    pub fn get_by_value(&self, value: &V) -> Option<&K> {
+        self.backward.get(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.forward.contains_key(key)
+    }
+
+    pub fn contains_value(&self, value: &V) -> bool {
+        self.backward.contains_key(value)
+    }
+
+    pub fn remove_by_key(&mut self, key: &K) -> Option<(K, V)> {
+        let value: V = self.forward.remove(key)?;
+        let key: K = self.backward.remove(&value).expect("BiMap sides out of sync");
+        Some((key, value))
+    }
+
+    pub fn remove_by_value(&mut self, value: &V) -> Option<(K, V)> {
+        let key: K = self.backward.remove(value)?;
+        let value: V = self.forward.remove(&key).expect("BiMap sides out of sync");
+        Some((key, value))
+    }
+
+    pub fn clear(&mut self) {
+        self.forward.clear();
+        self.backward.clear();
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    where
+        K: fmt::Display,
+        V: fmt::Display
+    {
+        for (key, value) in self {
+            super::write_pair(writer, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a map written by `write_to`, failing on a repeated key or
+    /// value rather than silently dropping a pair.
+    pub fn read_from<R: BufRead>(reader: R) -> Result<BiMap<K, V>, ReadError>
+    where
+        K: FromStr,
+        V: FromStr
+    {
+        let mut map: BiMap<K, V> = BiMap::new();
+        super::read_pairs(reader, |line, key, value| {
+            map.try_insert(key, value).map_err(|_| ReadError::Duplicate { line })
+        })?;
+        Ok(map)
+    }
+}
+
+impl<K, V> BiMap<K, V> {
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty()
+    }
+
+    /// Pairs in key order.
+    pub fn iter(&self) -> btree_map::Iter<'_, K, V> {
+        self.forward.iter()
+    }
+
+    /// Pairs in value order, value first.
+    pub fn iter_by_value(&self) -> btree_map::Iter<'_, V, K> {
+        self.backward.iter()
+    }
+
+    pub fn keys(&self) -> btree_map::Keys<'_, K, V> {
+        self.forward.keys()
+    }
+
+    pub fn values(&self) -> btree_map::Values<'_, K, V> {
+        self.forward.values()
+    }
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> Default for BiMap<K, V> {
+    fn default() -> BiMap<K, V> {
+        BiMap::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for BiMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.forward.iter()).finish()
+    }
+}
+
+/// Later pairs win, as with [`BiMap::insert`].
+impl<K: Ord + Clone, V: Ord + Clone> FromIterator<(K, V)> for BiMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> BiMap<K, V> {
+        let mut map: BiMap<K, V> = BiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> Extend<(K, V)> for BiMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a BiMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = btree_map::Iter<'a, K, V>;
+
+    fn into_iter(self) -> btree_map::Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_both_ways() {
+        let mut map: BiMap<&str, u32> = BiMap::new();
+        assert!(map.insert("India", 91).is_empty());
+        assert!(map.insert("UK", 44).is_empty());
+
+        assert_eq!(map.get_by_key(&"UK"), Some(&44));
+        assert_eq!(map.get_by_value(&91), Some(&"India"));
+        assert!(map.contains_value(&44) && !map.contains_value(&1));
+        assert_eq!(map.iter_by_value().next(), Some((&44, &"UK")));
+    }
+
+    #[test]
+    fn test_insert_keeps_both_sides_unique() {
+        let mut map: BiMap<char, i32> = vec![('a', 1), ('b', 2)].into_iter().collect();
+        let removed: Vec<(char, i32)> = map.insert('a', 2);
+        assert_eq!(removed, vec![('a', 1), ('b', 2)]);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get_by_value(&1), None);
+
+        assert_eq!(map.try_insert('c', 2), Err(('c', 2)));
+        assert_eq!(map.try_insert('a', 3), Err(('a', 3)));
+        assert_eq!(map.try_insert('c', 3), Ok(()));
+        assert_eq!(map.remove_by_value(&3), Some(('c', 3)));
+        assert_eq!(map.remove_by_key(&'c'), None);
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let map: BiMap<String, u16> = vec![(String::from("http"), 80), (String::from("ssh"), 22)].into_iter().collect();
+        let mut text: Vec<u8> = Vec::new();
+        map.write_to(&mut text).unwrap();
+        assert_eq!(text, b"http\t80\nssh\t22\n");
+        assert_eq!(BiMap::read_from(text.as_slice()).unwrap(), map);
+
+        let duplicate = BiMap::<String, u16>::read_from("http\t80\nwww\t80\n".as_bytes());
+        assert!(matches!(duplicate, Err(ReadError::Duplicate { line: 2 })));
+    }
+}
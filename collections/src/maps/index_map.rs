@@ -0,0 +1,302 @@
+use super::ReadError;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{ self, BufRead, Write };
+use std::iter::FromIterator;
+use std::slice;
+use std::str::FromStr;
+
+/// A hash map that iterates in insertion order. Pairs live in a `Vec` and a
+/// `HashMap` points each key at its position, so lookups stay O(1) and
+/// pairs can also be reached by position.
+#[derive(Clone)]
+pub struct IndexMap<K, V> {
+    entries: Vec<(K, V)>,
+    indices: HashMap<K, usize>
+}
+
+impl<K: Hash + Eq + Clone, V> IndexMap<K, V> {
+    pub fn new() -> IndexMap<K, V> {
+        IndexMap { entries: Vec::new(), indices: HashMap::new() }
+    }
+
+    /// Inserts a pair at the end, or replaces the value in place if the key
+    /// is already there, returning the old value.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(&index) = self.indices.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[index].1, value));
+        }
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.indices.get(key).map(|&index| &self.entries[index].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = *self.indices.get(key)?;
+        Some(&mut self.entries[index].1)
+    }
+
+    /// Where `key` sits in insertion order.
+    pub fn index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.indices.get(key).copied()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.indices.contains_key(key)
+    }
+
+    /// Removes a pair and closes the gap, keeping the order of the rest.
+    /// O(n); use [`swap_remove`](IndexMap::swap_remove) when order doesn't
+    /// matter.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+        for (shifted, _) in &self.entries[index..] {
+            *self.indices.get_mut::<K>(shifted).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    /// Removes a pair in O(1) by moving the last pair into its place.
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        let index: usize = self.indices.remove(key)?;
+        let (_, value) = self.entries.swap_remove(index);
+        if let Some((moved, _)) = self.entries.get(index) {
+            *self.indices.get_mut::<K>(moved).unwrap() = index;
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    where
+        K: fmt::Display,
+        V: fmt::Display
+    {
+        for (key, value) in self {
+            super::write_pair(writer, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a map written by `write_to`, keeping the line order and failing
+    /// on a repeated key.
+    pub fn read_from<R: BufRead>(reader: R) -> Result<IndexMap<K, V>, ReadError>
+    where
+        K: FromStr,
+        V: FromStr
+    {
+        let mut map: IndexMap<K, V> = IndexMap::new();
+        super::read_pairs(reader, |line, key: K, value| {
+            if map.contains_key(&key) {
+                return Err(ReadError::Duplicate { line });
+            }
+            map.insert(key, value);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+}
+
+impl<K, V> IndexMap<K, V> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.entries.get(index).map(|(key, value)| (key, value))
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.get_index(0)
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.entries.last().map(|(key, value)| (key, value))
+    }
+
+    /// Pairs in insertion order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { entries: self.entries.iter() }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> + '_ {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Default for IndexMap<K, V> {
+    fn default() -> IndexMap<K, V> {
+        IndexMap::new()
+    }
+}
+
+/// Two maps are equal when they hold the same pairs in the same order.
+impl<K: PartialEq, V: PartialEq> PartialEq for IndexMap<K, V> {
+    fn eq(&self, other: &IndexMap<K, V>) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for IndexMap<K, V> {}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for IndexMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> FromIterator<(K, V)> for IndexMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> IndexMap<K, V> {
+        let mut map: IndexMap<K, V> = IndexMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Extend<(K, V)> for IndexMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a IndexMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<K, V> IntoIterator for IndexMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> std::vec::IntoIter<(K, V)> {
+        self.entries.into_iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    entries: slice::Iter<'a, (K, V)>
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        self.entries.next().map(|(key, value)| (key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        self.entries.next_back().map(|(key, value)| (key, value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_insertion_order() {
+        let mut scores: IndexMap<String, i32> = IndexMap::new();
+        scores.insert(String::from("RCB"), 23);
+        scores.insert(String::from("SRH"), 22);
+        scores.insert(String::from("CSK"), 21);
+        assert_eq!(scores.insert(String::from("SRH"), 23), Some(22));
+
+        let keys: Vec<&str> = scores.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["RCB", "SRH", "CSK"]);
+        assert_eq!(scores.get("SRH"), Some(&23));
+        assert_eq!(scores.index_of("CSK"), Some(2));
+        assert_eq!(scores.get_index(1), Some((&String::from("SRH"), &23)));
+        assert_eq!(scores.iter().next_back().map(|(key, _)| key.as_str()), Some("CSK"));
+    }
+
+    #[test]
+    fn test_remove_and_swap_remove() {
+        let mut map: IndexMap<char, i32> = "abcde".chars().zip(0..).collect();
+        assert_eq!(map.remove(&'b'), Some(1));
+        assert_eq!(map.keys().collect::<String>(), "acde");
+        assert_eq!(map.index_of(&'e'), Some(3));
+
+        assert_eq!(map.swap_remove(&'a'), Some(0));
+        assert_eq!(map.keys().collect::<String>(), "ecd");
+        assert_eq!(map.index_of(&'e'), Some(0));
+        assert_eq!(map.swap_remove(&'d'), Some(3));
+        assert_eq!(map.swap_remove(&'z'), None);
+        assert_eq!(map.len(), 2);
+
+        map.values_mut().for_each(|value| *value *= 10);
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![('e', 40), ('c', 20)]);
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let map: IndexMap<String, f64> = vec![(String::from("z"), 1.5), (String::from("a"), -2.0)].into_iter().collect();
+        let mut text: Vec<u8> = Vec::new();
+        map.write_to(&mut text).unwrap();
+        assert_eq!(text, b"z\t1.5\na\t-2\n");
+        assert_eq!(IndexMap::read_from(text.as_slice()).unwrap(), map);
+
+        let duplicate = IndexMap::<String, f64>::read_from("a\t1\na\t2\n".as_bytes());
+        assert!(matches!(duplicate, Err(ReadError::Duplicate { line: 2 })));
+    }
+}
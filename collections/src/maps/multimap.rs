@@ -0,0 +1,328 @@
+use super::ReadError;
+use std::borrow::Borrow;
+use std::collections::btree_map::{ self, BTreeMap };
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::iter::FromIterator;
+use std::slice;
+use std::str::FromStr;
+
+/// Many values per key. Keys iterate in order and each key's values stay in
+/// the order they were added. A key is present exactly when it has at least
+/// one value.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MultiMap<K, V> {
+    map: BTreeMap<K, Vec<V>>,
+    len: usize
+}
+
+impl<K: Ord, V> MultiMap<K, V> {
+    pub fn new() -> MultiMap<K, V> {
+        MultiMap { map: BTreeMap::new(), len: 0 }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entry(key).push(value);
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        Entry { entry: self.map.entry(key), len: &mut self.len }
+    }
+
+    /// The values for `key`, empty if there are none.
+    pub fn get<Q>(&self, key: &Q) -> &[V]
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Removes and returns every value for `key`.
+    pub fn remove<Q>(&mut self, key: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        let values: Vec<V> = self.map.remove(key).unwrap_or_default();
+        self.len -= values.len();
+        values
+    }
+
+    /// Removes the first occurrence of `value` under `key`, dropping the key
+    /// if it was the last one.
+    pub fn remove_value<Q>(&mut self, key: &Q, value: &V) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        V: PartialEq
+    {
+        let values: &mut Vec<V> = match self.map.get_mut(key) {
+            Some(values) => values,
+            None => return false
+        };
+        let position: usize = match values.iter().position(|existing| existing == value) {
+            Some(position) => position,
+            None => return false
+        };
+
+        values.remove(position);
+        if values.is_empty() {
+            self.map.remove(key);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// Keeps only the pairs `f` returns true for.
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        let mut len: usize = 0;
+        self.map.retain(|key, values| {
+            values.retain(|value| f(key, value));
+            len += values.len();
+            !values.is_empty()
+        });
+        self.len = len;
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.len = 0;
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    where
+        K: fmt::Display,
+        V: fmt::Display
+    {
+        for (key, value) in self {
+            super::write_pair(writer, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a map written by `write_to`. Repeated keys are expected; that's
+    /// how several values are stored.
+    pub fn read_from<R: BufRead>(reader: R) -> Result<MultiMap<K, V>, ReadError>
+    where
+        K: FromStr,
+        V: FromStr
+    {
+        let mut map: MultiMap<K, V> = MultiMap::new();
+        super::read_pairs(reader, |_, key, value| {
+            map.insert(key, value);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+}
+
+impl<K, V> MultiMap<K, V> {
+    /// Total number of values, across all keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn keys_len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Every key-value pair, by key and then insertion order.
+    pub fn iter(&self) -> Pairs<'_, K, V> {
+        Pairs { groups: self.map.iter(), current: None, remaining: self.len }
+    }
+
+    /// Each key with all its values.
+    pub fn groups(&self) -> btree_map::Iter<'_, K, Vec<V>> {
+        self.map.iter()
+    }
+
+    pub fn keys(&self) -> btree_map::Keys<'_, K, Vec<V>> {
+        self.map.keys()
+    }
+}
+
+impl<K: Ord, V> Default for MultiMap<K, V> {
+    fn default() -> MultiMap<K, V> {
+        MultiMap::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for MultiMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.map.iter()).finish()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for MultiMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> MultiMap<K, V> {
+        let mut map: MultiMap<K, V> = MultiMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for MultiMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a MultiMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Pairs<'a, K, V>;
+
+    fn into_iter(self) -> Pairs<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// A key's place in a [`MultiMap`], whether or not it has values yet.
+pub struct Entry<'a, K, V> {
+    entry: btree_map::Entry<'a, K, Vec<V>>,
+    len: &'a mut usize
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        self.entry.key()
+    }
+
+    /// The values already stored, empty for a new key.
+    pub fn values(&self) -> &[V] {
+        match &self.entry {
+            btree_map::Entry::Occupied(entry) => entry.get(),
+            btree_map::Entry::Vacant(_) => &[]
+        }
+    }
+
+    /// Adds a value and returns all of the key's values.
+    pub fn push(self, value: V) -> &'a mut [V] {
+        *self.len += 1;
+        let values: &'a mut Vec<V> = self.entry.or_default();
+        values.push(value);
+        values
+    }
+
+    /// Adds several values. A new key with no values isn't stored.
+    pub fn extend<I: IntoIterator<Item = V>>(self, values: I) -> &'a mut [V] {
+        let mut values = values.into_iter().peekable();
+        if values.peek().is_none() {
+            return match self.entry {
+                btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                btree_map::Entry::Vacant(_) => &mut []
+            };
+        }
+
+        let existing: &'a mut Vec<V> = self.entry.or_default();
+        let before: usize = existing.len();
+        existing.extend(values);
+        *self.len += existing.len() - before;
+        existing
+    }
+}
+
+pub struct Pairs<'a, K, V> {
+    groups: btree_map::Iter<'a, K, Vec<V>>,
+    current: Option<(&'a K, slice::Iter<'a, V>)>,
+    remaining: usize
+}
+
+impl<'a, K, V> Iterator for Pairs<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            if let Some((key, values)) = &mut self.current {
+                if let Some(value) = values.next() {
+                    self.remaining -= 1;
+                    return Some((*key, value));
+                }
+            }
+            let (key, values) = self.groups.next()?;
+            self.current = Some((key, values.iter()));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Pairs<'a, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_accumulates() {
+        let text: &str = "hello world wonderful world";
+        let mut positions: MultiMap<&str, usize> = MultiMap::new();
+        for (i, word) in text.split_whitespace().enumerate() {
+            positions.entry(word).push(i);
+        }
+
+        assert_eq!(positions.get("world"), [1, 3]);
+        assert!(positions.get("nothing").is_empty());
+        assert_eq!((positions.len(), positions.keys_len()), (4, 3));
+        assert_eq!(positions.entry("hello").values(), [0]);
+        assert!(positions.entry("new").extend(Vec::new()).is_empty());
+        assert!(!positions.contains_key("new"));
+        assert_eq!(positions.entry("new").extend(vec![7, 8]), [7, 8]);
+        assert_eq!(positions.len(), 6);
+    }
+
+    #[test]
+    fn test_iterates_by_key_then_insertion() {
+        let map: MultiMap<char, i32> = vec![('b', 2), ('a', 9), ('b', 1), ('a', 3)].into_iter().collect();
+        let pairs: Vec<(char, i32)> = map.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(pairs, vec![('a', 9), ('a', 3), ('b', 2), ('b', 1)]);
+        assert_eq!(map.iter().len(), 4);
+        assert_eq!(format!("{:?}", map), "{'a': [9, 3], 'b': [2, 1]}");
+    }
+
+    #[test]
+    fn test_removal() {
+        let mut map: MultiMap<&str, i32> = vec![("a", 1), ("a", 2), ("b", 3), ("c", 4)].into_iter().collect();
+        assert!(map.remove_value("a", &1));
+        assert!(!map.remove_value("a", &1));
+        assert!(map.remove_value("b", &3));
+        assert!(!map.contains_key("b"));
+        assert_eq!(map.remove("c"), vec![4]);
+        assert_eq!(map.len(), 1);
+
+        map.extend(vec![("d", 5), ("d", 6)]);
+        map.retain(|_, &value| value % 2 == 0);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"a", &2), (&"d", &6)]);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let map: MultiMap<String, String> = vec![
+            (String::from("greeting"), String::from("hello\tworld")),
+            (String::from("greeting"), String::from("hi")),
+            (String::from("path"), String::from("C:\\temp"))
+        ].into_iter().collect();
+
+        let mut text: Vec<u8> = Vec::new();
+        map.write_to(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text.clone()).unwrap(), "greeting\thello\\tworld\ngreeting\thi\npath\tC:\\\\temp\n");
+        assert_eq!(MultiMap::read_from(text.as_slice()).unwrap(), map);
+    }
+}
@@ -0,0 +1,697 @@
+//! A rope: a string stored as a balanced tree of small chunks, so editing
+//! in the middle of a large text costs O(log n) instead of the O(n) copy a
+//! `String` needs.
+//!
+//! ```
+//! use collections::rope::Rope;
+//!
+//! let mut rope: Rope = Rope::from("Hello World\nsecond line");
+//! rope.insert(5, ",");
+//! rope.remove(6..12);
+//! assert_eq!(rope, "Hello,\nsecond line");
+//! assert_eq!(rope.len_lines(), 2);
+//! assert_eq!(rope.line(1), "second line");
+//! assert_eq!(rope.slice(0..5).to_string(), "Hello");
+//! ```
+//!
+//! Every index is a char index, not a byte index, and lines are split on
+//! `\n`. Nodes are shared between ropes, so cloning and slicing are cheap
+//! and never copy the text.
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+#[cfg(not(test))]
+const MAX_LEAF_BYTES: usize = 1024;
+// Small leaves make the tests build deep trees from short strings.
+#[cfg(test)]
+const MAX_LEAF_BYTES: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Metrics {
+    bytes: usize,
+    chars: usize,
+    newlines: usize
+}
+
+impl Metrics {
+    fn of(text: &str) -> Metrics {
+        Metrics {
+            bytes: text.len(),
+            chars: text.chars().count(),
+            newlines: text.bytes().filter(|&byte| byte == b'\n').count()
+        }
+    }
+
+    fn add(self, other: Metrics) -> Metrics {
+        Metrics {
+            bytes: self.bytes + other.bytes,
+            chars: self.chars + other.chars,
+            newlines: self.newlines + other.newlines
+        }
+    }
+}
+
+enum Node {
+    Leaf {
+        text: String,
+        metrics: Metrics
+    },
+    /// An AVL node: the heights of the two sides differ by at most one.
+    Branch {
+        left: Arc<Node>,
+        right: Arc<Node>,
+        metrics: Metrics,
+        height: usize
+    }
+}
+
+impl Node {
+    fn metrics(&self) -> Metrics {
+        match self {
+            Node::Leaf { metrics, .. } | Node::Branch { metrics, .. } => *metrics
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Node::Leaf { .. } => 0,
+            Node::Branch { height, .. } => *height
+        }
+    }
+
+    fn children(&self) -> (&Arc<Node>, &Arc<Node>) {
+        match self {
+            Node::Branch { left, right, .. } => (left, right),
+            Node::Leaf { .. } => unreachable!("a leaf has no children")
+        }
+    }
+}
+
+fn leaf(text: String) -> Arc<Node> {
+    let metrics: Metrics = Metrics::of(&text);
+    Arc::new(Node::Leaf { text, metrics })
+}
+
+fn branch(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+    Arc::new(Node::Branch {
+        metrics: left.metrics().add(right.metrics()),
+        height: 1 + left.height().max(right.height()),
+        left,
+        right
+    })
+}
+
+/// Joins two balanced subtrees whose heights differ by at most two, rotating
+/// to restore the balance.
+fn balance(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+    if left.height() > right.height() + 1 {
+        let (outer, inner) = left.children();
+        if outer.height() >= inner.height() {
+            branch(outer.clone(), branch(inner.clone(), right))
+        } else {
+            let (inner_left, inner_right) = inner.children();
+            branch(branch(outer.clone(), inner_left.clone()), branch(inner_right.clone(), right))
+        }
+    } else if right.height() > left.height() + 1 {
+        let (inner, outer) = right.children();
+        if outer.height() >= inner.height() {
+            branch(branch(left, inner.clone()), outer.clone())
+        } else {
+            let (inner_left, inner_right) = inner.children();
+            branch(branch(left, inner_left.clone()), branch(inner_right.clone(), outer.clone()))
+        }
+    } else {
+        branch(left, right)
+    }
+}
+
+/// Concatenates two trees in O(|height difference|) by walking down the
+/// taller one's spine until the heights match. Adjacent small leaves are
+/// merged so repeated small edits don't leave a trail of tiny chunks.
+fn join(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+    if left.metrics().bytes == 0 {
+        return right;
+    }
+    if right.metrics().bytes == 0 {
+        return left;
+    }
+
+    if let (Node::Leaf { text: left_text, .. }, Node::Leaf { text: right_text, .. }) = (&*left, &*right) {
+        if left_text.len() + right_text.len() <= MAX_LEAF_BYTES {
+            return leaf(format!("{}{}", left_text, right_text));
+        }
+    }
+
+    if left.height() > right.height() + 1 {
+        let (left_left, left_right) = left.children();
+        balance(left_left.clone(), join(left_right.clone(), right))
+    } else if right.height() > left.height() + 1 {
+        let (right_left, right_right) = right.children();
+        balance(join(left, right_left.clone()), right_right.clone())
+    } else {
+        branch(left, right)
+    }
+}
+
+/// Splits a tree into the first `at` chars and the rest.
+fn split(node: &Arc<Node>, at: usize) -> (Arc<Node>, Arc<Node>) {
+    match &**node {
+        Node::Leaf { text, metrics } => {
+            if at == 0 {
+                return (leaf(String::new()), node.clone());
+            }
+            if at == metrics.chars {
+                return (node.clone(), leaf(String::new()));
+            }
+            let byte: usize = byte_offset(text, at);
+            (leaf(text[..byte].to_string()), leaf(text[byte..].to_string()))
+        }
+        Node::Branch { left, right, .. } => {
+            let left_chars: usize = left.metrics().chars;
+            if at <= left_chars {
+                let (left_left, left_right) = split(left, at);
+                (left_left, join(left_right, right.clone()))
+            } else {
+                let (right_left, right_right) = split(right, at - left_chars);
+                (join(left.clone(), right_left), right_right)
+            }
+        }
+    }
+}
+
+fn byte_offset(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(byte, _)| byte)
+}
+
+/// Builds a perfectly balanced tree over chunks of `text`.
+fn build(text: &str) -> Arc<Node> {
+    let mut leaves: Vec<Arc<Node>> = Vec::new();
+    let mut rest: &str = text;
+    while !rest.is_empty() {
+        let mut end: usize = rest.len().min(MAX_LEAF_BYTES);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        leaves.push(leaf(rest[..end].to_string()));
+        rest = &rest[end..];
+    }
+    if leaves.is_empty() {
+        return leaf(String::new());
+    }
+
+    fn build_balanced(leaves: &[Arc<Node>]) -> Arc<Node> {
+        if leaves.len() == 1 {
+            return leaves[0].clone();
+        }
+        let (left, right) = leaves.split_at(leaves.len() / 2);
+        branch(build_balanced(left), build_balanced(right))
+    }
+    build_balanced(&leaves)
+}
+
+#[derive(Clone)]
+pub struct Rope {
+    root: Arc<Node>
+}
+
+impl Rope {
+    pub fn new() -> Rope {
+        Rope { root: leaf(String::new()) }
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.root.metrics().bytes
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.root.metrics().chars
+    }
+
+    /// One more than the number of `\n`s, so an empty rope has one (empty)
+    /// line and so does the end of a rope that ends in a newline.
+    pub fn len_lines(&self) -> usize {
+        self.root.metrics().newlines + 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len_bytes() == 0
+    }
+
+    /// Inserts `text` before the char at `char_index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_index` is past the end.
+    pub fn insert(&mut self, char_index: usize, text: &str) {
+        self.assert_char_index(char_index);
+        if text.is_empty() {
+            return;
+        }
+        let (before, after) = split(&self.root, char_index);
+        self.root = join(join(before, build(text)), after);
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.insert(self.len_chars(), text);
+    }
+
+    /// Appends another rope in O(log n), sharing its nodes.
+    pub fn append(&mut self, other: &Rope) {
+        self.root = join(self.root.clone(), other.root.clone());
+    }
+
+    /// Removes a range of chars.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is backwards or past the end.
+    pub fn remove(&mut self, range: Range<usize>) {
+        self.assert_range(&range);
+        let (before, rest) = split(&self.root, range.start);
+        let (_, after) = split(&rest, range.end - range.start);
+        self.root = join(before, after);
+    }
+
+    /// Splits the rope in two, keeping the first `char_index` chars and
+    /// returning the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_index` is past the end.
+    pub fn split_off(&mut self, char_index: usize) -> Rope {
+        self.assert_char_index(char_index);
+        let (before, after) = split(&self.root, char_index);
+        self.root = before;
+        Rope { root: after }
+    }
+
+    /// A new rope holding a range of chars, sharing nodes with this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is backwards or past the end.
+    pub fn slice(&self, range: Range<usize>) -> Rope {
+        self.assert_range(&range);
+        let (before, _) = split(&self.root, range.end);
+        let (_, slice) = split(&before, range.start);
+        Rope { root: slice }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `char_index` is not less than `len_chars`.
+    pub fn char(&self, char_index: usize) -> char {
+        assert!(char_index < self.len_chars(), "char index {} out of range for rope of {} chars", char_index, self.len_chars());
+        let mut node: &Node = &self.root;
+        let mut index: usize = char_index;
+        loop {
+            match node {
+                Node::Leaf { text, .. } => return text.chars().nth(index).unwrap(),
+                Node::Branch { left, right, .. } => {
+                    let left_chars: usize = left.metrics().chars;
+                    if index < left_chars {
+                        node = left;
+                    } else {
+                        index -= left_chars;
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The line containing the char at `char_index`, counting from zero. The
+    /// end of the rope counts as being on the last line.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_index` is past the end.
+    pub fn char_to_line(&self, char_index: usize) -> usize {
+        self.assert_char_index(char_index);
+        let mut node: &Node = &self.root;
+        let mut index: usize = char_index;
+        let mut line: usize = 0;
+        loop {
+            match node {
+                Node::Leaf { text, .. } => return line + text.chars().take(index).filter(|&c| c == '\n').count(),
+                Node::Branch { left, right, .. } => {
+                    let left_metrics: Metrics = left.metrics();
+                    if index <= left_metrics.chars {
+                        node = left;
+                    } else {
+                        index -= left_metrics.chars;
+                        line += left_metrics.newlines;
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The char index where line `line_index` starts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_index` is not less than `len_lines`.
+    pub fn line_to_char(&self, line_index: usize) -> usize {
+        assert!(line_index < self.len_lines(), "line index {} out of range for rope of {} lines", line_index, self.len_lines());
+        let mut node: &Node = &self.root;
+        let mut newlines: usize = line_index;
+        let mut start: usize = 0;
+        while newlines > 0 {
+            match node {
+                Node::Leaf { text, .. } => {
+                    let (position, _) = text.chars().enumerate().filter(|&(_, c)| c == '\n').nth(newlines - 1).unwrap();
+                    return start + position + 1;
+                }
+                Node::Branch { left, right, .. } => {
+                    let left_metrics: Metrics = left.metrics();
+                    if newlines <= left_metrics.newlines {
+                        node = left;
+                    } else {
+                        newlines -= left_metrics.newlines;
+                        start += left_metrics.chars;
+                        node = right;
+                    }
+                }
+            }
+        }
+        start
+    }
+
+    /// Line `line_index` without its `\n` or `\r\n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line_index` is not less than `len_lines`.
+    pub fn line(&self, line_index: usize) -> Rope {
+        let start: usize = self.line_to_char(line_index);
+        let mut end: usize = if line_index + 1 < self.len_lines() {
+            self.line_to_char(line_index + 1) - 1
+        } else {
+            self.len_chars()
+        };
+        if end > start && self.char(end - 1) == '\r' && end < self.len_chars() {
+            end -= 1;
+        }
+        self.slice(start..end)
+    }
+
+    /// The text in order, a chunk at a time, without copying.
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks { stack: vec![&*self.root] }
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.chunks().flat_map(str::chars)
+    }
+
+    /// Lines as [`str::lines`] splits them: without their line endings and
+    /// with no empty line after a final newline.
+    pub fn lines(&self) -> Lines<'_> {
+        Lines { chunks: self.chunks(), current: "" }
+    }
+
+    fn assert_char_index(&self, char_index: usize) {
+        assert!(char_index <= self.len_chars(), "char index {} out of range for rope of {} chars", char_index, self.len_chars());
+    }
+
+    fn assert_range(&self, range: &Range<usize>) {
+        assert!(range.start <= range.end, "range {:?} is backwards", range);
+        self.assert_char_index(range.end);
+    }
+}
+
+impl Default for Rope {
+    fn default() -> Rope {
+        Rope::new()
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Rope {
+        Rope { root: build(text) }
+    }
+}
+
+impl From<String> for Rope {
+    fn from(text: String) -> Rope {
+        Rope::from(text.as_str())
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rope({:?})", self.to_string())
+    }
+}
+
+impl PartialEq for Rope {
+    fn eq(&self, other: &Rope) -> bool {
+        self.len_bytes() == other.len_bytes() && self.chars().eq(other.chars())
+    }
+}
+
+impl Eq for Rope {}
+
+impl PartialEq<str> for Rope {
+    fn eq(&self, other: &str) -> bool {
+        self.len_bytes() == other.len() && self.chars().eq(other.chars())
+    }
+}
+
+impl PartialEq<&str> for Rope {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<String> for Rope {
+    fn eq(&self, other: &String) -> bool {
+        *self == *other.as_str()
+    }
+}
+
+pub struct Chunks<'a> {
+    stack: Vec<&'a Node>
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                Node::Leaf { text, .. } if !text.is_empty() => return Some(text),
+                Node::Leaf { .. } => {}
+                Node::Branch { left, right, .. } => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+            }
+        }
+        None
+    }
+}
+
+pub struct Lines<'a> {
+    chunks: Chunks<'a>,
+    /// What's left of the chunk being read.
+    current: &'a str
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mut line: String = String::new();
+        let mut started: bool = false;
+        loop {
+            if self.current.is_empty() {
+                match self.chunks.next() {
+                    Some(chunk) => self.current = chunk,
+                    None => return if started { Some(line) } else { None }
+                }
+            }
+            started = true;
+
+            match self.current.find('\n') {
+                Some(end) => {
+                    line.push_str(&self.current[..end]);
+                    self.current = &self.current[end + 1..];
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                    return Some(line);
+                }
+                None => {
+                    line.push_str(self.current);
+                    self.current = "";
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the AVL balance and the cached metrics, returning the height.
+    fn check(node: &Node) -> usize {
+        match node {
+            Node::Leaf { text, metrics } => {
+                assert_eq!(*metrics, Metrics::of(text));
+                assert!(text.len() <= MAX_LEAF_BYTES);
+                0
+            }
+            Node::Branch { left, right, metrics, height } => {
+                let (left_height, right_height) = (check(left), check(right));
+                assert!(left_height.abs_diff(right_height) <= 1, "unbalanced: {} vs {}", left_height, right_height);
+                assert_eq!(*height, 1 + left_height.max(right_height));
+                assert_eq!(*metrics, left.metrics().add(right.metrics()));
+                *height
+            }
+        }
+    }
+
+    /// xorshift, so the property tests are random but repeatable.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn text(&mut self) -> String {
+            const PIECES: [&str; 8] = ["a", "bc", "\n", "é", "日本", "\r\n", "xyz ", "🦀"];
+            (0..self.below(6)).map(|_| PIECES[self.below(PIECES.len())]).collect()
+        }
+    }
+
+    fn byte_range(text: &str, chars: Range<usize>) -> Range<usize> {
+        byte_offset(text, chars.start)..byte_offset(text, chars.end)
+    }
+
+    #[test]
+    fn test_basic_editing() {
+        let mut rope: Rope = Rope::from("The quick brown fox");
+        rope.insert(4, "very ");
+        rope.remove(14..20);
+        rope.push_str(" jumps");
+        assert_eq!(rope, "The very quick fox jumps");
+        assert_eq!(rope.char(4), 'v');
+
+        let tail: Rope = rope.split_off(14);
+        assert_eq!(rope, "The very quick");
+        assert_eq!(tail, " fox jumps");
+        rope.append(&tail);
+        assert_eq!(rope.to_string(), "The very quick fox jumps");
+        check(&rope.root);
+    }
+
+    #[test]
+    fn test_lines() {
+        let rope: Rope = Rope::from("first\r\nsecond\n\nlast\n");
+        assert_eq!(rope.len_lines(), 5);
+        assert_eq!(rope.line(0), "first");
+        assert_eq!(rope.line(2), "");
+        assert_eq!(rope.line(4), "");
+        assert_eq!(rope.line_to_char(1), 7);
+        assert_eq!(rope.char_to_line(7), 1);
+        assert_eq!(rope.char_to_line(rope.len_chars()), 4);
+        assert_eq!(rope.lines().collect::<Vec<_>>(), vec!["first", "second", "", "last"]);
+        assert_eq!(Rope::new().lines().count(), 0);
+        assert_eq!(Rope::new().len_lines(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_insert_past_end_panics() {
+        Rope::from("abc").insert(4, "d");
+    }
+
+    #[test]
+    fn test_slices_share_nodes() {
+        let text: String = "line of text\n".repeat(1000);
+        let rope: Rope = Rope::from(text.as_str());
+        let slice: Rope = rope.slice(13..26);
+        assert_eq!(slice, "line of text\n");
+        assert!(check(&rope.root) <= 12);
+
+        // Appending a sequence of single chars stays balanced and merges
+        // leaves.
+        let mut typed: Rope = Rope::new();
+        for c in text.chars().take(500) {
+            typed.push_str(&c.to_string());
+        }
+        assert!(check(&typed.root) <= 8);
+        assert_eq!(typed, &text[..500]);
+    }
+
+    #[test]
+    fn test_matches_string_under_random_edits() {
+        let mut rng: Rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..20 {
+            let mut model: String = rng.text();
+            let mut rope: Rope = Rope::from(model.as_str());
+
+            for _ in 0..200 {
+                let chars: usize = model.chars().count();
+                match rng.below(3) {
+                    0 | 1 => {
+                        let at: usize = rng.below(chars + 1);
+                        let text: String = rng.text();
+                        model.insert_str(byte_offset(&model, at), &text);
+                        rope.insert(at, &text);
+                    }
+                    _ => {
+                        let start: usize = rng.below(chars + 1);
+                        let end: usize = start + rng.below(chars - start + 1).min(8);
+                        model.replace_range(byte_range(&model, start..end), "");
+                        rope.remove(start..end);
+                    }
+                }
+
+                assert_eq!(rope, model);
+                check(&rope.root);
+            }
+
+            let chars: usize = model.chars().count();
+            assert_eq!(rope.len_chars(), chars);
+            assert_eq!(rope.len_lines(), model.split('\n').count());
+            assert_eq!(rope.lines().collect::<Vec<_>>(), model.lines().collect::<Vec<_>>());
+            assert_eq!(rope.chunks().collect::<String>(), model);
+
+            for _ in 0..20 {
+                let start: usize = rng.below(chars + 1);
+                let end: usize = start + rng.below(chars - start + 1);
+                assert_eq!(rope.slice(start..end), &model[byte_range(&model, start..end)]);
+
+                let line: usize = rope.char_to_line(start);
+                assert_eq!(line, model.chars().take(start).filter(|&c| c == '\n').count());
+                assert!(rope.line_to_char(line) <= start);
+            }
+            let lines: Vec<&str> = model.split('\n').collect();
+            for (index, &expected) in lines.iter().enumerate() {
+                // Only a line that ends in a newline loses a trailing \r.
+                let expected: &str = if index + 1 < lines.len() { expected.strip_suffix('\r').unwrap_or(expected) } else { expected };
+                assert_eq!(rope.line(index), expected);
+            }
+        }
+    }
+}
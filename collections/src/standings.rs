@@ -0,0 +1,682 @@
+//! Cricket league standings: points, net run rate and ranking.
+//!
+//! ```
+//! use collections::standings::{ Fixture, League };
+//!
+//! let mut league: League = League::new(20);
+//! league.record(&"RCB,SRH,182/4,20,160/8,20,home".parse::<Fixture>().unwrap());
+//!
+//! let table = league.table();
+//! assert_eq!(table[0].team, "RCB");
+//! assert_eq!(table[0].points, 2);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::str::FromStr;
+
+const BALLS_PER_OVER: u32 = 6;
+const WICKETS: u32 = 10;
+const CSV_HEADER: &str = "home,away,home_score,home_overs,away_score,away_overs,result";
+
+/// One side's batting: runs, wickets and legal balls faced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Innings {
+    pub runs: u32,
+    pub wickets: u32,
+    pub balls: u32
+}
+
+impl Innings {
+    pub fn is_all_out(&self) -> bool {
+        self.wickets >= WICKETS
+    }
+
+    /// Overs in cricket notation, e.g. `19.4` for 118 balls.
+    pub fn overs(&self) -> String {
+        format_overs(self.balls)
+    }
+}
+
+fn format_overs(balls: u32) -> String {
+    match balls % BALLS_PER_OVER {
+        0 => format!("{}", balls / BALLS_PER_OVER),
+        extra => format!("{}.{}", balls / BALLS_PER_OVER, extra)
+    }
+}
+
+fn parse_overs(s: &str) -> Result<u32, String> {
+    let (overs, balls) = match s.split_once('.') {
+        Some((overs, balls)) => (overs, balls),
+        None => (s, "0")
+    };
+    let overs: u32 = overs.parse().map_err(|_| format!("invalid overs '{}'", s))?;
+    let balls: u32 = balls.parse().map_err(|_| format!("invalid overs '{}'", s))?;
+    if balls >= BALLS_PER_OVER {
+        return Err(format!("invalid overs '{}': at most {} balls after the point", s, BALLS_PER_OVER - 1));
+    }
+    overs
+        .checked_mul(BALLS_PER_OVER)
+        .and_then(|whole| whole.checked_add(balls))
+        .ok_or_else(|| format!("invalid overs '{}': too many balls", s))
+}
+
+/// Quotes a CSV field when it wouldn't read back as itself: it has a comma
+/// or quote, surrounding spaces that would be trimmed, or a leading `#` that
+/// would start a comment.
+fn quote_field(field: &str) -> String {
+    let plain: bool = !field.contains([',', '"']) && field.trim() == field && !field.starts_with('#');
+    if plain {
+        field.to_string()
+    } else {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }
+}
+
+/// Splits a CSV row into fields, unquoting quoted ones and trimming the rest.
+fn split_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut rest: &str = line;
+
+    loop {
+        let trimmed: &str = rest.trim_start();
+        if let Some(quoted) = trimmed.strip_prefix('"') {
+            let mut field: String = String::new();
+            let mut chars = quoted.char_indices();
+            let end: usize = loop {
+                match chars.next() {
+                    Some((i, '"')) if quoted[i + 1..].starts_with('"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    Some((i, '"')) => break i + 1,
+                    Some((_, c)) => field.push(c),
+                    None => return Err(String::from("unterminated quoted field"))
+                }
+            };
+            fields.push(field);
+
+            let after: &str = quoted[end..].trim_start();
+            match after.strip_prefix(',') {
+                Some(next) => rest = next,
+                None if after.is_empty() => return Ok(fields),
+                None => return Err(String::from("unexpected text after a quoted field"))
+            }
+        } else {
+            match rest.split_once(',') {
+                Some((field, next)) => {
+                    fields.push(field.trim().to_string());
+                    rest = next;
+                }
+                None => {
+                    fields.push(rest.trim().to_string());
+                    return Ok(fields);
+                }
+            }
+        }
+    }
+}
+
+fn parse_score(s: &str) -> Result<(u32, u32), String> {
+    let (runs, wickets) = match s.split_once('/') {
+        Some((runs, wickets)) => (runs, wickets),
+        None => (s, "0")
+    };
+    let runs: u32 = runs.parse().map_err(|_| format!("invalid score '{}'", s))?;
+    let wickets: u32 = wickets.parse().map_err(|_| format!("invalid score '{}'", s))?;
+    if wickets > WICKETS {
+        return Err(format!("invalid score '{}': more than {} wickets", s, WICKETS));
+    }
+    Ok((runs, wickets))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    HomeWin,
+    AwayWin,
+    Tie,
+    NoResult
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::HomeWin => "home",
+            Outcome::AwayWin => "away",
+            Outcome::Tie => "tie",
+            Outcome::NoResult => "no result"
+        }
+    }
+}
+
+/// A match between two teams. `outcome` is `None` until it has been played.
+///
+/// The outcome is recorded separately from the scores, since super overs and
+/// rain rules can decide a match the scores alone don't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    pub home: String,
+    pub away: String,
+    pub home_innings: Option<Innings>,
+    pub away_innings: Option<Innings>,
+    pub outcome: Option<Outcome>
+}
+
+impl Fixture {
+    pub fn new(home: &str, away: &str) -> Fixture {
+        Fixture {
+            home: home.to_string(),
+            away: away.to_string(),
+            home_innings: None,
+            away_innings: None,
+            outcome: None
+        }
+    }
+
+    /// Returns the fixture as a CSV row in the format `read_fixtures` accepts.
+    pub fn to_csv(&self) -> String {
+        let innings = |innings: &Option<Innings>| match innings {
+            Some(innings) => (format!("{}/{}", innings.runs, innings.wickets), innings.overs()),
+            None => (String::new(), String::new())
+        };
+        let (home_score, home_overs) = innings(&self.home_innings);
+        let (away_score, away_overs) = innings(&self.away_innings);
+
+        format!(
+            "{},{},{},{},{},{},{}",
+            quote_field(&self.home),
+            quote_field(&self.away),
+            home_score,
+            home_overs,
+            away_score,
+            away_overs,
+            self.outcome.map_or("", |outcome| outcome.as_str())
+        )
+    }
+}
+
+impl FromStr for Fixture {
+    type Err = String;
+
+    /// Parses one CSV row: `home,away,home_score,home_overs,away_score,away_overs,result`.
+    ///
+    /// Fields may be wrapped in double quotes, with `""` for a quote inside,
+    /// so team names can contain commas.
+    ///
+    /// Scores are `runs/wickets` or just `runs`, overs use cricket notation
+    /// (`19.4`), and the result is `home`, `away`, `tie`, `no result` or
+    /// empty for a match that hasn't been played. Scores may be left empty
+    /// for an unplayed or abandoned match.
+    fn from_str(line: &str) -> Result<Fixture, String> {
+        let fields: Vec<String> = split_fields(line)?;
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        if fields.len() != 7 {
+            return Err(format!("expected 7 fields, found {}", fields.len()));
+        }
+        if fields[0].is_empty() || fields[1].is_empty() {
+            return Err(String::from("team names can't be empty"));
+        }
+        if fields[0] == fields[1] {
+            return Err(format!("{} can't play itself", fields[0]));
+        }
+
+        let innings = |score: &str, overs: &str| -> Result<Option<Innings>, String> {
+            match (score, overs) {
+                ("", "") => Ok(None),
+                ("", _) | (_, "") => Err(String::from("a score needs both runs and overs")),
+                (score, overs) => {
+                    let (runs, wickets) = parse_score(score)?;
+                    Ok(Some(Innings { runs, wickets, balls: parse_overs(overs)? }))
+                }
+            }
+        };
+
+        let outcome: Option<Outcome> = match fields[6].to_ascii_lowercase().as_str() {
+            "" => None,
+            "home" => Some(Outcome::HomeWin),
+            "away" => Some(Outcome::AwayWin),
+            "tie" => Some(Outcome::Tie),
+            "no result" | "nr" => Some(Outcome::NoResult),
+            other => return Err(format!("unknown result '{}'", other))
+        };
+
+        let fixture: Fixture = Fixture {
+            home: fields[0].to_string(),
+            away: fields[1].to_string(),
+            home_innings: innings(fields[2], fields[3])?,
+            away_innings: innings(fields[4], fields[5])?,
+            outcome
+        };
+
+        let decided: bool = matches!(fixture.outcome, Some(Outcome::HomeWin) | Some(Outcome::AwayWin) | Some(Outcome::Tie));
+        if decided && (fixture.home_innings.is_none() || fixture.away_innings.is_none()) {
+            return Err(String::from("a decided match needs both scores"));
+        }
+
+        Ok(fixture)
+    }
+}
+
+#[derive(Debug)]
+pub enum FixtureError {
+    Io(io::Error),
+    /// A malformed row, with its 1-based line number.
+    Parse { line: usize, message: String }
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureError::Io(error) => write!(f, "{}", error),
+            FixtureError::Parse { line, message } => write!(f, "line {}: {}", line, message)
+        }
+    }
+}
+
+impl Error for FixtureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FixtureError::Io(error) => Some(error),
+            FixtureError::Parse { .. } => None
+        }
+    }
+}
+
+impl From<io::Error> for FixtureError {
+    fn from(error: io::Error) -> FixtureError {
+        FixtureError::Io(error)
+    }
+}
+
+/// Reads fixtures from CSV. The header row is optional, and blank lines and
+/// lines starting with `#` are skipped.
+pub fn read_fixtures<R: BufRead>(reader: R) -> Result<Vec<Fixture>, FixtureError> {
+    let mut fixtures: Vec<Fixture> = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let trimmed: &str = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || (index == 0 && trimmed == CSV_HEADER) {
+            continue;
+        }
+
+        let fixture: Fixture = trimmed
+            .parse()
+            .map_err(|message| FixtureError::Parse { line: index + 1, message })?;
+        fixtures.push(fixture);
+    }
+
+    Ok(fixtures)
+}
+
+pub fn write_fixtures<W: Write>(writer: &mut W, fixtures: &[Fixture]) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)?;
+    for fixture in fixtures {
+        writeln!(writer, "{}", fixture.to_csv())?;
+    }
+    Ok(())
+}
+
+/// Points awarded for each kind of result. The default is the IPL's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointsRule {
+    pub win: u32,
+    pub tie: u32,
+    pub no_result: u32,
+    pub loss: u32
+}
+
+impl Default for PointsRule {
+    fn default() -> PointsRule {
+        PointsRule { win: 2, tie: 1, no_result: 1, loss: 0 }
+    }
+}
+
+/// A team's line in the table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Standing {
+    pub team: String,
+    pub played: u32,
+    pub won: u32,
+    pub lost: u32,
+    pub tied: u32,
+    pub no_result: u32,
+    pub points: u32,
+    pub runs_for: u32,
+    pub balls_faced: u32,
+    pub runs_against: u32,
+    pub balls_bowled: u32
+}
+
+impl Standing {
+    /// Runs scored per over minus runs conceded per over, or 0 before any
+    /// match with a result.
+    pub fn net_run_rate(&self) -> f64 {
+        if self.balls_faced == 0 || self.balls_bowled == 0 {
+            return 0.0;
+        }
+        let per_over = |runs: u32, balls: u32| runs as f64 * BALLS_PER_OVER as f64 / balls as f64;
+        per_over(self.runs_for, self.balls_faced) - per_over(self.runs_against, self.balls_bowled)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct League {
+    overs: u32,
+    rule: PointsRule,
+    standings: HashMap<String, Standing>,
+    /// Points each team took off each opponent, for head-to-head tie-breaks.
+    head_to_head: HashMap<(String, String), u32>
+}
+
+impl League {
+    /// A league whose matches are `overs` a side.
+    pub fn new(overs: u32) -> League {
+        League::with_points(overs, PointsRule::default())
+    }
+
+    pub fn with_points(overs: u32, rule: PointsRule) -> League {
+        League {
+            overs,
+            rule,
+            standings: HashMap::new(),
+            head_to_head: HashMap::new()
+        }
+    }
+
+    /// Adds a team with no matches, so it shows up in the table before it
+    /// has played.
+    pub fn add_team(&mut self, team: &str) {
+        self.entry(team);
+    }
+
+    fn entry(&mut self, team: &str) -> &mut Standing {
+        self.standings.entry(team.to_string()).or_insert_with(|| Standing {
+            team: team.to_string(),
+            ..Standing::default()
+        })
+    }
+
+    /// Records a played fixture. Returns `false`, and changes nothing, if
+    /// it hasn't been played yet.
+    pub fn record(&mut self, fixture: &Fixture) -> bool {
+        let outcome: Outcome = match fixture.outcome {
+            Some(outcome) => outcome,
+            None => {
+                self.add_team(&fixture.home);
+                self.add_team(&fixture.away);
+                return false;
+            }
+        };
+
+        let (home_points, away_points) = match outcome {
+            Outcome::HomeWin => (self.rule.win, self.rule.loss),
+            Outcome::AwayWin => (self.rule.loss, self.rule.win),
+            Outcome::Tie => (self.rule.tie, self.rule.tie),
+            Outcome::NoResult => (self.rule.no_result, self.rule.no_result)
+        };
+
+        // Overs only count towards net run rate when the match had a result.
+        // A side bowled out is charged its full quota of overs.
+        let quota: u32 = self.overs * BALLS_PER_OVER;
+        let charged = |innings: &Innings| if innings.is_all_out() { quota } else { innings.balls };
+        let innings: Option<(Innings, Innings)> = match (outcome, fixture.home_innings, fixture.away_innings) {
+            (Outcome::NoResult, _, _) => None,
+            (_, Some(home), Some(away)) => Some((home, away)),
+            _ => None
+        };
+
+        for &(team, opponent, points, won, lost) in [
+            (&fixture.home, &fixture.away, home_points, outcome == Outcome::HomeWin, outcome == Outcome::AwayWin),
+            (&fixture.away, &fixture.home, away_points, outcome == Outcome::AwayWin, outcome == Outcome::HomeWin)
+        ].iter() {
+            let standing: &mut Standing = self.entry(team);
+            standing.played += 1;
+            standing.points += points;
+            if won {
+                standing.won += 1;
+            } else if lost {
+                standing.lost += 1;
+            } else if outcome == Outcome::Tie {
+                standing.tied += 1;
+            } else {
+                standing.no_result += 1;
+            }
+
+            if let Some((home, away)) = innings {
+                let (batting, bowling) = if team == &fixture.home { (home, away) } else { (away, home) };
+                // Saturate rather than wrap on absurd inputs, like the overs
+                // check does when parsing.
+                standing.runs_for = standing.runs_for.saturating_add(batting.runs);
+                standing.balls_faced = standing.balls_faced.saturating_add(charged(&batting));
+                standing.runs_against = standing.runs_against.saturating_add(bowling.runs);
+                standing.balls_bowled = standing.balls_bowled.saturating_add(charged(&bowling));
+            }
+
+            *self.head_to_head.entry((team.clone(), opponent.clone())).or_insert(0) += points;
+        }
+
+        true
+    }
+
+    pub fn standing(&self, team: &str) -> Option<&Standing> {
+        self.standings.get(team)
+    }
+
+    /// The table, best first.
+    ///
+    /// Teams are ranked by points, then wins, then net run rate. Teams still
+    /// level after that are ranked by the points they took off each other,
+    /// and finally by name.
+    pub fn table(&self) -> Vec<Standing> {
+        let mut table: Vec<Standing> = self.standings.values().cloned().collect();
+        table.sort_by(|a, b| {
+            b.points
+                .cmp(&a.points)
+                .then(b.won.cmp(&a.won))
+                .then(b.net_run_rate().total_cmp(&a.net_run_rate()))
+                .then(a.team.cmp(&b.team))
+        });
+
+        let mut start: usize = 0;
+        while start < table.len() {
+            let mut end: usize = start + 1;
+            while end < table.len() && self.level(&table[start], &table[end]) {
+                end += 1;
+            }
+            if end - start > 1 {
+                self.break_tie(&mut table[start..end]);
+            }
+            start = end;
+        }
+
+        table
+    }
+
+    fn level(&self, a: &Standing, b: &Standing) -> bool {
+        a.points == b.points && a.won == b.won && a.net_run_rate() == b.net_run_rate()
+    }
+
+    /// Orders teams that are level on everything else by a mini-league of
+    /// the matches between them.
+    fn break_tie(&self, group: &mut [Standing]) {
+        let teams: Vec<String> = group.iter().map(|standing| standing.team.clone()).collect();
+        let mini_league = |team: &str| -> u32 {
+            teams
+                .iter()
+                .filter(|opponent| opponent.as_str() != team)
+                .map(|opponent| {
+                    self.head_to_head
+                        .get(&(team.to_string(), opponent.clone()))
+                        .copied()
+                        .unwrap_or(0)
+                })
+                .sum()
+        };
+
+        group.sort_by(|a, b| mini_league(&b.team).cmp(&mini_league(&a.team)).then(a.team.cmp(&b.team)));
+    }
+
+    /// Writes the table as aligned text.
+    pub fn write_table<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{:>3}  {:<12} {:>3} {:>3} {:>3} {:>3} {:>3} {:>4} {:>7}", "#", "Team", "P", "W", "L", "T", "NR", "Pts", "NRR")?;
+        for (position, standing) in self.table().iter().enumerate() {
+            writeln!(
+                writer,
+                "{:>3}  {:<12} {:>3} {:>3} {:>3} {:>3} {:>3} {:>4} {:>+7.3}",
+                position + 1,
+                standing.team,
+                standing.played,
+                standing.won,
+                standing.lost,
+                standing.tied,
+                standing.no_result,
+                standing.points,
+                standing.net_run_rate()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn league_from(rows: &[&str]) -> League {
+        let mut league: League = League::new(20);
+        for row in rows {
+            league.record(&row.parse().unwrap());
+        }
+        league
+    }
+
+    #[test]
+    fn test_points_and_results() {
+        let league: League = league_from(&[
+            "RCB,SRH,182/4,20,160/8,20,home",
+            "SRH,CSK,150/6,20,150/9,20,tie",
+            "CSK,RCB,,,,,no result"
+        ]);
+
+        let rcb: &Standing = league.standing("RCB").unwrap();
+        assert_eq!((rcb.played, rcb.won, rcb.no_result, rcb.points), (2, 1, 1, 3));
+        let srh: &Standing = league.standing("SRH").unwrap();
+        assert_eq!((srh.played, srh.lost, srh.tied, srh.points), (2, 1, 1, 1));
+        let csk: &Standing = league.standing("CSK").unwrap();
+        assert_eq!((csk.tied, csk.no_result, csk.points), (1, 1, 2));
+
+        let huge: League = league_from(&["RCB,SRH,4294967295,20,1,20,home", "RCB,SRH,4294967295,20,1,20,home"]);
+        assert_eq!(huge.standing("RCB").unwrap().runs_for, u32::MAX);
+    }
+
+    #[test]
+    fn test_net_run_rate_charges_full_quota_when_all_out() {
+        // SRH are bowled out in 15 overs but are charged all 20.
+        let league: League = league_from(&["RCB,SRH,200/5,20,100/10,15,home"]);
+
+        let rcb: &Standing = league.standing("RCB").unwrap();
+        assert_eq!(rcb.balls_bowled, 120);
+        assert!((rcb.net_run_rate() - 5.0).abs() < 1e-9);
+        assert!((league.standing("SRH").unwrap().net_run_rate() + 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_overs_notation() {
+        let fixture: Fixture = "RCB,SRH,170/3,19.4,169/7,20,home".parse().unwrap();
+        assert_eq!(fixture.home_innings.unwrap().balls, 118);
+        assert_eq!(fixture.home_innings.unwrap().overs(), "19.4");
+
+        assert!("RCB,SRH,170/3,19.6,169/7,20,home".parse::<Fixture>().is_err());
+        assert!("RCB,SRH,170/3,715827882.4,169/7,20,home".parse::<Fixture>().is_err());
+        assert!("RCB,SRH,170/11,20,169/7,20,home".parse::<Fixture>().is_err());
+        assert!("RCB,RCB,170,20,169,20,home".parse::<Fixture>().is_err());
+    }
+
+    #[test]
+    fn test_ranking_uses_net_run_rate_then_head_to_head() {
+        // Three teams on one win each, separated by run rate.
+        let league: League = league_from(&[
+            "RCB,SRH,160/5,20,150/5,20,home",
+            "SRH,CSK,140/5,20,139/5,20,home",
+            "CSK,RCB,200/5,20,100/5,20,home"
+        ]);
+        let order: Vec<String> = league.table().into_iter().map(|s| s.team).collect();
+        assert_eq!(order, vec!["CSK", "SRH", "RCB"]);
+
+        // Identical run rates and a cycle of results, so the name decides.
+        let league: League = league_from(&[
+            "RCB,SRH,150/5,20,160/5,20,away",
+            "CSK,RCB,150/5,20,160/5,20,away",
+            "SRH,CSK,150/5,20,160/5,20,away"
+        ]);
+        let table: Vec<Standing> = league.table();
+        assert_eq!(table[0].team, "CSK");
+        assert_eq!(table[0].net_run_rate(), table[1].net_run_rate());
+    }
+
+    #[test]
+    fn test_head_to_head_breaks_exact_tie() {
+        let mut league: League = League::new(20);
+        league.record(&"SRH,RCB,160/5,20,150/5,20,home".parse().unwrap());
+        league.record(&"RCB,SRH,160/5,20,150/5,20,home".parse().unwrap());
+        league.record(&"CSK,RCB,,,,,no result".parse().unwrap());
+        league.record(&"CSK,SRH,,,,,no result".parse().unwrap());
+        league.record(&"RCB,SRH,,,,,no result".parse().unwrap());
+
+        // RCB and SRH are level on points, wins and run rate, and so are
+        // their head-to-head points, so the name decides.
+        let order: Vec<String> = league.table().into_iter().map(|s| s.team).collect();
+        assert_eq!(order, vec!["RCB", "SRH", "CSK"]);
+
+        let mut league: League = League::new(20);
+        league.record(&"SRH,RCB,160/5,20,150/5,20,home".parse().unwrap());
+        league.record(&"RCB,CSK,160/5,20,150/5,20,home".parse().unwrap());
+        league.record(&"SRH,CSK,,,,,no result".parse().unwrap());
+        league.record(&"RCB,CSK,,,,,no result".parse().unwrap());
+        league.record(&"CSK,SRH,160/5,20,150/5,20,home".parse().unwrap());
+        // RCB and SRH are level on everything but SRH won their match.
+        let order: Vec<String> = league.table().into_iter().map(|s| s.team).collect();
+        assert_eq!(order, vec!["CSK", "SRH", "RCB"]);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let csv: &str = "\
+home,away,home_score,home_overs,away_score,away_overs,result
+RCB,SRH,182/4,20,160/8,20,home
+# rained off
+SRH,CSK,,,,,no result
+CSK,RCB,,,,,
+";
+        let fixtures: Vec<Fixture> = read_fixtures(csv.as_bytes()).unwrap();
+        assert_eq!(fixtures.len(), 3);
+        assert_eq!(fixtures[2].outcome, None);
+
+        let mut written: Vec<u8> = Vec::new();
+        write_fixtures(&mut written, &fixtures).unwrap();
+        assert_eq!(read_fixtures(written.as_slice()).unwrap(), fixtures);
+
+        let awkward: Vec<Fixture> = vec![Fixture::new("Kings, Punjab", "\"Royals\""), Fixture::new("#1 ", "Titans")];
+        let mut written: Vec<u8> = Vec::new();
+        write_fixtures(&mut written, &awkward).unwrap();
+        assert!(String::from_utf8(written.clone()).unwrap().contains("\"Kings, Punjab\",\"\"\"Royals\"\"\""));
+        assert_eq!(read_fixtures(written.as_slice()).unwrap(), awkward);
+        assert!("\"RCB,SRH,,,,,".parse::<Fixture>().is_err());
+
+        match read_fixtures("RCB,SRH,1,1,1,1,maybe\n".as_bytes()) {
+            Err(FixtureError::Parse { line, .. }) => assert_eq!(line, 1),
+            other => panic!("expected a parse error, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_unplayed_fixture_adds_teams_only() {
+        let mut league: League = League::new(20);
+        assert!(!league.record(&Fixture::new("RCB", "CSK")));
+        assert_eq!(league.table().len(), 2);
+        assert_eq!(league.standing("RCB").unwrap().played, 0);
+    }
+}
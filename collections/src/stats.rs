@@ -0,0 +1,677 @@
+//! Descriptive statistics over integer and float slices, plus one-pass
+//! versions for inputs too large to hold in memory.
+//!
+//! ```
+//! use collections::stats;
+//!
+//! let nums: Vec<i32> = vec![2, 3, 3, 5, 7, 10];
+//! assert_eq!(stats::mean(&nums), Some(5.0));
+//! assert_eq!(stats::median(&nums), Some(4.0));
+//! assert_eq!(stats::modes(&nums), vec![3]);
+//! assert_eq!(stats::percentile(&nums, 50.0), Some(4.0));
+//! ```
+//!
+//! NaN values in float input are ignored by every function.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{ self, BufRead };
+
+/// A number the statistics functions accept.
+pub trait Number: Copy + PartialEq + fmt::Debug {
+    fn to_f64(self) -> f64;
+    fn is_nan(self) -> bool;
+    /// A total order, so floats can be sorted.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! impl_number_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn is_nan(self) -> bool {
+                    false
+                }
+
+                fn total_cmp(&self, other: &Self) -> Ordering {
+                    self.cmp(other)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_number_for_float {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn is_nan(self) -> bool {
+                    <$t>::is_nan(self)
+                }
+
+                fn total_cmp(&self, other: &Self) -> Ordering {
+                    <$t>::total_cmp(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_number_for_integer!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+impl_number_for_float!(f32, f64);
+
+fn sorted<T: Number>(values: &[T]) -> Vec<T> {
+    let mut sorted: Vec<T> = values.iter().copied().filter(|value| !value.is_nan()).collect();
+    sorted.sort_by(Number::total_cmp);
+    sorted
+}
+
+pub fn mean<T: Number>(values: &[T]) -> Option<f64> {
+    let mut stats: OnlineStats = OnlineStats::new();
+    values.iter().for_each(|value| stats.add(value.to_f64()));
+    stats.mean()
+}
+
+/// The middle value, or the mean of the two middle values for an even count.
+pub fn median<T: Number>(values: &[T]) -> Option<f64> {
+    percentile(values, 50.0)
+}
+
+/// Every value that occurs most often, in ascending order. Empty for empty
+/// input.
+pub fn modes<T: Number>(values: &[T]) -> Vec<T> {
+    let sorted: Vec<T> = sorted(values);
+    let mut modes: Vec<T> = Vec::new();
+    let mut best: usize = 0;
+
+    let mut start: usize = 0;
+    while start < sorted.len() {
+        let mut end: usize = start + 1;
+        while end < sorted.len() && sorted[end] == sorted[start] {
+            end += 1;
+        }
+        let run: usize = end - start;
+        match run.cmp(&best) {
+            Ordering::Greater => {
+                best = run;
+                modes.clear();
+                modes.push(sorted[start]);
+            }
+            Ordering::Equal => modes.push(sorted[start]),
+            Ordering::Less => {}
+        }
+        start = end;
+    }
+
+    modes
+}
+
+/// The `p`th percentile, interpolating linearly between the closest ranks
+/// (the same method as Excel's `PERCENTILE.INC` and NumPy's default).
+///
+/// # Panics
+///
+/// Panics if `p` is outside 0 to 100.
+pub fn percentile<T: Number>(values: &[T], p: f64) -> Option<f64> {
+    assert!((0.0..=100.0).contains(&p), "Percentiles go from 0 to 100, got {}", p);
+    let sorted: Vec<T> = sorted(values);
+    percentile_of_sorted(&sorted, p)
+}
+
+fn percentile_of_sorted<T: Number>(sorted: &[T], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank: f64 = p / 100.0 * (sorted.len() - 1) as f64;
+    let below: usize = rank.floor() as usize;
+    let above: usize = rank.ceil() as usize;
+    let (low, high) = (sorted[below].to_f64(), sorted[above].to_f64());
+    Some(low + (high - low) * (rank - below as f64))
+}
+
+/// Population variance: the mean squared distance from the mean.
+pub fn variance<T: Number>(values: &[T]) -> Option<f64> {
+    let mut stats: OnlineStats = OnlineStats::new();
+    values.iter().for_each(|value| stats.add(value.to_f64()));
+    stats.variance()
+}
+
+/// Population standard deviation.
+pub fn std_dev<T: Number>(values: &[T]) -> Option<f64> {
+    variance(values).map(f64::sqrt)
+}
+
+/// Sample standard deviation, dividing by `n - 1`. Needs at least two
+/// values.
+pub fn sample_std_dev<T: Number>(values: &[T]) -> Option<f64> {
+    let mut stats: OnlineStats = OnlineStats::new();
+    values.iter().for_each(|value| stats.add(value.to_f64()));
+    stats.sample_variance().map(f64::sqrt)
+}
+
+/// One bar of a histogram. `end` is exclusive except for the last bucket,
+/// which also counts values equal to the maximum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize
+}
+
+/// Splits the range from the smallest to the largest value into `buckets`
+/// equal-width buckets and counts the values in each.
+///
+/// # Panics
+///
+/// Panics if `buckets` is 0.
+pub fn histogram<T: Number>(values: &[T], buckets: usize) -> Vec<Bucket> {
+    assert!(buckets > 0, "A histogram needs at least one bucket");
+    let sorted: Vec<T> = sorted(values);
+    let (min, max) = match (sorted.first(), sorted.last()) {
+        (Some(min), Some(max)) => (min.to_f64(), max.to_f64()),
+        _ => return Vec::new()
+    };
+
+    let mut histogram: StreamingHistogram = StreamingHistogram::new(min, max, buckets);
+    sorted.iter().for_each(|value| histogram.add(value.to_f64()));
+    histogram.buckets
+}
+
+/// Everything above in one go, for printing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary<T> {
+    pub count: usize,
+    pub min: T,
+    pub max: T,
+    pub mean: f64,
+    pub median: f64,
+    pub modes: Vec<T>,
+    pub std_dev: f64,
+    pub p25: f64,
+    pub p75: f64
+}
+
+/// `None` if there are no (non-NaN) values.
+pub fn summarize<T: Number>(values: &[T]) -> Option<Summary<T>> {
+    let sorted: Vec<T> = sorted(values);
+    let (&min, &max) = (sorted.first()?, sorted.last()?);
+
+    Some(Summary {
+        count: sorted.len(),
+        min,
+        max,
+        mean: mean(&sorted)?,
+        median: percentile_of_sorted(&sorted, 50.0)?,
+        modes: modes(&sorted),
+        std_dev: std_dev(&sorted)?,
+        p25: percentile_of_sorted(&sorted, 25.0)?,
+        p75: percentile_of_sorted(&sorted, 75.0)?
+    })
+}
+
+/// Count, mean, variance, minimum and maximum in one pass and constant
+/// memory, using Welford's algorithm so the variance doesn't lose precision
+/// on large inputs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OnlineStats {
+    count: usize,
+    mean: f64,
+    /// Sum of squared distances from the running mean.
+    m2: f64,
+    min: f64,
+    max: f64
+}
+
+impl OnlineStats {
+    pub fn new() -> OnlineStats {
+        OnlineStats::default()
+    }
+
+    /// Adds a value. NaN is ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+
+        self.count += 1;
+        let delta: f64 = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.mean) }
+    }
+
+    pub fn variance(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.m2 / self.count as f64) }
+    }
+
+    pub fn sample_variance(&self) -> Option<f64> {
+        if self.count < 2 { None } else { Some(self.m2 / (self.count - 1) as f64) }
+    }
+
+    pub fn std_dev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.min) }
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        if self.count == 0 { None } else { Some(self.max) }
+    }
+}
+
+/// Estimates one percentile in constant memory with the P² algorithm
+/// (Jain and Chlamtac, 1985). Exact for the first five values, and usually
+/// within a fraction of a percent after that on smooth distributions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantileEstimator {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5]
+}
+
+impl QuantileEstimator {
+    /// # Panics
+    ///
+    /// Panics if `percentile` is outside 0 to 100.
+    pub fn new(percentile: f64) -> QuantileEstimator {
+        assert!((0.0..=100.0).contains(&percentile), "Percentiles go from 0 to 100, got {}", percentile);
+        let p: f64 = percentile / 100.0;
+
+        QuantileEstimator {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0]
+        }
+    }
+
+    /// Adds a value. NaN is ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        let cell: usize = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (1..5).find(|&i| value < self.heights[i]).unwrap() - 1
+        };
+
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments.iter()) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let offset: f64 = self.desired[i] - self.positions[i];
+            let room_above: bool = self.positions[i + 1] - self.positions[i] > 1.0;
+            let room_below: bool = self.positions[i - 1] - self.positions[i] < -1.0;
+            if (offset >= 1.0 && room_above) || (offset <= -1.0 && room_below) {
+                let step: f64 = offset.signum();
+                let parabolic: f64 = self.parabolic(i, step);
+                self.heights[i] = if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                    parabolic
+                } else {
+                    self.linear(i, step)
+                };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, step: f64) -> f64 {
+        let j: usize = if step > 0.0 { i + 1 } else { i - 1 };
+        self.heights[i] + step * (self.heights[j] - self.heights[i]) / (self.positions[j] - self.positions[i])
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The current estimate, or `None` before any values.
+    pub fn estimate(&self) -> Option<f64> {
+        if self.count < 5 {
+            let mut seen: Vec<f64> = self.heights[..self.count].to_vec();
+            seen.sort_by(f64::total_cmp);
+            return percentile_of_sorted(&seen, self.p * 100.0);
+        }
+        Some(self.heights[2])
+    }
+}
+
+/// A histogram over a range fixed in advance, so it can be filled in one
+/// pass. Values outside the range are counted separately.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingHistogram {
+    min: f64,
+    max: f64,
+    buckets: Vec<Bucket>,
+    below: usize,
+    above: usize
+}
+
+impl StreamingHistogram {
+    /// # Panics
+    ///
+    /// Panics if `buckets` is 0 or `max < min`.
+    pub fn new(min: f64, max: f64, buckets: usize) -> StreamingHistogram {
+        assert!(buckets > 0, "A histogram needs at least one bucket");
+        assert!(min <= max, "The histogram range is empty: {} > {}", min, max);
+
+        let width: f64 = (max - min) / buckets as f64;
+        StreamingHistogram {
+            min,
+            max,
+            buckets: (0..buckets)
+                .map(|i| Bucket {
+                    start: min + width * i as f64,
+                    end: if i + 1 == buckets { max } else { min + width * (i + 1) as f64 },
+                    count: 0
+                })
+                .collect(),
+            below: 0,
+            above: 0
+        }
+    }
+
+    /// Adds a value. NaN is ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if value < self.min {
+            self.below += 1;
+        } else if value > self.max {
+            self.above += 1;
+        } else {
+            let width: f64 = self.max - self.min;
+            let index: usize = if width == 0.0 {
+                0
+            } else {
+                (((value - self.min) / width) * self.buckets.len() as f64) as usize
+            };
+            let last: usize = self.buckets.len() - 1;
+            self.buckets[index.min(last)].count += 1;
+        }
+    }
+
+    pub fn buckets(&self) -> &[Bucket] {
+        &self.buckets
+    }
+
+    /// Values below and above the range.
+    pub fn outliers(&self) -> (usize, usize) {
+        (self.below, self.above)
+    }
+}
+
+/// Counts integer values in one pass to find the modes. Memory grows with
+/// the number of distinct values, not the number of values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModeCounter {
+    counts: HashMap<i64, usize>
+}
+
+impl ModeCounter {
+    pub fn new() -> ModeCounter {
+        ModeCounter::default()
+    }
+
+    pub fn add(&mut self, value: i64) {
+        *self.counts.entry(value).or_insert(0) += 1;
+    }
+
+    /// Every most frequent value, in ascending order.
+    pub fn modes(&self) -> Vec<i64> {
+        let best: usize = match self.counts.values().max() {
+            Some(&best) => best,
+            None => return Vec::new()
+        };
+        let mut modes: Vec<i64> = self.counts
+            .iter()
+            .filter(|(_, &count)| count == best)
+            .map(|(&value, _)| value)
+            .collect();
+        modes.sort_unstable();
+        modes
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// A token that isn't a number, with its 1-based line number.
+    Parse { line: usize, token: String }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(error) => write!(f, "{}", error),
+            ReadError::Parse { line, token } => write!(f, "line {}: '{}' is not a number", line, token)
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Io(error) => Some(error),
+            ReadError::Parse { .. } => None
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> ReadError {
+        ReadError::Io(error)
+    }
+}
+
+/// Reads numbers separated by whitespace or commas and hands each one to
+/// `f`, a line at a time, so the input never has to fit in memory. Lines
+/// starting with `#` are skipped.
+pub fn for_each_number<R, F>(reader: R, mut f: F) -> Result<(), ReadError>
+where
+    R: BufRead,
+    F: FnMut(f64)
+{
+    for (index, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for token in line.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()) {
+            let value: f64 = token.parse().map_err(|_| ReadError::Parse {
+                line: index + 1,
+                token: token.to_string()
+            })?;
+            f(value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_central_tendency() {
+        let nums: Vec<i32> = vec![1, 2, 3, 4];
+        assert_eq!(mean(&nums), Some(2.5));
+        assert_eq!(median(&nums), Some(2.5));
+        assert_eq!(median(&[5, 1, 3]), Some(3.0));
+        assert_eq!(mean::<i32>(&[]), None);
+        assert_eq!(median::<f64>(&[]), None);
+
+        assert_eq!(modes(&[1, 2, 2, 3, 3]), vec![2, 3]);
+        assert_eq!(modes(&[4.5, 1.0, 4.5, f64::NAN, f64::NAN]), vec![4.5]);
+        assert!(modes::<u8>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_percentiles_interpolate() {
+        let nums: Vec<i64> = (1..=10).collect();
+        assert_eq!(percentile(&nums, 0.0), Some(1.0));
+        assert_eq!(percentile(&nums, 100.0), Some(10.0));
+        assert!(close(percentile(&nums, 25.0).unwrap(), 3.25));
+        assert!(close(percentile(&nums, 90.0).unwrap(), 9.1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Percentiles go from 0 to 100")]
+    fn test_percentile_out_of_range_panics() {
+        percentile(&[1, 2, 3], 101.0);
+    }
+
+    #[test]
+    fn test_spread() {
+        let nums: Vec<f32> = vec![2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert!(close(std_dev(&nums).unwrap(), 2.0));
+        assert!(close(sample_std_dev(&nums).unwrap(), (32.0f64 / 7.0).sqrt()));
+        assert_eq!(sample_std_dev(&[1]), None);
+        assert_eq!(variance(&[f64::NAN, 3.0]), Some(0.0));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let buckets: Vec<Bucket> = histogram(&[0, 1, 2, 5, 9, 10], 2);
+        assert_eq!(buckets, vec![
+            Bucket { start: 0.0, end: 5.0, count: 3 },
+            Bucket { start: 5.0, end: 10.0, count: 3 }
+        ]);
+        assert_eq!(histogram(&[7, 7], 3)[0].count, 2);
+        assert!(histogram::<i32>(&[], 3).is_empty());
+
+        let mut streaming: StreamingHistogram = StreamingHistogram::new(0.0, 1.0, 4);
+        for value in [-1.0, 0.0, 0.3, 0.99, 1.0, 2.0].iter() {
+            streaming.add(*value);
+        }
+        let counts: Vec<usize> = streaming.buckets().iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, vec![1, 1, 0, 2]);
+        assert_eq!(streaming.outliers(), (1, 1));
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary: Summary<i32> = summarize(&[3, 1, 2, 2]).unwrap();
+        assert_eq!((summary.count, summary.min, summary.max), (4, 1, 3));
+        assert_eq!(summary.modes, vec![2]);
+        assert_eq!(summary.median, 2.0);
+        assert!(summarize::<f64>(&[f64::NAN]).is_none());
+    }
+
+    #[test]
+    fn test_online_stats_match_batch() {
+        let values: Vec<f64> = (0..1000).map(|i| ((i * 7919) % 1000) as f64 / 10.0 + 1e9).collect();
+        let mut online: OnlineStats = OnlineStats::new();
+        values.iter().for_each(|&value| online.add(value));
+
+        assert_eq!(online.count(), 1000);
+        assert!((online.mean().unwrap() - mean(&values).unwrap()).abs() < 1e-6);
+        // A naive sum-of-squares variance loses everything at an offset of 1e9.
+        let expected: f64 = values.iter().map(|v| (v - 1e9 - 49.95).powi(2)).sum::<f64>() / 1000.0;
+        assert!((online.variance().unwrap() - expected).abs() < 1e-3);
+        assert_eq!(online.min(), Some(1e9));
+    }
+
+    #[test]
+    fn test_quantile_estimator() {
+        let mut median: QuantileEstimator = QuantileEstimator::new(50.0);
+        assert_eq!(median.estimate(), None);
+        for value in [5.0, 1.0, 3.0].iter() {
+            median.add(*value);
+        }
+        assert_eq!(median.estimate(), Some(3.0));
+
+        // A shuffled 0..10000 should land close to the exact answers.
+        let values: Vec<f64> = (0..10_000).map(|i| ((i * 7919) % 10_000) as f64).collect();
+        for &p in [10.0, 50.0, 90.0, 99.0].iter() {
+            let mut estimator: QuantileEstimator = QuantileEstimator::new(p);
+            values.iter().for_each(|&value| estimator.add(value));
+            let exact: f64 = percentile(&values, p).unwrap();
+            assert!((estimator.estimate().unwrap() - exact).abs() < 100.0, "p{}: {:?} vs {}", p, estimator.estimate(), exact);
+        }
+    }
+
+    #[test]
+    fn test_mode_counter() {
+        let mut counter: ModeCounter = ModeCounter::new();
+        for value in [3, -1, 3, -1, 2].iter() {
+            counter.add(*value);
+        }
+        assert_eq!(counter.modes(), vec![-1, 3]);
+        assert!(ModeCounter::new().modes().is_empty());
+    }
+
+    #[test]
+    fn test_for_each_number() {
+        let mut values: Vec<f64> = Vec::new();
+        for_each_number("1, 2 3\n# comment\n\n4.5,-6e1".as_bytes(), |value| values.push(value)).unwrap();
+        assert_eq!(values, vec![1.0, 2.0, 3.0, 4.5, -60.0]);
+
+        match for_each_number("1\n2 x".as_bytes(), |_| {}) {
+            Err(ReadError::Parse { line, token }) => assert_eq!((line, token.as_str()), (2, "x")),
+            other => panic!("expected a parse error, got {:?}", other)
+        }
+    }
+}
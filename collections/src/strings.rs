@@ -0,0 +1,328 @@
+//! String helpers that work in characters and grapheme clusters, so they
+//! never slice through the middle of a code point.
+//!
+//! `&s[0..1]` indexes bytes and panics on "é" or "🦀". These functions
+//! count characters or grapheme clusters (what a reader sees as one
+//! character, such as "é" written as `e` plus a combining accent, or a flag
+//! emoji) and clamp out-of-range indices instead of panicking.
+//!
+//! ```
+//! use collections::strings;
+//!
+//! assert_eq!(strings::substring("héllo", 1, 3), "él");
+//! assert_eq!(strings::graphemes("🇮🇳 e\u{301}").collect::<Vec<&str>>(), ["🇮🇳", " ", "e\u{301}"]);
+//! assert_eq!(strings::display_width("日本"), 4);
+//! assert_eq!(strings::truncate("Hello World", 8, "…"), "Hello W…");
+//! ```
+
+mod tables;
+
+use std::borrow::Cow;
+
+/// Grapheme_Cluster_Break property values from UAX #29.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GraphemeClass {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    ExtendedPictographic
+}
+
+const HANGUL_SYLLABLES: (u32, u32) = (0xAC00, 0xD7A3);
+const HANGUL_T_COUNT: u32 = 28;
+
+fn grapheme_class(c: char) -> GraphemeClass {
+    let cp: u32 = c as u32;
+    if (HANGUL_SYLLABLES.0..=HANGUL_SYLLABLES.1).contains(&cp) {
+        return if (cp - HANGUL_SYLLABLES.0).is_multiple_of(HANGUL_T_COUNT) {
+            GraphemeClass::Lv
+        } else {
+            GraphemeClass::Lvt
+        };
+    }
+
+    match search(&tables::GRAPHEME_CLASSES, cp, |&(low, high, _)| (low, high)) {
+        Some(&(_, _, class)) => class,
+        None => GraphemeClass::Other
+    }
+}
+
+fn search<T>(table: &[T], cp: u32, range: impl Fn(&T) -> (u32, u32)) -> Option<&T> {
+    table
+        .binary_search_by(|entry| {
+            let (low, high) = range(entry);
+            if high < cp {
+                std::cmp::Ordering::Less
+            } else if low > cp {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .ok()
+        .map(|index| &table[index])
+}
+
+/// Byte offset of the `index`th character, or `s.len()` past the end.
+fn char_offset(s: &str, index: usize) -> usize {
+    s.char_indices().nth(index).map_or(s.len(), |(offset, _)| offset)
+}
+
+/// Characters `start..end` of `s`. Indices past the end are clamped, and an
+/// empty string comes back if `start >= end`.
+pub fn substring(s: &str, start: usize, end: usize) -> &str {
+    if start >= end {
+        return "";
+    }
+    let from: usize = char_offset(s, start);
+    let to: usize = from + char_offset(&s[from..], end - start);
+    &s[from..to]
+}
+
+/// Grapheme clusters `start..end` of `s`, clamped like `substring`.
+pub fn grapheme_substring(s: &str, start: usize, end: usize) -> &str {
+    if start >= end {
+        return "";
+    }
+    let from: usize = grapheme_offset(s, start);
+    let to: usize = from + grapheme_offset(&s[from..], end - start);
+    &s[from..to]
+}
+
+fn grapheme_offset(s: &str, index: usize) -> usize {
+    graphemes(s).take(index).map(str::len).sum()
+}
+
+/// Iterates over the extended grapheme clusters of `s`, following the rules
+/// in UAX #29 (Unicode 14).
+pub fn graphemes(s: &str) -> Graphemes<'_> {
+    Graphemes { rest: s }
+}
+
+pub fn grapheme_count(s: &str) -> usize {
+    graphemes(s).count()
+}
+
+#[derive(Debug, Clone)]
+pub struct Graphemes<'a> {
+    rest: &'a str
+}
+
+impl<'a> Graphemes<'a> {
+    /// The text not yet iterated over.
+    pub fn as_str(&self) -> &'a str {
+        self.rest
+    }
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+
+        let mut previous: GraphemeClass = grapheme_class(first);
+        // Regional indicators pair up, so count how many are in a row.
+        let mut regional_indicators: usize = (previous == GraphemeClass::RegionalIndicator) as usize;
+        // Whether we're inside ExtendedPictographic Extend*, and whether
+        // that run has just ended in a ZWJ.
+        let mut in_emoji: bool = previous == GraphemeClass::ExtendedPictographic;
+        let mut emoji_zwj: bool = false;
+
+        let mut end: usize = self.rest.len();
+        for (offset, c) in chars {
+            let class: GraphemeClass = grapheme_class(c);
+            if is_boundary(previous, class, regional_indicators, emoji_zwj) {
+                end = offset;
+                break;
+            }
+
+            regional_indicators = if class == GraphemeClass::RegionalIndicator { regional_indicators + 1 } else { 0 };
+            emoji_zwj = in_emoji && class == GraphemeClass::Zwj;
+            in_emoji = match class {
+                GraphemeClass::ExtendedPictographic => true,
+                GraphemeClass::Extend => in_emoji,
+                _ => false
+            };
+            previous = class;
+        }
+
+        let (cluster, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(cluster)
+    }
+}
+
+/// Whether there is a grapheme cluster boundary between characters of
+/// classes `before` and `after`.
+fn is_boundary(before: GraphemeClass, after: GraphemeClass, regional_indicators: usize, emoji_zwj: bool) -> bool {
+    use GraphemeClass::*;
+
+    match (before, after) {
+        // GB3, GB4, GB5
+        (Cr, Lf) => false,
+        (Cr, _) | (Lf, _) | (Control, _) => true,
+        (_, Cr) | (_, Lf) | (_, Control) => true,
+        // GB6, GB7, GB8
+        (L, L) | (L, V) | (L, Lv) | (L, Lvt) => false,
+        (Lv, V) | (V, V) | (Lv, T) | (V, T) => false,
+        (Lvt, T) | (T, T) => false,
+        // GB9, GB9a, GB9b
+        (_, Extend) | (_, Zwj) | (_, SpacingMark) | (Prepend, _) => false,
+        // GB11
+        (Zwj, ExtendedPictographic) => !emoji_zwj,
+        // GB12, GB13
+        (RegionalIndicator, RegionalIndicator) => regional_indicators.is_multiple_of(2),
+        // GB999
+        _ => true
+    }
+}
+
+/// Terminal columns `c` takes on its own: 2 for wide East Asian characters
+/// and emoji, 0 for marks, joiners and control characters, 1 otherwise.
+fn char_width(c: char) -> usize {
+    match grapheme_class(c) {
+        GraphemeClass::Control | GraphemeClass::Cr | GraphemeClass::Lf => return 0,
+        GraphemeClass::Extend | GraphemeClass::Zwj | GraphemeClass::V | GraphemeClass::T => return 0,
+        _ => {}
+    }
+    if search(&tables::WIDE, c as u32, |&range| range).is_some() { 2 } else { 1 }
+}
+
+/// Terminal columns a grapheme cluster takes. A cluster is as wide as its
+/// first character, except that emoji forced to emoji presentation with
+/// U+FE0F and pairs of regional indicators (flags) take two columns.
+fn grapheme_width(cluster: &str) -> usize {
+    let mut chars = cluster.chars();
+    let first: char = match chars.next() {
+        Some(first) => first,
+        None => return 0
+    };
+
+    let class: GraphemeClass = grapheme_class(first);
+    if class == GraphemeClass::RegionalIndicator && chars.clone().next().map(grapheme_class) == Some(class) {
+        return 2;
+    }
+    if class == GraphemeClass::ExtendedPictographic && chars.any(|c| c == '\u{FE0F}') {
+        return 2;
+    }
+    char_width(first)
+}
+
+/// Columns `s` takes in a monospace terminal.
+pub fn display_width(s: &str) -> usize {
+    graphemes(s).map(grapheme_width).sum()
+}
+
+/// Shortens `s` to at most `max_width` columns, ending with `ellipsis` if
+/// anything was cut. Whole grapheme clusters are kept or dropped, so
+/// accents stay on their letters and emoji aren't split.
+///
+/// If `ellipsis` itself doesn't fit, as much of it as fits is returned.
+pub fn truncate<'a>(s: &'a str, max_width: usize, ellipsis: &str) -> Cow<'a, str> {
+    if display_width(s) <= max_width {
+        return Cow::Borrowed(s);
+    }
+
+    let ellipsis_width: usize = display_width(ellipsis);
+    if ellipsis_width > max_width {
+        return Cow::Owned(truncate(ellipsis, max_width, "").into_owned());
+    }
+
+    let budget: usize = max_width - ellipsis_width;
+    let mut width: usize = 0;
+    let mut end: usize = 0;
+    for cluster in graphemes(s) {
+        let cluster_width: usize = grapheme_width(cluster);
+        if width + cluster_width > budget {
+            break;
+        }
+        width += cluster_width;
+        end += cluster.len();
+    }
+
+    Cow::Owned(format!("{}{}", &s[..end], ellipsis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clusters(s: &str) -> Vec<&str> {
+        graphemes(s).collect()
+    }
+
+    #[test]
+    fn test_substring_never_panics() {
+        assert_eq!(substring("héllo", 1, 3), "él");
+        assert_eq!(substring("héllo", 3, 100), "lo");
+        assert_eq!(substring("héllo", 10, 20), "");
+        assert_eq!(substring("héllo", 3, 1), "");
+        assert_eq!(substring("🦀rust", 0, 1), "🦀");
+    }
+
+    #[test]
+    fn test_grapheme_clusters() {
+        // Combining accent, CRLF, Hangul jamo and a precomposed syllable.
+        assert_eq!(clusters("e\u{301}\r\nx"), ["e\u{301}", "\r\n", "x"]);
+        assert_eq!(clusters("\u{1100}\u{1161}\u{11A8}\u{AC00}"), ["\u{1100}\u{1161}\u{11A8}", "\u{AC00}"]);
+        assert_eq!(clusters("\u{AC01}\u{1161}"), ["\u{AC01}", "\u{1161}"]);
+
+        // Devanagari spacing marks and a prepended Arabic mark.
+        assert_eq!(clusters("नमस्ते"), ["न", "म", "स्", "ते"]);
+        assert_eq!(clusters("\u{0600}1"), ["\u{0600}1"]);
+    }
+
+    #[test]
+    fn test_emoji_clusters() {
+        // Family joined with ZWJ, skin tone modifier, and keycap.
+        assert_eq!(clusters("👨‍👩‍👧!"), ["👨‍👩‍👧", "!"]);
+        assert_eq!(clusters("👍🏽👍"), ["👍🏽", "👍"]);
+        assert_eq!(clusters("1\u{FE0F}\u{20E3}"), ["1\u{FE0F}\u{20E3}"]);
+        // A ZWJ not after an emoji doesn't join the next one.
+        assert_eq!(clusters("a\u{200D}👍"), ["a\u{200D}", "👍"]);
+    }
+
+    #[test]
+    fn test_regional_indicators_pair_up() {
+        assert_eq!(clusters("🇮🇳🇯🇵🇺"), ["🇮🇳", "🇯🇵", "🇺"]);
+        assert_eq!(grapheme_count("🇮🇳🇯🇵🇺"), 3);
+        assert_eq!(grapheme_substring("a🇮🇳e\u{301}b", 1, 3), "🇮🇳e\u{301}");
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("hello"), 5);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👨‍👩‍👧"), 2);
+        assert_eq!(display_width("🇮🇳"), 2);
+        assert_eq!(display_width("❤\u{FE0F}"), 2);
+        assert_eq!(display_width("ｆｕｌｌ"), 8);
+        assert_eq!(display_width("a\tb"), 2);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10, "…"), "short");
+        assert!(matches!(truncate("short", 5, "…"), Cow::Borrowed(_)));
+        assert_eq!(truncate("Hello World", 8, "..."), "Hello...");
+        // A wide character that would straddle the limit is dropped whole.
+        assert_eq!(truncate("日本語テキスト", 6, "…"), "日本…");
+        assert_eq!(truncate("cafe\u{301}s!", 5, "…"), "cafe\u{301}…");
+        assert_eq!(truncate("👨‍👩‍👧👨‍👩‍👧", 3, "…"), "👨‍👩‍👧…");
+        assert_eq!(truncate("Hello", 2, "..."), "..");
+    }
+}
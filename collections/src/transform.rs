@@ -0,0 +1,356 @@
+//! Text transformations that can be chained into a pipeline.
+//!
+//! ```
+//! use collections::transform::{ Pipeline, Transform };
+//!
+//! let pipeline: Pipeline = Pipeline::new().then(Transform::NormalizeWhitespace).then(Transform::Snake);
+//! assert_eq!(pipeline.apply("  Hello   World  "), "hello_world");
+//!
+//! let pipeline: Pipeline = "pig-latin,rot13".parse().unwrap();
+//! assert_eq!(pipeline.apply("first apple"), "vefg-snl nccyr-unl");
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// "first" becomes "irst-fay" and "apple" becomes "apple-hay".
+    PigLatin,
+    /// Capitalises the first letter of every word and lowercases the rest.
+    Title,
+    Upper,
+    Lower,
+    /// `snake_case`, applied to each line.
+    Snake,
+    /// `kebab-case`, applied to each line.
+    Kebab,
+    /// `camelCase`, applied to each line.
+    Camel,
+    /// `PascalCase`, applied to each line.
+    Pascal,
+    /// Collapses runs of spaces and tabs, trims every line and squeezes
+    /// runs of blank lines down to one.
+    NormalizeWhitespace,
+    /// Rotates ASCII letters by 13 places. Applying it twice gives back the
+    /// original.
+    Rot13
+}
+
+const NAMES: [(&str, Transform); 10] = [
+    ("pig-latin", Transform::PigLatin),
+    ("title", Transform::Title),
+    ("upper", Transform::Upper),
+    ("lower", Transform::Lower),
+    ("snake", Transform::Snake),
+    ("kebab", Transform::Kebab),
+    ("camel", Transform::Camel),
+    ("pascal", Transform::Pascal),
+    ("normalize", Transform::NormalizeWhitespace),
+    ("rot13", Transform::Rot13)
+];
+
+impl Transform {
+    pub fn apply(&self, input: &str) -> String {
+        match self {
+            Transform::PigLatin => map_words(input, pig_latin),
+            Transform::Title => map_words(input, capitalize),
+            Transform::Upper => input.to_uppercase(),
+            Transform::Lower => input.to_lowercase(),
+            Transform::Snake => map_lines(input, |line| join_words(line, "_", str::to_lowercase)),
+            Transform::Kebab => map_lines(input, |line| join_words(line, "-", str::to_lowercase)),
+            Transform::Camel => map_lines(input, |line| {
+                let pascal: String = join_words(line, "", capitalize);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_lowercase().chain(chars).collect(),
+                    None => pascal
+                }
+            }),
+            Transform::Pascal => map_lines(input, |line| join_words(line, "", capitalize)),
+            Transform::NormalizeWhitespace => normalize_whitespace(input),
+            Transform::Rot13 => input.chars().map(rot13).collect()
+        }
+    }
+
+    /// The name `FromStr` accepts, e.g. `pig-latin`.
+    pub fn name(&self) -> &'static str {
+        NAMES.iter().find(|(_, transform)| transform == self).map(|(name, _)| *name).unwrap()
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTransform(pub String);
+
+impl fmt::Display for UnknownTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = NAMES.iter().map(|(name, _)| *name).collect();
+        write!(f, "unknown transform '{}', expected one of {}", self.0, names.join(", "))
+    }
+}
+
+impl Error for UnknownTransform {}
+
+impl FromStr for Transform {
+    type Err = UnknownTransform;
+
+    fn from_str(s: &str) -> Result<Transform, UnknownTransform> {
+        let name: String = s.trim().to_ascii_lowercase().replace('_', "-");
+        NAMES
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, transform)| *transform)
+            .ok_or_else(|| UnknownTransform(s.trim().to_string()))
+    }
+}
+
+/// Transforms applied one after another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    steps: Vec<Transform>
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    pub fn then(mut self, transform: Transform) -> Pipeline {
+        self.steps.push(transform);
+        self
+    }
+
+    pub fn steps(&self) -> &[Transform] {
+        &self.steps
+    }
+
+    pub fn apply(&self, input: &str) -> String {
+        self.steps.iter().fold(input.to_string(), |text, step| step.apply(&text))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = UnknownTransform;
+
+    /// Parses transform names separated by `,` or `|`, e.g. `normalize|title`.
+    fn from_str(s: &str) -> Result<Pipeline, UnknownTransform> {
+        s.split([',', '|'])
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Transform>, UnknownTransform>>()
+            .map(|steps| Pipeline { steps })
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.steps.iter().map(Transform::name).collect();
+        f.write_str(&names.join(" | "))
+    }
+}
+
+/// Applies `f` to every run of letters, digits and apostrophes, leaving
+/// everything in between untouched.
+fn map_words<F: Fn(&str) -> String>(input: &str, f: F) -> String {
+    let mut output: String = String::with_capacity(input.len());
+    let mut word_start: Option<usize> = None;
+
+    for (offset, c) in input.char_indices() {
+        let in_word: bool = c.is_alphanumeric() || (c == '\'' && word_start.is_some());
+        match (in_word, word_start) {
+            (true, None) => word_start = Some(offset),
+            (false, Some(start)) => {
+                output.push_str(&f(&input[start..offset]));
+                output.push(c);
+                word_start = None;
+            }
+            (false, None) => output.push(c),
+            (true, Some(_)) => {}
+        }
+    }
+    if let Some(start) = word_start {
+        output.push_str(&f(&input[start..]));
+    }
+
+    output
+}
+
+/// Applies `f` to each line, keeping the line endings as they were.
+fn map_lines<F: Fn(&str) -> String>(input: &str, f: F) -> String {
+    input
+        .split_inclusive('\n')
+        .map(|line| {
+            let content: &str = line.trim_end_matches(['\r', '\n']);
+            format!("{}{}", f(content), &line[content.len()..])
+        })
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new()
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn pig_latin(word: &str) -> String {
+    let first: char = match word.chars().next() {
+        Some(first) if first.is_alphabetic() => first,
+        _ => return word.to_string()
+    };
+
+    if is_vowel(first) {
+        return format!("{}-hay", word);
+    }
+
+    let rest: &str = &word[first.len_utf8()..];
+    if rest.is_empty() {
+        return format!("{}ay", word);
+    }
+
+    // Keep a capitalised word capitalised: "Hello" becomes "Ello-hay".
+    if first.is_uppercase() {
+        let mut rest_chars = rest.chars();
+        let capitalized: String = rest_chars.next().into_iter().flat_map(char::to_uppercase).chain(rest_chars).collect();
+        let lowered: String = first.to_lowercase().collect();
+        format!("{}-{}ay", capitalized, lowered)
+    } else {
+        format!("{}-{}ay", rest, first)
+    }
+}
+
+/// Splits an identifier or phrase into words at spaces, punctuation and
+/// case changes, so "parseHTTPResponse" gives "parse", "HTTP", "Response".
+fn split_words(line: &str) -> Vec<&str> {
+    let mut words: Vec<&str> = Vec::new();
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut start: Option<usize> = None;
+
+    for (index, &(offset, c)) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if let Some(start) = start.take() {
+                words.push(&line[start..offset]);
+            }
+            continue;
+        }
+
+        if let Some(word_start) = start {
+            let previous: char = chars[index - 1].1;
+            let next: Option<char> = chars.get(index + 1).map(|&(_, next)| next);
+            let lower_to_upper: bool = c.is_uppercase() && (previous.is_lowercase() || previous.is_numeric());
+            let acronym_end: bool = c.is_uppercase()
+                && previous.is_uppercase()
+                && next.is_some_and(char::is_lowercase);
+            if lower_to_upper || acronym_end {
+                words.push(&line[word_start..offset]);
+                start = Some(offset);
+            }
+        } else {
+            start = Some(offset);
+        }
+    }
+    if let Some(start) = start {
+        words.push(&line[start..]);
+    }
+
+    words
+}
+
+fn join_words<F: Fn(&str) -> String>(line: &str, separator: &str, f: F) -> String {
+    split_words(line).into_iter().map(f).collect::<Vec<String>>().join(separator)
+}
+
+fn normalize_whitespace(input: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        let line: String = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let blank_run: bool = line.is_empty() && lines.last().is_none_or(|last| last.is_empty());
+        if !blank_run {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+
+    let mut output: String = lines.join("\n");
+    if input.ends_with('\n') && !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+fn rot13(c: char) -> char {
+    match c {
+        'a'..='z' => (((c as u8 - b'a') + 13) % 26 + b'a') as char,
+        'A'..='Z' => (((c as u8 - b'A') + 13) % 26 + b'A') as char,
+        _ => c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pig_latin() {
+        assert_eq!(Transform::PigLatin.apply("first apple"), "irst-fay apple-hay");
+        assert_eq!(Transform::PigLatin.apply("Hello, World! I'm 42."), "Ello-hay, Orld-way! I'm-hay 42.");
+        assert_eq!(Transform::PigLatin.apply("x über"), "xay ber-üay");
+    }
+
+    #[test]
+    fn test_case_conversions() {
+        assert_eq!(Transform::Snake.apply("parseHTTPResponse"), "parse_http_response");
+        assert_eq!(Transform::Kebab.apply("Hello World v2"), "hello-world-v2");
+        assert_eq!(Transform::Camel.apply("user_id\nHTTP server"), "userId\nhttpServer");
+        assert_eq!(Transform::Pascal.apply("some-kebab-name"), "SomeKebabName");
+        assert_eq!(Transform::Title.apply("the QUICK brown fox's tail"), "The Quick Brown Fox's Tail");
+        assert_eq!(Transform::Snake.apply("crlf line\r\n"), "crlf_line\r\n");
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        assert_eq!(
+            Transform::NormalizeWhitespace.apply("\n\n  a \t b  \n\n\n\nc   \n\n"),
+            "a b\n\nc\n"
+        );
+        assert_eq!(Transform::NormalizeWhitespace.apply("   "), "");
+    }
+
+    #[test]
+    fn test_rot13_round_trips() {
+        let text: &str = "Hello, World! ünïcode stays";
+        assert_eq!(Transform::Rot13.apply(text), "Uryyb, Jbeyq! üaïpbqr fgnlf");
+        assert_eq!(Transform::Rot13.apply(&Transform::Rot13.apply(text)), text);
+    }
+
+    #[test]
+    fn test_pipeline_parsing_and_order() {
+        let pipeline: Pipeline = "normalize | title, rot13".parse().unwrap();
+        assert_eq!(pipeline.steps(), [Transform::NormalizeWhitespace, Transform::Title, Transform::Rot13]);
+        assert_eq!(pipeline.to_string(), "normalize | title | rot13");
+        assert_eq!(pipeline.apply("  hello   world "), "Uryyb Jbeyq");
+
+        // Order matters.
+        let snake_then_title: Pipeline = Pipeline::new().then(Transform::Snake).then(Transform::Title);
+        let title_then_snake: Pipeline = Pipeline::new().then(Transform::Title).then(Transform::Snake);
+        assert_eq!(snake_then_title.apply("hello world"), "Hello_World");
+        assert_eq!(title_then_snake.apply("hello world"), "hello_world");
+
+        assert_eq!("pig_latin".parse::<Transform>(), Ok(Transform::PigLatin));
+        assert_eq!("title,shout".parse::<Pipeline>(), Err(UnknownTransform(String::from("shout"))));
+    }
+}
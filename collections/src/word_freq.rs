@@ -0,0 +1,347 @@
+//! Word and n-gram frequency counting.
+//!
+//! ```
+//! use collections::word_freq::{ Config, WordCounter };
+//!
+//! let mut counter: WordCounter = WordCounter::new(Config::default());
+//! counter.add_text("Hello world, wonderful World!");
+//! assert_eq!(counter.top(1), vec![("world", 2)]);
+//! ```
+
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::str::FromStr;
+
+/// A small list of common English words, for `Config::stop_words`.
+pub const ENGLISH_STOP_WORDS: [&str; 48] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "had", "has", "have",
+    "he", "her", "his", "i", "if", "in", "is", "it", "its", "me", "my", "not", "of", "on", "or",
+    "our", "she", "so", "that", "the", "their", "them", "they", "this", "to", "was", "we", "were",
+    "what", "which", "who", "will", "with", "you"
+];
+
+/// Splits `text` into words.
+///
+/// A word is a run of alphanumeric characters in any script. Apostrophes and
+/// hyphens are kept inside a word ("don't", "well-known") but dropped at its
+/// edges. Typographic variants such as ’ and ‐ are folded into their ASCII
+/// forms first, and every other kind of punctuation separates words.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut current: String = String::new();
+
+    for c in text.chars().map(fold_punctuation) {
+        if c.is_alphanumeric() || ((c == '\'' || c == '-') && !current.is_empty()) {
+            current.push(c);
+        } else if !current.is_empty() {
+            push_word(&mut words, &mut current);
+        }
+    }
+    if !current.is_empty() {
+        push_word(&mut words, &mut current);
+    }
+
+    words
+}
+
+fn fold_punctuation(c: char) -> char {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201B}' | '\u{2032}' | '\u{FF07}' => '\'',
+        '\u{2010}' | '\u{2011}' | '\u{00AD}' => '-',
+        _ => c
+    }
+}
+
+fn push_word(words: &mut Vec<String>, current: &mut String) {
+    let word: &str = current.trim_end_matches(['\'', '-']);
+    if !word.is_empty() {
+        words.push(word.to_string());
+    }
+    current.clear();
+}
+
+/// Parses a stop-word list: one word per line, blank lines and lines
+/// starting with `#` are ignored.
+pub fn read_stop_words<R: BufRead>(reader: R) -> io::Result<HashSet<String>> {
+    let mut words: HashSet<String> = HashSet::new();
+    for line in reader.lines() {
+        let line: String = line?;
+        let word: &str = line.trim();
+        if !word.is_empty() && !word.starts_with('#') {
+            words.insert(word.to_lowercase());
+        }
+    }
+    Ok(words)
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Fold everything to lower case so "World" and "world" count together.
+    pub lowercase: bool,
+    /// Words to drop before counting. They're matched ignoring case, even
+    /// when `lowercase` is off.
+    pub stop_words: HashSet<String>,
+    /// Count runs of this many consecutive words. 1 counts single words.
+    pub ngram: usize
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            lowercase: true,
+            stop_words: HashSet::new(),
+            ngram: 1
+        }
+    }
+}
+
+/// Accumulates term counts over any number of texts.
+#[derive(Debug, Clone)]
+pub struct WordCounter {
+    config: Config,
+    counts: HashMap<String, usize>,
+    /// Last `ngram - 1` words, so n-grams can span lines of the same document.
+    window: Vec<String>,
+    total: usize
+}
+
+impl WordCounter {
+    /// # Panics
+    ///
+    /// Panics if `config.ngram` is 0.
+    pub fn new(mut config: Config) -> WordCounter {
+        assert!(config.ngram > 0, "N-grams need at least one word");
+        config.stop_words = config.stop_words.iter().map(|word| word.to_lowercase()).collect();
+
+        WordCounter {
+            config,
+            counts: HashMap::new(),
+            window: Vec::new(),
+            total: 0
+        }
+    }
+
+    /// Counts the words in `text`, continuing any n-gram from the previous
+    /// call.
+    pub fn add_text(&mut self, text: &str) {
+        for word in tokenize(text) {
+            let folded: String = word.to_lowercase();
+            if self.config.stop_words.contains(&folded) {
+                continue;
+            }
+            let word: String = if self.config.lowercase { folded } else { word };
+
+            self.window.push(word);
+            if self.window.len() > self.config.ngram {
+                self.window.remove(0);
+            }
+            if self.window.len() == self.config.ngram {
+                let term: String = self.window.join(" ");
+                *self.counts.entry(term).or_insert(0) += 1;
+                self.total += 1;
+            }
+        }
+    }
+
+    /// Counts the whole of `reader` as one document, so n-grams run on
+    /// across line breaks but not into whatever is added next.
+    pub fn add_reader<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        for line in reader.lines() {
+            self.add_text(&line?);
+        }
+        self.end_document();
+        Ok(())
+    }
+
+    /// Stops n-grams from running on into the next text.
+    pub fn end_document(&mut self) {
+        self.window.clear();
+    }
+
+    pub fn count(&self, term: &str) -> usize {
+        self.counts.get(term).copied().unwrap_or(0)
+    }
+
+    pub fn counts(&self) -> &HashMap<String, usize> {
+        &self.counts
+    }
+
+    /// Number of terms counted, including repeats.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Number of distinct terms.
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The `k` most frequent terms, most frequent first. Ties are broken
+    /// alphabetically so the output is stable.
+    pub fn top(&self, k: usize) -> Vec<(&str, usize)> {
+        let mut entries: Vec<(&str, usize)> = self.counts
+            .iter()
+            .map(|(term, &count)| (term.as_str(), count))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        entries.truncate(k);
+        entries
+    }
+
+    /// Every term, in the same order as `top`.
+    pub fn sorted(&self) -> Vec<(&str, usize)> {
+        self.top(self.counts.len())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFormatError(String);
+
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown output format '{}', expected csv or json", self.0)
+    }
+}
+
+impl std::error::Error for ParseFormatError {}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Format, ParseFormatError> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(ParseFormatError(s.to_string()))
+        }
+    }
+}
+
+/// Writes `entries` as `term,count` rows under a header.
+pub fn write_csv<W: Write>(writer: &mut W, entries: &[(&str, usize)]) -> io::Result<()> {
+    writeln!(writer, "term,count")?;
+    for (term, count) in entries {
+        if term.contains([',', '"', '\n']) {
+            writeln!(writer, "\"{}\",{}", term.replace('"', "\"\""), count)?;
+        } else {
+            writeln!(writer, "{},{}", term, count)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `entries` as a JSON array of `{"term": ..., "count": ...}` objects.
+pub fn write_json<W: Write>(writer: &mut W, entries: &[(&str, usize)]) -> io::Result<()> {
+    writeln!(writer, "[")?;
+    for (index, (term, count)) in entries.iter().enumerate() {
+        let separator: &str = if index + 1 < entries.len() { "," } else { "" };
+        writeln!(writer, "  {{\"term\": \"{}\", \"count\": {}}}{}", escape_json(term), count, separator)?;
+    }
+    writeln!(writer, "]")
+}
+
+pub fn write<W: Write>(writer: &mut W, format: Format, entries: &[(&str, usize)]) -> io::Result<()> {
+    match format {
+        Format::Csv => write_csv(writer, entries),
+        Format::Json => write_json(writer, entries)
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped: String = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_handles_unicode_punctuation() {
+        assert_eq!(
+            tokenize("“Don’t stop,” she said—well-known café… 'quoted' -dash- ¿qué?"),
+            vec!["Don't", "stop", "she", "said", "well-known", "café", "quoted", "dash", "qué"]
+        );
+    }
+
+    #[test]
+    fn test_counts_are_case_folded() {
+        let mut counter: WordCounter = WordCounter::new(Config::default());
+        counter.add_text("hello world wonderful World");
+
+        assert_eq!(counter.count("world"), 2);
+        assert_eq!(counter.total(), 4);
+        assert_eq!(counter.top(2), vec![("world", 2), ("hello", 1)]);
+
+        let mut keep_case: WordCounter = WordCounter::new(Config { lowercase: false, ..Config::default() });
+        keep_case.add_text("hello world wonderful World");
+        assert_eq!(keep_case.count("world"), 1);
+    }
+
+    #[test]
+    fn test_stop_words_are_skipped_before_ngrams() {
+        let stop_words: HashSet<String> = read_stop_words("# comment\nthe\n\nOF\n".as_bytes()).unwrap();
+        let mut counter: WordCounter = WordCounter::new(Config {
+            stop_words,
+            ngram: 2,
+            ..Config::default()
+        });
+        counter.add_reader("The end of the world\nthe end".as_bytes()).unwrap();
+
+        assert_eq!(counter.sorted(), vec![("end world", 1), ("world end", 1)]);
+
+        let mut keep_case: WordCounter = WordCounter::new(Config {
+            lowercase: false,
+            stop_words: ["The"].iter().map(|word| word.to_string()).collect(),
+            ..Config::default()
+        });
+        keep_case.add_text("The end of THE World");
+        assert_eq!(keep_case.sorted(), vec![("World", 1), ("end", 1), ("of", 1)]);
+    }
+
+    #[test]
+    fn test_ngrams_stop_at_document_boundaries() {
+        let mut counter: WordCounter = WordCounter::new(Config { ngram: 2, ..Config::default() });
+        counter.add_reader("a b".as_bytes()).unwrap();
+        counter.add_reader("c d".as_bytes()).unwrap();
+
+        assert_eq!(counter.count("b c"), 0);
+        assert_eq!(counter.distinct(), 2);
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let entries: Vec<(&str, usize)> = vec![("plain", 2), ("a,\"b\"", 1)];
+
+        let mut csv: Vec<u8> = Vec::new();
+        write(&mut csv, "CSV".parse().unwrap(), &entries).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "term,count\nplain,2\n\"a,\"\"b\"\"\",1\n");
+
+        let mut json: Vec<u8> = Vec::new();
+        write(&mut json, Format::Json, &entries).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "[\n  {\"term\": \"plain\", \"count\": 2},\n  {\"term\": \"a,\\\"b\\\"\", \"count\": 1}\n]\n"
+        );
+
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use concurrency::lockfree::{ mpsc as lockfree_mpsc, spsc };
+use std::sync::mpsc;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+const MESSAGES: u64 = 2_000_000;
+const RING_CAPACITY: usize = 1024;
+const PRODUCER_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+fn report(name: &str, producers: usize, elapsed: Duration) {
+    println!(
+        "{:<22} {:>2} producers {:>10.2?} {:>8.2} Mmsg/s",
+        name,
+        producers,
+        elapsed,
+        MESSAGES as f64 / elapsed.as_secs_f64() / 1_000_000.0
+    );
+}
+
+fn bench_std_channel(producers: usize) -> Duration {
+    let (tx, rx) = mpsc::channel::<u64>();
+    let start: Instant = Instant::now();
+
+    let handles: Vec<thread::JoinHandle<()>> = (0..producers)
+        .map(|_| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..MESSAGES / producers as u64 {
+                    tx.send(i).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut sum: u64 = 0;
+    for value in rx {
+        sum = sum.wrapping_add(value);
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    std::hint::black_box(sum);
+    start.elapsed()
+}
+
+fn bench_std_sync_channel() -> Duration {
+    let (tx, rx) = mpsc::sync_channel::<u64>(RING_CAPACITY);
+    let start: Instant = Instant::now();
+
+    let handle = thread::spawn(move || {
+        for i in 0..MESSAGES {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let mut sum: u64 = 0;
+    for value in rx {
+        sum = sum.wrapping_add(value);
+    }
+    handle.join().unwrap();
+
+    std::hint::black_box(sum);
+    start.elapsed()
+}
+
+fn bench_spsc() -> Duration {
+    let (mut producer, mut consumer) = spsc::channel::<u64>(RING_CAPACITY);
+    let start: Instant = Instant::now();
+
+    let handle = thread::spawn(move || {
+        for i in 0..MESSAGES {
+            let mut value: u64 = i;
+            while let Err(returned) = producer.push(value) {
+                value = returned;
+                thread::yield_now();
+            }
+        }
+    });
+
+    let mut sum: u64 = 0;
+    let mut received: u64 = 0;
+    while received < MESSAGES {
+        match consumer.pop() {
+            Some(value) => {
+                sum = sum.wrapping_add(value);
+                received += 1;
+            }
+            None => thread::yield_now()
+        }
+    }
+    handle.join().unwrap();
+
+    std::hint::black_box(sum);
+    start.elapsed()
+}
+
+fn bench_lockfree_mpsc(producers: usize) -> Duration {
+    let (producer, mut consumer) = lockfree_mpsc::queue::<u64>();
+    let per_producer: u64 = MESSAGES / producers as u64;
+    let start: Instant = Instant::now();
+
+    let handles: Vec<thread::JoinHandle<()>> = (0..producers)
+        .map(|_| {
+            let producer = producer.clone();
+            thread::spawn(move || {
+                for i in 0..per_producer {
+                    producer.push(i);
+                }
+            })
+        })
+        .collect();
+    drop(producer);
+
+    let mut sum: u64 = 0;
+    let mut received: u64 = 0;
+    while received < per_producer * producers as u64 {
+        match consumer.pop() {
+            Some(value) => {
+                sum = sum.wrapping_add(value);
+                received += 1;
+            }
+            None => thread::yield_now()
+        }
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    std::hint::black_box(sum);
+    start.elapsed()
+}
+
+fn main() {
+    report("mpsc::sync_channel", 1, bench_std_sync_channel());
+    report("spsc::channel", 1, bench_spsc());
+
+    println!();
+
+    for &producers in PRODUCER_COUNTS.iter() {
+        report("mpsc::channel", producers, bench_std_channel(producers));
+        report("lockfree::mpsc::queue", producers, bench_lockfree_mpsc(producers));
+    }
+}
@@ -0,0 +1,71 @@
+use concurrency::sharded::{ ShardedCounter, ShardedMap };
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+const OPS_PER_THREAD: u64 = 200_000;
+const KEYS: u64 = 1024;
+const THREAD_COUNTS: [usize; 7] = [1, 2, 4, 8, 16, 32, 64];
+
+fn run<F>(threads: usize, work: F) -> Duration
+where
+    F: Fn(u64) + Send + Sync + 'static
+{
+    let work: Arc<F> = Arc::new(work);
+    let start: Instant = Instant::now();
+
+    let handles: Vec<thread::JoinHandle<()>> = (0..threads as u64)
+        .map(|id| {
+            let work = Arc::clone(&work);
+            thread::spawn(move || {
+                for i in 0..OPS_PER_THREAD {
+                    work(id.wrapping_mul(7919).wrapping_add(i));
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    start.elapsed()
+}
+
+fn report(name: &str, threads: usize, elapsed: Duration) {
+    let ops: f64 = (threads as u64 * OPS_PER_THREAD) as f64;
+    println!(
+        "{:<20} {:>3} threads {:>10.2?} {:>8.2} Mops/s",
+        name,
+        threads,
+        elapsed,
+        ops / elapsed.as_secs_f64() / 1_000_000.0
+    );
+}
+
+fn main() {
+    for &threads in THREAD_COUNTS.iter() {
+        let counter: Arc<Mutex<u64>> = Arc::new(Mutex::new(0));
+        report("Arc<Mutex<u64>>", threads, run(threads, move |_| {
+            *counter.lock().unwrap() += 1;
+        }));
+
+        let counter: Arc<ShardedCounter> = Arc::new(ShardedCounter::new());
+        report("ShardedCounter", threads, run(threads, move |_| counter.increment()));
+    }
+
+    println!();
+
+    for &threads in THREAD_COUNTS.iter() {
+        let map: Arc<Mutex<HashMap<u64, u64>>> = Arc::new(Mutex::new(HashMap::new()));
+        report("Mutex<HashMap>", threads, run(threads, move |i| {
+            *map.lock().unwrap().entry(i % KEYS).or_insert(0) += 1;
+        }));
+
+        let map: Arc<ShardedMap<u64, u64>> = Arc::new(ShardedMap::new());
+        report("ShardedMap", threads, run(threads, move |i| {
+            map.update_or_insert(i % KEYS, 0, |count| *count += 1);
+        }));
+    }
+}
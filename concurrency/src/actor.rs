@@ -0,0 +1,436 @@
+//! Actors: state owned by one thread and changed only by messages.
+//!
+//! An [`Actor`] runs on its own thread and handles one message at a time from
+//! an `mpsc` channel. Other threads talk to it through a cloneable
+//! [`ActorRef`], either fire-and-forget with [`ActorRef::send`] or
+//! request/response with [`ActorRef::ask`], which hands the actor a
+//! one-shot [`Reply`].
+
+use std::error::Error;
+use std::fmt;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::mpsc::{ self, RecvTimeoutError };
+use std::thread;
+use std::time::Duration;
+
+pub trait Actor: Send + 'static {
+    type Message: Send + 'static;
+
+    fn handle(&mut self, message: Self::Message, ctx: &mut Context);
+
+    /// Called before the first message, and again after every restart. A
+    /// panic here counts against the restart policy like one in `handle`.
+    fn started(&mut self) {}
+
+    /// Called once the actor stops normally. Not called after a panic that
+    /// isn't followed by a restart.
+    fn stopped(&mut self) {}
+}
+
+/// Passed to [`Actor::handle`] to let the actor inspect and end its run.
+#[derive(Debug)]
+pub struct Context {
+    stop: bool,
+    restarts: u32
+}
+
+impl Context {
+    /// Stops the actor after the current message.
+    pub fn stop(&mut self) {
+        self.stop = true;
+    }
+
+    /// How many times the supervisor has restarted this actor.
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+}
+
+/// What the supervisor does when `handle` panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// Let the actor die.
+    Never,
+    /// Restart with fresh state at most this many times.
+    UpTo(u32),
+    Always
+}
+
+/// The one-shot sender for the answer to an [`ActorRef::ask`].
+#[derive(Debug)]
+pub struct Reply<T> {
+    sender: mpsc::Sender<T>
+}
+
+impl<T> Reply<T> {
+    /// Sends the answer. Nothing happens if the asker has stopped waiting.
+    pub fn send(self, value: T) {
+        let _ = self.sender.send(value);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AskError {
+    /// The actor has stopped, so the request was never delivered.
+    Stopped,
+    /// The actor dropped the [`Reply`] without answering, for example
+    /// because handling the request panicked.
+    NoReply,
+    Timeout
+}
+
+impl fmt::Display for AskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AskError::Stopped => f.write_str("actor has stopped"),
+            AskError::NoReply => f.write_str("actor dropped the request without replying"),
+            AskError::Timeout => f.write_str("timed out waiting for the actor to reply")
+        }
+    }
+}
+
+impl Error for AskError {}
+
+/// Returned by [`ActorRef::send`] when the actor has stopped, giving the
+/// message back.
+#[derive(PartialEq, Eq)]
+pub struct Stopped<M>(pub M);
+
+impl<M> fmt::Debug for Stopped<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Stopped(..)")
+    }
+}
+
+impl<M> fmt::Display for Stopped<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("actor has stopped")
+    }
+}
+
+impl<M> Error for Stopped<M> {}
+
+enum Envelope<M> {
+    Message(M),
+    Stop
+}
+
+/// Address of a running actor. Cloning it is cheap.
+pub struct ActorRef<M> {
+    sender: mpsc::Sender<Envelope<M>>
+}
+
+impl<M> ActorRef<M> {
+    pub fn send(&self, message: M) -> Result<(), Stopped<M>> {
+        self.sender.send(Envelope::Message(message)).map_err(|error| match error.0 {
+            Envelope::Message(message) => Stopped(message),
+            Envelope::Stop => unreachable!("Only messages are sent here")
+        })
+    }
+
+    /// Sends the message built by `make` around a fresh [`Reply`] and waits
+    /// for the answer.
+    ///
+    /// ```
+    /// use concurrency::actor::{ self, Actor, Context, Reply };
+    ///
+    /// struct Echo;
+    ///
+    /// impl Actor for Echo {
+    ///     type Message = (String, Reply<String>);
+    ///
+    ///     fn handle(&mut self, (text, reply): Self::Message, _ctx: &mut Context) {
+    ///         reply.send(text.to_uppercase());
+    ///     }
+    /// }
+    ///
+    /// let (echo, handle) = actor::spawn(Echo);
+    /// assert_eq!(echo.ask(|reply| (String::from("hi"), reply)), Ok(String::from("HI")));
+    /// echo.stop();
+    /// handle.join();
+    /// ```
+    pub fn ask<R, F>(&self, make: F) -> Result<R, AskError>
+    where
+        F: FnOnce(Reply<R>) -> M
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.send(make(Reply { sender })).map_err(|_| AskError::Stopped)?;
+        receiver.recv().map_err(|_| AskError::NoReply)
+    }
+
+    /// Like [`ActorRef::ask`], but gives up after `timeout`.
+    pub fn ask_timeout<R, F>(&self, timeout: Duration, make: F) -> Result<R, AskError>
+    where
+        F: FnOnce(Reply<R>) -> M
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.send(make(Reply { sender })).map_err(|_| AskError::Stopped)?;
+        receiver.recv_timeout(timeout).map_err(|error| match error {
+            RecvTimeoutError::Timeout => AskError::Timeout,
+            RecvTimeoutError::Disconnected => AskError::NoReply
+        })
+    }
+
+    /// Asks the actor to stop once it has handled the messages already
+    /// queued. Returns `false` if it had already stopped.
+    pub fn stop(&self) -> bool {
+        self.sender.send(Envelope::Stop).is_ok()
+    }
+}
+
+impl<M> Clone for ActorRef<M> {
+    fn clone(&self) -> ActorRef<M> {
+        ActorRef { sender: self.sender.clone() }
+    }
+}
+
+impl<M> fmt::Debug for ActorRef<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ActorRef { .. }")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// Stopped by [`ActorRef::stop`] or [`Context::stop`].
+    Stopped,
+    /// Every [`ActorRef`] was dropped.
+    Disconnected,
+    /// Building the actor, `started`, `handle` or `stopped` panicked and
+    /// the restart policy didn't allow a restart.
+    Panicked
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActorExit {
+    pub reason: ExitReason,
+    pub restarts: u32
+}
+
+/// Owns the actor's thread.
+#[derive(Debug)]
+pub struct ActorHandle {
+    thread: thread::JoinHandle<ActorExit>
+}
+
+impl ActorHandle {
+    /// Waits for the actor's thread to finish.
+    pub fn join(self) -> ActorExit {
+        self.thread.join().expect("Actor panics are caught by the supervisor")
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
+
+/// Starts `actor` on its own thread without restarts.
+pub fn spawn<A: Actor>(actor: A) -> (ActorRef<A::Message>, ActorHandle) {
+    let mut actor: Option<A> = Some(actor);
+    spawn_supervised(move || actor.take().expect("Restart::Never never restarts"), Restart::Never)
+}
+
+/// Starts an actor built by `factory` on its own thread. When `handle`
+/// panics, the message is dropped and, if `restart` allows it, the actor is
+/// rebuilt from `factory` and carries on with the next message. A panic in
+/// `factory` or [`Actor::started`] uses up a restart the same way, so
+/// `Restart::Always` with a factory that always panics never gets going.
+pub fn spawn_supervised<A, F>(mut factory: F, restart: Restart) -> (ActorRef<A::Message>, ActorHandle)
+where
+    A: Actor,
+    F: FnMut() -> A + Send + 'static
+{
+    let (sender, receiver) = mpsc::channel::<Envelope<A::Message>>();
+
+    let thread = thread::spawn(move || {
+        let mut restarts: u32 = 0;
+        let mut actor: A = match start(&mut factory, restart, &mut restarts) {
+            Some(actor) => actor,
+            None => return ActorExit { reason: ExitReason::Panicked, restarts }
+        };
+
+        let reason: ExitReason = loop {
+            let message: A::Message = match receiver.recv() {
+                Ok(Envelope::Message(message)) => message,
+                Ok(Envelope::Stop) => break ExitReason::Stopped,
+                Err(_) => break ExitReason::Disconnected
+            };
+
+            let mut ctx: Context = Context { stop: false, restarts };
+            let result = panic::catch_unwind(AssertUnwindSafe(|| actor.handle(message, &mut ctx)));
+
+            match result {
+                Ok(()) if ctx.stop => break ExitReason::Stopped,
+                Ok(()) => {}
+                Err(_) => {
+                    if !may_restart(restart, restarts) {
+                        return ActorExit { reason: ExitReason::Panicked, restarts };
+                    }
+                    restarts += 1;
+                    actor = match start(&mut factory, restart, &mut restarts) {
+                        Some(actor) => actor,
+                        None => return ActorExit { reason: ExitReason::Panicked, restarts }
+                    };
+                }
+            }
+        };
+
+        match panic::catch_unwind(AssertUnwindSafe(|| actor.stopped())) {
+            Ok(()) => ActorExit { reason, restarts },
+            Err(_) => ActorExit { reason: ExitReason::Panicked, restarts }
+        }
+    });
+
+    (ActorRef { sender }, ActorHandle { thread })
+}
+
+fn may_restart(restart: Restart, restarts: u32) -> bool {
+    match restart {
+        Restart::Never => false,
+        Restart::UpTo(limit) => restarts < limit,
+        Restart::Always => true
+    }
+}
+
+/// Builds and starts an actor, retrying while the policy allows. Returns
+/// `None` once a panic uses up the last restart.
+fn start<A, F>(factory: &mut F, restart: Restart, restarts: &mut u32) -> Option<A>
+where
+    A: Actor,
+    F: FnMut() -> A
+{
+    loop {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut actor: A = factory();
+            actor.started();
+            actor
+        }));
+        match result {
+            Ok(actor) => return Some(actor),
+            Err(_) if may_restart(restart, *restarts) => *restarts += 1,
+            Err(_) => return None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{ Arc, Mutex };
+
+    enum CounterMessage {
+        Add(u32),
+        Get(Reply<u32>),
+        Fail
+    }
+
+    struct Counter {
+        total: u32,
+        log: Arc<Mutex<Vec<String>>>
+    }
+
+    impl Actor for Counter {
+        type Message = CounterMessage;
+
+        fn handle(&mut self, message: CounterMessage, _ctx: &mut Context) {
+            match message {
+                CounterMessage::Add(amount) => self.total += amount,
+                CounterMessage::Get(reply) => reply.send(self.total),
+                CounterMessage::Fail => panic!("Counter failed")
+            }
+        }
+
+        fn started(&mut self) {
+            self.log.lock().unwrap().push(String::from("started"));
+        }
+
+        fn stopped(&mut self) {
+            self.log.lock().unwrap().push(format!("stopped at {}", self.total));
+        }
+    }
+
+    fn counter(log: &Arc<Mutex<Vec<String>>>) -> Counter {
+        Counter { total: 0, log: Arc::clone(log) }
+    }
+
+    #[test]
+    fn test_ask_and_orderly_stop() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let (counter_ref, handle) = spawn(counter(&log));
+
+        let mut senders = vec![];
+        for _ in 0..4 {
+            let counter_ref = counter_ref.clone();
+            senders.push(thread::spawn(move || {
+                for _ in 0..10 {
+                    counter_ref.send(CounterMessage::Add(1)).unwrap();
+                }
+            }));
+        }
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        assert_eq!(counter_ref.ask(CounterMessage::Get), Ok(40));
+
+        counter_ref.send(CounterMessage::Add(2)).unwrap();
+        assert!(counter_ref.stop());
+        let exit: ActorExit = handle.join();
+
+        assert_eq!(exit, ActorExit { reason: ExitReason::Stopped, restarts: 0 });
+        assert_eq!(*log.lock().unwrap(), vec!["started", "stopped at 42"]);
+        assert!(counter_ref.send(CounterMessage::Add(1)).is_err());
+        assert_eq!(counter_ref.ask(CounterMessage::Get), Err(AskError::Stopped));
+    }
+
+    #[test]
+    fn test_supervisor_restarts_with_fresh_state() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let factory_log = Arc::clone(&log);
+        let (counter_ref, handle) = spawn_supervised(move || counter(&factory_log), Restart::UpTo(1));
+
+        counter_ref.send(CounterMessage::Add(5)).unwrap();
+        counter_ref.send(CounterMessage::Fail).unwrap();
+        assert_eq!(counter_ref.ask(CounterMessage::Get), Ok(0));
+
+        counter_ref.send(CounterMessage::Fail).unwrap();
+        let exit: ActorExit = handle.join();
+        assert_eq!(exit, ActorExit { reason: ExitReason::Panicked, restarts: 1 });
+        assert_eq!(*log.lock().unwrap(), vec!["started", "started"]);
+    }
+
+    #[test]
+    fn test_dropped_reply_and_disconnect() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let (counter_ref, handle) = spawn_supervised({
+            let log = Arc::clone(&log);
+            move || counter(&log)
+        }, Restart::Always);
+
+        let answer = counter_ref.ask(|_reply| CounterMessage::Fail);
+        assert_eq!(answer, Err::<u32, AskError>(AskError::NoReply));
+
+        drop(counter_ref);
+        assert_eq!(handle.join().reason, ExitReason::Disconnected);
+    }
+
+    #[test]
+    fn test_panics_while_starting_use_up_restarts() {
+        let log: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let mut builds: u32 = 0;
+        let factory_log = Arc::clone(&log);
+        let (counter_ref, handle) = spawn_supervised(move || {
+            builds += 1;
+            assert!(builds > 1, "first build fails");
+            counter(&factory_log)
+        }, Restart::UpTo(1));
+
+        assert_eq!(counter_ref.ask(CounterMessage::Get), Ok(0));
+        counter_ref.stop();
+        assert_eq!(handle.join(), ActorExit { reason: ExitReason::Stopped, restarts: 1 });
+
+        let (counter_ref, handle) = spawn_supervised(|| -> Counter { panic!("never builds") }, Restart::UpTo(2));
+        assert_eq!(handle.join(), ActorExit { reason: ExitReason::Panicked, restarts: 2 });
+        assert_eq!(counter_ref.ask(CounterMessage::Get), Err(AskError::Stopped));
+    }
+}
@@ -0,0 +1,60 @@
+use concurrency::actor::{ self, Actor, ActorRef, Context, Reply, Restart };
+use std::collections::HashMap;
+use std::thread;
+
+enum ScoreMessage {
+    Add(String, i32),
+    Get(String, Reply<Option<i32>>),
+    Reset
+}
+
+#[derive(Default)]
+struct ScoreBoard {
+    scores: HashMap<String, i32>
+}
+
+impl Actor for ScoreBoard {
+    type Message = ScoreMessage;
+
+    fn handle(&mut self, message: ScoreMessage, ctx: &mut Context) {
+        match message {
+            ScoreMessage::Add(team, runs) => *self.scores.entry(team).or_insert(0) += runs,
+            ScoreMessage::Get(team, reply) => reply.send(self.scores.get(&team).copied()),
+            ScoreMessage::Reset => panic!("Scoreboard reset after {} restarts", ctx.restarts())
+        }
+    }
+
+    fn started(&mut self) {
+        println!("Scoreboard started");
+    }
+
+    fn stopped(&mut self) {
+        println!("Scoreboard stopped with {:?}", self.scores);
+    }
+}
+
+fn main() {
+    let (board, handle) = actor::spawn_supervised(ScoreBoard::default, Restart::Always);
+
+    let mut producers = vec![];
+    for team in ["RCB", "SRH", "CSK"].iter() {
+        let board: ActorRef<ScoreMessage> = board.clone();
+        producers.push(thread::spawn(move || {
+            for runs in 1..=3 {
+                board.send(ScoreMessage::Add(team.to_string(), runs)).unwrap();
+            }
+        }));
+    }
+    for producer in producers {
+        producer.join().unwrap();
+    }
+
+    println!("RCB: {:?}", board.ask(|reply| ScoreMessage::Get("RCB".to_string(), reply)));
+
+    board.send(ScoreMessage::Reset).unwrap();
+    println!("RCB after restart: {:?}", board.ask(|reply| ScoreMessage::Get("RCB".to_string(), reply)));
+
+    board.send(ScoreMessage::Add("CSK".to_string(), 23)).unwrap();
+    board.stop();
+    println!("Exit: {:?}", handle.join());
+}
@@ -0,0 +1,42 @@
+use concurrency::executor::{ self, Executor, JoinHandle };
+use std::time::Duration;
+
+fn main() {
+    let executor: Executor = Executor::new();
+
+    executor.block_on(async {
+        let (tx, mut rx) = executor::channel();
+        let tx_clone = executor::Sender::clone(&tx);
+
+        let first: JoinHandle<()> = executor::spawn(async move {
+            let messages: Vec<String> = vec![
+                "Hello World!".to_string(),
+                "I'm Express".to_string(),
+                "Goodbye".to_string()
+            ];
+
+            for message in messages {
+                tx.send(message).unwrap();
+                executor::sleep(Duration::from_millis(200)).await;
+            }
+        });
+
+        let second: JoinHandle<()> = executor::spawn(async move {
+            let messages: Vec<String> = vec![
+                "Sadness".to_string(),
+                "Drowning in Sadness".to_string()
+            ];
+
+            for message in messages {
+                tx_clone.send(message).unwrap();
+                executor::sleep(Duration::from_millis(100)).await;
+            }
+        });
+
+        while let Some(received) = rx.recv().await {
+            println!("Got: {}", received);
+        }
+
+        executor::join_all(vec![first, second]).await;
+    });
+}
@@ -0,0 +1,46 @@
+use concurrency::channel::{ self, Receiver, Sender };
+use concurrency::select;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let (tx, rx): (Sender<String>, Receiver<String>) = channel::bounded(1);
+    let (tick_tx, tick_rx): (Sender<String>, Receiver<String>) = channel::bounded(1);
+
+    thread::spawn(move || {
+        let messages: Vec<String> = vec![
+            "Hello World!".to_string(),
+            "I'm Express".to_string(),
+            "Goodbye".to_string()
+        ];
+
+        for message in messages {
+            // Blocks while the consumer still hasn't taken the last message.
+            tx.send(message).unwrap();
+        }
+    });
+
+    thread::spawn(move || {
+        for tick in 1..=3 {
+            tick_tx.send(format!("Tick {}", tick)).unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    });
+
+    let remaining: &Receiver<String> = loop {
+        select! {
+            recv(rx) -> message => match message {
+                Ok(message) => println!("Got: {}", message),
+                Err(_) => break &tick_rx
+            },
+            recv(tick_rx) -> message => match message {
+                Ok(message) => println!("Got: {}", message),
+                Err(_) => break &rx
+            },
+        }
+    };
+
+    for message in remaining {
+        println!("Got: {}", message);
+    }
+}
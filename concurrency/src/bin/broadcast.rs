@@ -0,0 +1,34 @@
+use concurrency::broadcast::{ Bus, Message, RecvError, Subscription };
+use std::thread;
+
+fn main() {
+    let bus: Bus<String> = Bus::new(8);
+    let mut handles = vec![];
+
+    let subscribers: [(&str, &[&str]); 2] = [
+        ("Scores", &["scores"]),
+        ("Everything", &["scores", "news"])
+    ];
+
+    for (name, topics) in subscribers.iter().copied() {
+        let subscription: Subscription<String> = bus.subscribe(topics);
+
+        let handle = thread::spawn(move || loop {
+            match subscription.recv() {
+                Ok(Message { topic, payload }) => println!("{} got [{}] {}", name, topic, payload),
+                Err(RecvError::Lagged(missed)) => println!("{} missed {} messages", name, missed),
+                Err(RecvError::Closed) => break
+            }
+        });
+        handles.push(handle);
+    }
+
+    bus.publish("scores", "RCB 23".to_string());
+    bus.publish("news", "Match delayed".to_string());
+    bus.publish("scores", "SRH 22".to_string());
+    drop(bus);
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
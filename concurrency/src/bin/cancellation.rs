@@ -0,0 +1,41 @@
+use concurrency::cancel::{ self, CancellationToken, TimeoutError };
+use concurrency::channel::{ self, Receiver, Sender };
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let (tx, rx): (Sender<String>, Receiver<String>) = channel::bounded(4);
+    let shutdown: CancellationToken = CancellationToken::new();
+
+    let producer_token: CancellationToken = shutdown.child_token();
+    let producer = thread::spawn(move || {
+        let mut count: u32 = 0;
+        loop {
+            count += 1;
+            if tx.send(format!("Message {}", count)).is_err() {
+                break;
+            }
+            // Unlike `thread::sleep`, this returns as soon as we shut down.
+            if producer_token.sleep(Duration::from_millis(20)).is_err() {
+                println!("Producer stopping after {} messages", count);
+                break;
+            }
+        }
+    });
+
+    shutdown.cancel_after(Duration::from_millis(70));
+    while let Ok(message) = shutdown.recv(&rx) {
+        println!("Got: {}", message);
+    }
+    producer.join().unwrap();
+
+    let slow = cancel::spawn_with_timeout(Duration::from_millis(30), |token| {
+        while token.sleep(Duration::from_millis(5)).is_ok() {}
+        "gave up"
+    });
+    match slow.join() {
+        Ok(result) => println!("Finished: {}", result),
+        Err(TimeoutError::TimedOut) => println!("Timed out"),
+        Err(TimeoutError::Panicked(_)) => println!("Panicked")
+    }
+}
@@ -1,3 +1,4 @@
+use concurrency::sharded::ShardedCounter;
 use std::sync::{ Mutex, Arc };
 use std::thread;
 
@@ -31,4 +32,23 @@ fn main() {
 
         println!("Result: {}", *counter.lock().unwrap());
     }
+
+    {
+        let counter: Arc<ShardedCounter> = Arc::new(ShardedCounter::new());
+        let mut handles = vec![];
+
+        for _ in 0..10 {
+            let counter = Arc::clone(&counter);
+            let handle = thread::spawn(move || {
+                counter.increment();
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        println!("Sharded Result: {}", counter.get());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,18 @@
+use concurrency::par_iter::{ par_for_each, par_map, par_reduce };
+use std::thread;
+
+fn main() {
+    let vector: Vec<u32> = vec![1, 2, 3, 4, 5];
+    let threads: usize = 3;
+
+    // Unlike `thread::spawn` with `move`, the vector is only borrowed.
+    par_for_each(&vector, threads, |num| {
+        println!("Vector num: {} from {:?}", num, thread::current().id());
+    });
+
+    let squares: Vec<u32> = par_map(&vector, threads, |num| num * num);
+    println!("Squares: {:?}", squares);
+
+    let sum: u32 = par_reduce(&vector, threads, || 0, |acc, num| acc + num, |a, b| a + b);
+    println!("Sum of {:?}: {}", vector, sum);
+}
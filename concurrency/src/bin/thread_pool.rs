@@ -0,0 +1,29 @@
+use concurrency::{ JobHandle, ThreadPool };
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let pool: ThreadPool = ThreadPool::new(3);
+
+    let handles: Vec<JobHandle<u32>> = (1..=6)
+        .map(|i| {
+            pool.execute(move || {
+                println!("Job {} on {}", i, thread::current().name().unwrap_or("?"));
+                thread::sleep(Duration::from_millis(1));
+                i * i
+            })
+        })
+        .collect();
+
+    let failed: JobHandle<u32> = pool.execute(|| panic!("This job fails"));
+
+    for handle in handles {
+        println!("Result: {}", handle.join().unwrap());
+    }
+
+    if failed.join().is_err() {
+        println!("The failing job panicked, the pool is still running");
+    }
+
+    println!("Still works: {}", pool.execute(|| 42).join().unwrap());
+}
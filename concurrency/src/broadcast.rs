@@ -0,0 +1,377 @@
+use crate::channel::{ self, Receiver, Sender };
+use std::collections::{ HashMap, HashSet };
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::mpsc::{ self as std_mpsc, TrySendError };
+use std::sync::{ Arc, Mutex, MutexGuard, Weak };
+use std::time::Duration;
+
+/// A message delivered to a [`Subscription`], tagged with its topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message<T> {
+    pub topic: String,
+    pub payload: T
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The subscriber fell behind and this many messages were dropped for it.
+    Lagged(usize),
+    /// Every [`Bus`] handle has been dropped and the buffer is drained.
+    Closed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Lagged(usize),
+    Closed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Lagged(usize),
+    Closed
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(missed) => write!(f, "subscriber lagged and missed {} messages", missed),
+            RecvError::Closed => f.write_str("bus closed")
+        }
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("no message available"),
+            TryRecvError::Lagged(missed) => write!(f, "subscriber lagged and missed {} messages", missed),
+            TryRecvError::Closed => f.write_str("bus closed")
+        }
+    }
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => f.write_str("timed out waiting for a message"),
+            RecvTimeoutError::Lagged(missed) => write!(f, "subscriber lagged and missed {} messages", missed),
+            RecvTimeoutError::Closed => f.write_str("bus closed")
+        }
+    }
+}
+
+impl Error for RecvError {}
+impl Error for TryRecvError {}
+impl Error for RecvTimeoutError {}
+
+struct Slot<T> {
+    topics: HashSet<String>,
+    sender: Sender<Message<T>>,
+    missed: Arc<AtomicUsize>
+}
+
+struct Registry<T> {
+    next_id: u64,
+    subscribers: HashMap<u64, Slot<T>>
+}
+
+/// A topic-based publish/subscribe bus.
+///
+/// Every subscriber gets its own copy of each message published on a topic
+/// it follows. Each subscriber has a bounded buffer; when a subscriber falls
+/// behind, new messages for it are dropped and its next receive reports
+/// [`RecvError::Lagged`] instead of slowing the publisher down.
+///
+/// Clone the bus to publish from several threads. Subscribers see the bus as
+/// closed once every handle has been dropped.
+pub struct Bus<T> {
+    registry: Arc<Mutex<Registry<T>>>,
+    capacity: usize
+}
+
+impl<T: Clone> Bus<T> {
+    /// Creates a bus where each subscriber buffers up to `capacity` messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Bus<T> {
+        assert!(capacity > 0, "A bus needs a subscriber capacity of at least one");
+
+        Bus {
+            registry: Arc::new(Mutex::new(Registry {
+                next_id: 0,
+                subscribers: HashMap::new()
+            })),
+            capacity
+        }
+    }
+
+    /// Subscribes to `topics`. More can be added later with
+    /// [`Subscription::subscribe`].
+    pub fn subscribe(&self, topics: &[&str]) -> Subscription<T> {
+        let (sender, receiver) = channel::bounded(self.capacity);
+        let missed: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        let mut registry = lock(&self.registry);
+        let id: u64 = registry.next_id;
+        registry.next_id += 1;
+        registry.subscribers.insert(id, Slot {
+            topics: topics.iter().map(|topic| topic.to_string()).collect(),
+            sender,
+            missed: Arc::clone(&missed)
+        });
+
+        Subscription {
+            id,
+            receiver,
+            missed,
+            registry: Arc::downgrade(&self.registry)
+        }
+    }
+
+    /// Publishes `payload` on `topic` and returns how many subscribers it
+    /// was delivered to. Lagging subscribers are skipped, never waited on.
+    pub fn publish(&self, topic: &str, payload: T) -> usize {
+        let mut registry = lock(&self.registry);
+        let mut delivered: usize = 0;
+        let mut gone: Vec<u64> = Vec::new();
+
+        for (id, slot) in registry.subscribers.iter() {
+            if !slot.topics.contains(topic) {
+                continue;
+            }
+
+            let message: Message<T> = Message {
+                topic: topic.to_string(),
+                payload: payload.clone()
+            };
+
+            match slot.sender.try_send(message) {
+                Ok(()) => delivered += 1,
+                Err(TrySendError::Full(_)) => {
+                    slot.missed.fetch_add(1, Ordering::SeqCst);
+                }
+                Err(TrySendError::Disconnected(_)) => gone.push(*id)
+            }
+        }
+
+        for id in gone {
+            registry.subscribers.remove(&id);
+        }
+
+        delivered
+    }
+
+    /// Number of live subscribers following `topic`.
+    pub fn subscriber_count(&self, topic: &str) -> usize {
+        lock(&self.registry)
+            .subscribers
+            .values()
+            .filter(|slot| slot.topics.contains(topic))
+            .count()
+    }
+}
+
+impl<T> Clone for Bus<T> {
+    fn clone(&self) -> Bus<T> {
+        Bus {
+            registry: Arc::clone(&self.registry),
+            capacity: self.capacity
+        }
+    }
+}
+
+impl<T> fmt::Debug for Bus<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bus").field("capacity", &self.capacity).finish()
+    }
+}
+
+/// One subscriber's view of a [`Bus`]. Dropping it unsubscribes.
+pub struct Subscription<T> {
+    id: u64,
+    receiver: Receiver<Message<T>>,
+    missed: Arc<AtomicUsize>,
+    registry: Weak<Mutex<Registry<T>>>
+}
+
+impl<T> Subscription<T> {
+    pub fn recv(&self) -> Result<Message<T>, RecvError> {
+        if let Some(missed) = self.take_missed() {
+            return Err(RecvError::Lagged(missed));
+        }
+
+        self.receiver.recv().map_err(|_| RecvError::Closed)
+    }
+
+    pub fn try_recv(&self) -> Result<Message<T>, TryRecvError> {
+        if let Some(missed) = self.take_missed() {
+            return Err(TryRecvError::Lagged(missed));
+        }
+
+        self.receiver.try_recv().map_err(|error| match error {
+            std_mpsc::TryRecvError::Empty => TryRecvError::Empty,
+            std_mpsc::TryRecvError::Disconnected => TryRecvError::Closed
+        })
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Message<T>, RecvTimeoutError> {
+        if let Some(missed) = self.take_missed() {
+            return Err(RecvTimeoutError::Lagged(missed));
+        }
+
+        self.receiver.recv_timeout(timeout).map_err(|error| match error {
+            std_mpsc::RecvTimeoutError::Timeout => RecvTimeoutError::Timeout,
+            std_mpsc::RecvTimeoutError::Disconnected => RecvTimeoutError::Closed
+        })
+    }
+
+    /// Starts following another topic. Does nothing once the bus is closed.
+    pub fn subscribe(&self, topic: &str) {
+        self.with_slot(|slot| {
+            slot.topics.insert(topic.to_string());
+        });
+    }
+
+    /// Stops following `topic`. Messages already buffered are still received.
+    pub fn unsubscribe(&self, topic: &str) {
+        self.with_slot(|slot| {
+            slot.topics.remove(topic);
+        });
+    }
+
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = Vec::new();
+        self.with_slot(|slot| topics.extend(slot.topics.iter().cloned()));
+        topics.sort();
+        topics
+    }
+
+    /// Messages dropped for this subscriber that haven't been reported yet.
+    pub fn pending_lag(&self) -> usize {
+        self.missed.load(Ordering::SeqCst)
+    }
+
+    fn take_missed(&self) -> Option<usize> {
+        match self.missed.swap(0, Ordering::SeqCst) {
+            0 => None,
+            missed => Some(missed)
+        }
+    }
+
+    fn with_slot<F: FnOnce(&mut Slot<T>)>(&self, f: F) {
+        if let Some(registry) = self.registry.upgrade() {
+            if let Some(slot) = lock(&registry).subscribers.get_mut(&self.id) {
+                f(slot);
+            }
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            lock(&registry).subscribers.remove(&self.id);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription").field("id", &self.id).finish()
+    }
+}
+
+fn lock<T>(registry: &Mutex<Registry<T>>) -> MutexGuard<'_, Registry<T>> {
+    registry.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_every_subscriber_gets_every_message() {
+        let bus: Bus<u32> = Bus::new(16);
+        let subscribers: Vec<Subscription<u32>> = (0..3).map(|_| bus.subscribe(&["scores"])).collect();
+
+        let publisher: Bus<u32> = bus.clone();
+        thread::spawn(move || {
+            for score in 0..5 {
+                assert_eq!(publisher.publish("scores", score), 3);
+            }
+        })
+        .join()
+        .unwrap();
+        drop(bus);
+
+        for subscriber in subscribers {
+            let payloads: Vec<u32> = std::iter::from_fn(|| subscriber.recv().ok())
+                .map(|message| message.payload)
+                .collect();
+            assert_eq!(payloads, vec![0, 1, 2, 3, 4]);
+            assert_eq!(subscriber.recv(), Err(RecvError::Closed));
+        }
+    }
+
+    #[test]
+    fn test_topic_filtering() {
+        let bus: Bus<&str> = Bus::new(4);
+        let news = bus.subscribe(&["news"]);
+        let both = bus.subscribe(&["news", "sports"]);
+
+        assert_eq!(bus.publish("sports", "goal"), 1);
+        assert_eq!(bus.publish("news", "headline"), 2);
+        assert_eq!(bus.publish("weather", "rain"), 0);
+
+        assert_eq!(news.try_recv().unwrap().payload, "headline");
+        assert_eq!(news.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(both.try_recv().unwrap().topic, "sports");
+        assert_eq!(both.try_recv().unwrap().topic, "news");
+    }
+
+    #[test]
+    fn test_lagging_subscriber() {
+        let bus: Bus<u32> = Bus::new(2);
+        let slow = bus.subscribe(&["ticks"]);
+        let fast = bus.subscribe(&["ticks"]);
+
+        for tick in 0..5 {
+            bus.publish("ticks", tick);
+            if let Ok(message) = fast.try_recv() {
+                assert_eq!(message.payload, tick);
+            }
+        }
+
+        assert_eq!(slow.pending_lag(), 3);
+        assert_eq!(slow.recv(), Err(RecvError::Lagged(3)));
+        assert_eq!(slow.recv().unwrap().payload, 0);
+        assert_eq!(slow.recv().unwrap().payload, 1);
+        assert_eq!(
+            slow.recv_timeout(Duration::from_millis(5)),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let bus: Bus<u32> = Bus::new(4);
+        let subscriber = bus.subscribe(&["a"]);
+        subscriber.subscribe("b");
+        assert_eq!(subscriber.topics(), vec!["a", "b"]);
+
+        subscriber.unsubscribe("a");
+        assert_eq!(bus.publish("a", 1), 0);
+        assert_eq!(bus.publish("b", 2), 1);
+
+        drop(subscriber);
+        assert_eq!(bus.subscriber_count("b"), 0);
+    }
+}
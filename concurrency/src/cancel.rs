@@ -0,0 +1,417 @@
+use crate::channel::{ Receiver, Signal, Watch };
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::TryRecvError;
+use std::sync::{ Arc, Condvar, Mutex, MutexGuard, Weak };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// Error returned when a wait was cut short by cancellation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("operation was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// Error returned by [`CancellationToken::recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelRecvError {
+    Cancelled,
+    Disconnected
+}
+
+impl fmt::Display for CancelRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CancelRecvError::Cancelled => f.write_str("receive was cancelled"),
+            CancelRecvError::Disconnected => f.write_str("receiving on a closed channel")
+        }
+    }
+}
+
+impl Error for CancelRecvError {}
+
+type Callback = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct NodeState {
+    cancelled: bool,
+    children: Vec<Weak<Node>>,
+    callbacks: Vec<(u64, Callback)>,
+    next_callback: u64
+}
+
+#[derive(Default)]
+struct Node {
+    state: Mutex<NodeState>,
+    condvar: Condvar
+}
+
+impl Node {
+    fn lock(&self) -> MutexGuard<'_, NodeState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn cancel(&self) {
+        let (children, callbacks) = {
+            let mut state = self.lock();
+            if state.cancelled {
+                return;
+            }
+            state.cancelled = true;
+            let callbacks: Vec<Callback> = state.callbacks.drain(..).map(|(_, callback)| callback).collect();
+            (std::mem::take(&mut state.children), callbacks)
+        };
+        self.condvar.notify_all();
+
+        for callback in callbacks {
+            callback();
+        }
+        for child in children {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+/// A cooperative cancellation flag shared between threads.
+///
+/// Clones share the same flag. [`child_token`](CancellationToken::child_token)
+/// creates a token that is cancelled along with its parent but can also be
+/// cancelled on its own without affecting the parent.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    node: Arc<Node>
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Creates a token that is cancelled whenever this one is.
+    pub fn child_token(&self) -> CancellationToken {
+        let child: CancellationToken = CancellationToken::new();
+
+        let mut state = self.node.lock();
+        if state.cancelled {
+            drop(state);
+            child.cancel();
+        } else {
+            state.children.retain(|child| child.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child.node));
+        }
+
+        child
+    }
+
+    /// Cancels this token and all of its children. Cancelling twice is a no-op.
+    pub fn cancel(&self) {
+        self.node.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.node.lock().cancelled
+    }
+
+    /// Returns `Err(Cancelled)` if the token has been cancelled, for use
+    /// with `?` between steps of a long-running job.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Cancels this token after `timeout` from a background timer thread.
+    /// The timer exits early if the token is cancelled first.
+    pub fn cancel_after(&self, timeout: Duration) {
+        let token: CancellationToken = self.clone();
+        thread::spawn(move || {
+            if token.sleep(timeout).is_ok() {
+                token.cancel();
+            }
+        });
+    }
+
+    /// Blocks until the token is cancelled.
+    pub fn wait(&self) {
+        let mut state = self.node.lock();
+        while !state.cancelled {
+            state = self.node.condvar.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+    }
+
+    /// Sleeps for `duration`, waking early with `Err(Cancelled)` if the
+    /// token is cancelled in the meantime. A duration too long to add to
+    /// `Instant::now()` sleeps until cancelled.
+    pub fn sleep(&self, duration: Duration) -> Result<(), Cancelled> {
+        let deadline: Option<Instant> = Instant::now().checked_add(duration);
+        let mut state = self.node.lock();
+
+        loop {
+            if state.cancelled {
+                return Err(Cancelled);
+            }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let now: Instant = Instant::now();
+                    if now >= deadline {
+                        return Ok(());
+                    }
+                    self.node.condvar
+                        .wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|p| p.into_inner())
+                        .0
+                }
+                None => self.node.condvar.wait(state).unwrap_or_else(|p| p.into_inner())
+            };
+        }
+    }
+
+    /// Receives from a [`bounded`](crate::channel::bounded) channel, giving
+    /// up as soon as the token is cancelled. Cancellation wins over a message
+    /// that arrives at the same time.
+    pub fn recv<T>(&self, receiver: &Receiver<T>) -> Result<T, CancelRecvError> {
+        let signal: Arc<Signal> = Arc::new(Signal::new());
+        receiver.watch(&signal);
+
+        let callback_signal: Arc<Signal> = Arc::clone(&signal);
+        let id: Option<u64> = self.on_cancel(Arc::new(move || callback_signal.notify()));
+
+        let result: Result<T, CancelRecvError> = loop {
+            if self.is_cancelled() {
+                break Err(CancelRecvError::Cancelled);
+            }
+            match receiver.try_recv() {
+                Ok(value) => break Ok(value),
+                Err(TryRecvError::Disconnected) => break Err(CancelRecvError::Disconnected),
+                Err(TryRecvError::Empty) => {
+                    signal.wait(None);
+                }
+            }
+        };
+
+        if let Some(id) = id {
+            self.node.lock().callbacks.retain(|(callback_id, _)| *callback_id != id);
+        }
+        receiver.unwatch(&signal);
+
+        result
+    }
+
+    /// Registers `callback` to run on cancellation. Runs it right away and
+    /// returns `None` if the token is already cancelled.
+    fn on_cancel(&self, callback: Callback) -> Option<u64> {
+        let mut state = self.node.lock();
+        if state.cancelled {
+            drop(state);
+            callback();
+            return None;
+        }
+
+        let id: u64 = state.next_callback;
+        state.next_callback += 1;
+        state.callbacks.push((id, callback));
+        Some(id)
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Error returned by [`TimeoutJoinHandle::join`].
+pub enum TimeoutError {
+    /// The deadline passed before the closure returned. The closure saw its
+    /// token cancelled and has since stopped.
+    TimedOut,
+    /// The closure panicked; this is the panic payload.
+    Panicked(Box<dyn Any + Send + 'static>)
+}
+
+impl fmt::Debug for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutError::TimedOut => f.write_str("TimedOut"),
+            TimeoutError::Panicked(_) => f.write_str("Panicked(..)")
+        }
+    }
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutError::TimedOut => f.write_str("spawned work timed out"),
+            TimeoutError::Panicked(_) => f.write_str("spawned work panicked")
+        }
+    }
+}
+
+impl Error for TimeoutError {}
+
+/// Handle returned by [`spawn_with_timeout`].
+#[derive(Debug)]
+pub struct TimeoutJoinHandle<T> {
+    handle: thread::JoinHandle<(T, bool)>,
+    token: CancellationToken
+}
+
+impl<T> TimeoutJoinHandle<T> {
+    /// The token handed to the spawned closure.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Cancels the spawned closure before its deadline.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Waits for the thread to exit. A closure that was still running when
+    /// its deadline passed is reported as [`TimeoutError::TimedOut`], even if
+    /// it then returned a value, since it may have cut its work short.
+    pub fn join(self) -> Result<T, TimeoutError> {
+        match self.handle.join() {
+            Err(payload) => Err(TimeoutError::Panicked(payload)),
+            Ok((_, true)) => Err(TimeoutError::TimedOut),
+            Ok((value, false)) => Ok(value)
+        }
+    }
+}
+
+/// Spawns `f` on a new thread and cancels the token passed to it once
+/// `timeout` has elapsed.
+///
+/// Cancellation is cooperative: `f` should check the token, or use its
+/// [`sleep`](CancellationToken::sleep) and [`recv`](CancellationToken::recv),
+/// so it returns promptly after the deadline.
+pub fn spawn_with_timeout<F, T>(timeout: Duration, f: F) -> TimeoutJoinHandle<T>
+where
+    F: FnOnce(CancellationToken) -> T + Send + 'static,
+    T: Send + 'static
+{
+    let token: CancellationToken = CancellationToken::new();
+    let timed_out: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+    let timer_token: CancellationToken = token.clone();
+    let timer_flag: Arc<AtomicBool> = Arc::clone(&timed_out);
+    thread::spawn(move || {
+        if timer_token.sleep(timeout).is_ok() {
+            timer_flag.store(true, Ordering::SeqCst);
+            timer_token.cancel();
+        }
+    });
+
+    let worker_token: CancellationToken = token.clone();
+    let handle: thread::JoinHandle<(T, bool)> = thread::spawn(move || {
+        // Finishing early, or panicking, also stops the timer thread.
+        let _stop_timer: CancelOnDrop = CancelOnDrop(worker_token.clone());
+        let value: T = f(worker_token);
+        (value, timed_out.load(Ordering::SeqCst))
+    });
+
+    TimeoutJoinHandle { handle, token }
+}
+
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel;
+
+    #[test]
+    fn test_children_follow_parent() {
+        let parent: CancellationToken = CancellationToken::new();
+        let child: CancellationToken = parent.child_token();
+        let grandchild: CancellationToken = child.child_token();
+        let sibling: CancellationToken = parent.child_token();
+
+        child.cancel();
+        assert!(child.is_cancelled() && grandchild.is_cancelled());
+        assert!(!parent.is_cancelled() && !sibling.is_cancelled());
+
+        parent.cancel();
+        assert!(sibling.is_cancelled());
+        assert!(parent.child_token().is_cancelled());
+        assert_eq!(parent.check(), Err(Cancelled));
+    }
+
+    #[test]
+    fn test_sleep_wakes_on_cancel() {
+        let token: CancellationToken = CancellationToken::new();
+        assert_eq!(token.sleep(Duration::from_millis(1)), Ok(()));
+
+        let canceller: CancellationToken = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+
+        let start: Instant = Instant::now();
+        assert_eq!(token.sleep(Duration::MAX), Err(Cancelled));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_recv_is_cancellable() {
+        let (tx, rx) = channel::bounded::<u32>(1);
+        let token: CancellationToken = CancellationToken::new();
+
+        tx.send(1).unwrap();
+        assert_eq!(token.recv(&rx), Ok(1));
+
+        token.cancel_after(Duration::from_millis(10));
+        assert_eq!(token.recv(&rx), Err(CancelRecvError::Cancelled));
+
+        let token: CancellationToken = CancellationToken::new();
+        drop(tx);
+        assert_eq!(token.recv(&rx), Err(CancelRecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_spawn_with_timeout() {
+        let quick = spawn_with_timeout(Duration::from_secs(30), |_token| 5);
+        assert_eq!(quick.join().unwrap(), 5);
+
+        let slow = spawn_with_timeout(Duration::from_millis(10), |token| {
+            let mut steps: u32 = 0;
+            while token.sleep(Duration::from_millis(1)).is_ok() {
+                steps += 1;
+            }
+            steps
+        });
+        assert!(matches!(slow.join(), Err(TimeoutError::TimedOut)));
+
+        let failing = spawn_with_timeout(Duration::MAX, |_token| -> u32 { panic!("boom") });
+        let token: CancellationToken = failing.token().clone();
+        assert!(matches!(failing.join(), Err(TimeoutError::Panicked(_))));
+        // The panic still cancels the token, which releases the timer thread.
+        assert!(token.is_cancelled());
+    }
+}
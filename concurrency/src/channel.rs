@@ -0,0 +1,704 @@
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::hash::{ BuildHasher, Hasher };
+use std::marker::PhantomData;
+use std::sync::mpsc::{ RecvError, RecvTimeoutError, SendError, TryRecvError, TrySendError };
+use std::sync::{ Arc, Condvar, Mutex, MutexGuard };
+use std::time::{ Duration, Instant };
+
+/// Creates a channel that holds at most `capacity` messages.
+///
+/// Once the buffer is full, `send` blocks until the receiver makes room, so a
+/// fast producer can't outrun a slow consumer and exhaust memory.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "A bounded channel needs a capacity of at least one");
+
+    let shared: Arc<Shared<T>> = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            senders: 1,
+            receiver_alive: true,
+            watchers: Vec::new()
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        capacity
+    });
+
+    let sender: Sender<T> = Sender { shared: Arc::clone(&shared) };
+    let receiver: Receiver<T> = Receiver { shared, _not_sync: PhantomData };
+
+    (sender, receiver)
+}
+
+/// Error returned by [`Sender::send_timeout`].
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    Timeout(T),
+    Disconnected(T)
+}
+
+impl<T> SendTimeoutError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendTimeoutError::Timeout(value) => value,
+            SendTimeoutError::Disconnected(value) => value
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => f.write_str("Timeout(..)"),
+            SendTimeoutError::Disconnected(_) => f.write_str("Disconnected(..)")
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendTimeoutError::Timeout(_) => f.write_str("timed out waiting on send operation"),
+            SendTimeoutError::Disconnected(_) => f.write_str("sending on a closed channel")
+        }
+    }
+}
+
+impl<T> Error for SendTimeoutError<T> {}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receiver_alive: bool,
+    watchers: Vec<Arc<Signal>>
+}
+
+impl<T> State<T> {
+    fn wake_watchers(&self) {
+        for watcher in &self.watchers {
+            watcher.notify();
+        }
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // No user code runs while the lock is held, so poisoning can't leave
+        // the queue half-updated.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The sending half of a [`bounded`] channel. Clone it for more producers.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>
+}
+
+impl<T> Sender<T> {
+    /// Sends `value`, blocking while the channel is full.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = self.shared.lock();
+
+        loop {
+            if !state.receiver_alive {
+                return Err(SendError(value));
+            }
+            if state.queue.len() < self.shared.capacity {
+                self.push(state, value);
+                return Ok(());
+            }
+            state = self.shared.not_full.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+    }
+
+    /// Sends `value` only if there is room right now.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let state = self.shared.lock();
+
+        if !state.receiver_alive {
+            Err(TrySendError::Disconnected(value))
+        } else if state.queue.len() == self.shared.capacity {
+            Err(TrySendError::Full(value))
+        } else {
+            self.push(state, value);
+            Ok(())
+        }
+    }
+
+    /// Sends `value`, waiting at most `timeout` for room in the channel. A
+    /// timeout too long to add to `Instant::now()` waits like `send`.
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let deadline: Instant = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return self.send(value).map_err(|error| SendTimeoutError::Disconnected(error.0))
+        };
+        let mut state = self.shared.lock();
+
+        loop {
+            if !state.receiver_alive {
+                return Err(SendTimeoutError::Disconnected(value));
+            }
+            if state.queue.len() < self.shared.capacity {
+                self.push(state, value);
+                return Ok(());
+            }
+
+            let now: Instant = Instant::now();
+            if now >= deadline {
+                return Err(SendTimeoutError::Timeout(value));
+            }
+            state = self.shared.not_full
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|p| p.into_inner())
+                .0;
+        }
+    }
+
+    /// Returns `true` once the receiver has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        !self.shared.lock().receiver_alive
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    fn push(&self, mut state: MutexGuard<'_, State<T>>, value: T) {
+        state.queue.push_back(value);
+        state.wake_watchers();
+        drop(state);
+        self.shared.not_empty.notify_one();
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.shared.lock().senders += 1;
+        Sender { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            state.wake_watchers();
+            drop(state);
+            self.shared.not_empty.notify_all();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").field("capacity", &self.shared.capacity).finish()
+    }
+}
+
+/// The receiving half of a [`bounded`] channel.
+///
+/// Like `std::sync::mpsc::Receiver` it is `Send` but not `Sync`, so a
+/// [`Select`] reporting it ready can't be raced by another thread.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    _not_sync: PhantomData<Cell<()>>
+}
+
+impl<T> Receiver<T> {
+    /// Blocks until a message arrives, or fails once every sender is gone
+    /// and the buffer has been drained.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut state = self.shared.lock();
+
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Ok(value);
+            }
+            if state.senders == 0 {
+                return Err(RecvError);
+            }
+            state = self.shared.not_empty.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+
+        match state.queue.pop_front() {
+            Some(value) => {
+                drop(state);
+                self.shared.not_full.notify_one();
+                Ok(value)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Like `recv`, but gives up after `timeout`. A timeout too long to add
+    /// to `Instant::now()` waits like `recv`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline: Instant = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return self.recv().map_err(|RecvError| RecvTimeoutError::Disconnected)
+        };
+        let mut state = self.shared.lock();
+
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Ok(value);
+            }
+            if state.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+
+            let now: Instant = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = self.shared.not_empty
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|p| p.into_inner())
+                .0;
+        }
+    }
+
+    /// Returns `true` once every sender has been dropped. Buffered messages
+    /// can still be received.
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.lock().senders == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    /// Blocking iterator that ends when the channel disconnects.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { receiver: self }
+    }
+
+    /// Iterator over the messages that are already buffered.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { receiver: self }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_alive = false;
+        self.shared.not_full.notify_all();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").field("capacity", &self.shared.capacity).finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    receiver: &'a Receiver<T>
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct TryIter<'a, T> {
+    receiver: &'a Receiver<T>
+}
+
+impl<'a, T> Iterator for TryIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    receiver: Receiver<T>
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { receiver: self }
+    }
+}
+
+/// A one-bit wakeup shared between a waiting thread and the channels (or
+/// cancellation tokens) it is waiting on.
+pub(crate) struct Signal {
+    notified: Mutex<bool>,
+    condvar: Condvar
+}
+
+impl Signal {
+    pub(crate) fn new() -> Signal {
+        Signal {
+            notified: Mutex::new(false),
+            condvar: Condvar::new()
+        }
+    }
+
+    pub(crate) fn notify(&self) {
+        *self.notified.lock().unwrap_or_else(|p| p.into_inner()) = true;
+        self.condvar.notify_all();
+    }
+
+    /// Waits for a notification. Returns `false` if the deadline passed first.
+    pub(crate) fn wait(&self, deadline: Option<Instant>) -> bool {
+        let mut notified = self.notified.lock().unwrap_or_else(|p| p.into_inner());
+
+        while !*notified {
+            match deadline {
+                Some(deadline) => {
+                    let now: Instant = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    notified = self.condvar
+                        .wait_timeout(notified, deadline - now)
+                        .unwrap_or_else(|p| p.into_inner())
+                        .0;
+                }
+                None => {
+                    notified = self.condvar.wait(notified).unwrap_or_else(|p| p.into_inner());
+                }
+            }
+        }
+
+        *notified = false;
+        true
+    }
+}
+
+pub(crate) trait Watch {
+    fn is_ready(&self) -> bool;
+    fn watch(&self, signal: &Arc<Signal>);
+    fn unwatch(&self, signal: &Arc<Signal>);
+}
+
+impl<T> Watch for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        let state = self.shared.lock();
+        !state.queue.is_empty() || state.senders == 0
+    }
+
+    fn watch(&self, signal: &Arc<Signal>) {
+        self.shared.lock().watchers.push(Arc::clone(signal));
+    }
+
+    fn unwatch(&self, signal: &Arc<Signal>) {
+        self.shared.lock().watchers.retain(|watcher| !Arc::ptr_eq(watcher, signal));
+    }
+}
+
+/// Waits on several receivers at once.
+///
+/// Each call to [`Select::recv`] registers a receiver and returns its index.
+/// The `select` methods return the index of a receiver whose next `recv`
+/// won't block, because it has a message buffered or has disconnected.
+/// See the [`select!`](crate::select) macro for the usual way to use it.
+pub struct Select<'a> {
+    receivers: Vec<&'a dyn Watch>
+}
+
+impl<'a> Select<'a> {
+    pub fn new() -> Select<'a> {
+        Select { receivers: Vec::new() }
+    }
+
+    pub fn recv<T>(&mut self, receiver: &'a Receiver<T>) -> usize {
+        self.receivers.push(receiver);
+        self.receivers.len() - 1
+    }
+
+    /// Returns the index of a ready receiver without blocking.
+    pub fn try_select(&self) -> Option<usize> {
+        let count: usize = self.receivers.len();
+        if count == 0 {
+            return None;
+        }
+
+        // Start at a random receiver so a busy one can't starve the rest.
+        let start: usize = RandomState::new().build_hasher().finish() as usize % count;
+        (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&index| self.receivers[index].is_ready())
+    }
+
+    /// Blocks until one of the receivers is ready.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no receivers were registered.
+    pub fn select(&self) -> usize {
+        assert!(!self.receivers.is_empty(), "Select needs at least one receiver");
+        self.wait(None).expect("Waiting without a deadline always finds a receiver")
+    }
+
+    /// Like [`Select::select`], but gives up after `timeout`.
+    pub fn select_timeout(&self, timeout: Duration) -> Option<usize> {
+        if self.receivers.is_empty() {
+            return None;
+        }
+        // A timeout too long to add to `Instant::now()` waits like `select`.
+        self.wait(Instant::now().checked_add(timeout))
+    }
+
+    fn wait(&self, deadline: Option<Instant>) -> Option<usize> {
+        if let Some(index) = self.try_select() {
+            return Some(index);
+        }
+
+        let signal: Arc<Signal> = Arc::new(Signal::new());
+        for receiver in &self.receivers {
+            receiver.watch(&signal);
+        }
+
+        // Check again after registering so a send that raced with the first
+        // check isn't missed.
+        let mut selected: Option<usize> = self.try_select();
+        while selected.is_none() {
+            if !signal.wait(deadline) {
+                break;
+            }
+            selected = self.try_select();
+        }
+
+        for receiver in &self.receivers {
+            receiver.unwatch(&signal);
+        }
+
+        selected
+    }
+}
+
+impl<'a> Default for Select<'a> {
+    fn default() -> Select<'a> {
+        Select::new()
+    }
+}
+
+impl<'a> fmt::Debug for Select<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select").field("receivers", &self.receivers.len()).finish()
+    }
+}
+
+/// Blocks on several [`Receiver`]s and runs the arm of the first one ready.
+///
+/// ```
+/// use concurrency::channel::bounded;
+/// use concurrency::select;
+///
+/// let (tx_one, rx_one) = bounded::<u32>(1);
+/// let (_tx_two, rx_two) = bounded::<&str>(1);
+/// tx_one.send(7).unwrap();
+///
+/// let got: u32 = select! {
+///     recv(rx_one) -> message => message.unwrap(),
+///     recv(rx_two) -> _message => 0,
+/// };
+/// assert_eq!(got, 7);
+/// ```
+///
+/// Each `message` is the `Result<T, RecvError>` of calling `recv` on that
+/// receiver, which is `Err` when the channel has disconnected.
+#[macro_export]
+macro_rules! select {
+    ($(recv($rx:expr) -> $res:pat => $body:expr),+ $(,)?) => {
+        $crate::select!(@bind []; $(recv($rx) -> $res => $body,)+)
+    };
+    // Evaluates each receiver expression once, binding it to a `receiver`
+    // that hygiene keeps apart from the others and from the caller's names.
+    (@bind [$($bound:ident $res:pat => $body:expr,)*]; recv($rx:expr) -> $next_res:pat => $next_body:expr, $($rest:tt)*) => {{
+        let receiver = &$rx;
+        $crate::select!(@bind [$($bound $res => $body,)* receiver $next_res => $next_body,]; $($rest)*)
+    }};
+    (@bind [$($bound:ident $res:pat => $body:expr,)*]; ) => {{
+        let mut select = $crate::channel::Select::new();
+        $( select.recv($bound); )*
+        let selected: usize = select.select();
+        drop(select);
+        $crate::select!(@dispatch selected, 0; $($bound $res => $body,)*)
+    }};
+    (@dispatch $selected:ident, $index:expr; ) => {
+        unreachable!("Select returned an index that has no arm")
+    };
+    (@dispatch $selected:ident, $index:expr; $receiver:ident $res:pat => $body:expr, $($rest:tt)*) => {
+        if $selected == $index {
+            let $res = $receiver.recv();
+            $body
+        } else {
+            $crate::select!(@dispatch $selected, $index + 1; $($rest)*)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_send_blocks_when_full() {
+        let (tx, rx) = bounded::<u32>(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+
+        assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(
+            tx.send_timeout(3, Duration::from_millis(10)),
+            Err(SendTimeoutError::Timeout(3))
+        );
+
+        let producer = thread::spawn(move || {
+            tx.send(3).unwrap();
+        });
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(rx.len(), 2);
+
+        assert_eq!(rx.recv(), Ok(1));
+        producer.join().unwrap();
+        assert_eq!(rx.iter().collect::<Vec<u32>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let (tx, rx) = bounded::<u32>(4);
+        let tx_clone: Sender<u32> = tx.clone();
+        tx.send(1).unwrap();
+        drop(tx);
+
+        assert!(!rx.is_disconnected());
+        drop(tx_clone);
+        assert!(rx.is_disconnected());
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, rx) = bounded::<u32>(1);
+        drop(rx);
+        assert!(tx.is_disconnected());
+        assert_eq!(tx.send(5), Err(SendError(5)));
+        assert!(matches!(tx.send_timeout(6, Duration::MAX), Err(SendTimeoutError::Disconnected(6))));
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let (tx, rx) = bounded::<u32>(1);
+        assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Timeout));
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx.send(9).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(9));
+        // Too long for an `Instant`, so it waits like `recv` does.
+        assert_eq!(rx.recv_timeout(Duration::MAX), Err(RecvTimeoutError::Disconnected));
+    }
+
+    #[test]
+    fn test_select_wakes_on_later_send() {
+        let (tx_one, rx_one) = bounded::<u32>(1);
+        let (tx_two, rx_two) = bounded::<String>(1);
+
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx_two.send(String::from("two")).unwrap();
+            tx_one
+        });
+
+        let mut lookups: u32 = 0;
+        let got: String = select! {
+            recv(rx_one) -> message => format!("one: {:?}", message),
+            recv({ lookups += 1; &rx_two }) -> message => message.unwrap(),
+        };
+        assert_eq!(got, "two");
+        assert_eq!(lookups, 1);
+
+        let _tx_one: Sender<u32> = producer.join().unwrap();
+        let mut select: Select = Select::new();
+        select.recv(&rx_one);
+        assert_eq!(select.select_timeout(Duration::from_millis(10)), None);
+    }
+
+    #[test]
+    fn test_select_reports_disconnect() {
+        let (tx, rx) = bounded::<u32>(1);
+        drop(tx);
+
+        let mut select: Select = Select::new();
+        let index: usize = select.recv(&rx);
+        assert_eq!(select.select(), index);
+        assert_eq!(select.select_timeout(Duration::MAX), Some(index));
+        assert_eq!(rx.recv(), Err(RecvError));
+    }
+}
@@ -0,0 +1,484 @@
+//! A `Mutex` wrapper that can check lock ordering.
+//!
+//! Without the `deadlock_detection` feature [`Mutex`] is a thin wrapper
+//! around `std::sync::Mutex`. With it, every blocking `lock` records which
+//! locks the current thread already holds into a global lock graph. If taking
+//! the new lock would close a cycle in that graph, two threads taking the
+//! same locks in opposite orders could deadlock, so the inversion is reported
+//! with the backtraces of both acquisitions before the thread blocks.
+
+use std::fmt;
+use std::ops::{ Deref, DerefMut };
+use std::sync::{ self, LockResult, PoisonError, TryLockError, TryLockResult };
+
+#[cfg(feature = "deadlock_detection")]
+pub use self::detection::{ set_panic_on_violation, take_violations, Violation };
+
+/// Drop-in replacement for `std::sync::Mutex` that takes part in lock-order
+/// checking when the `deadlock_detection` feature is enabled.
+pub struct Mutex<T: ?Sized> {
+    #[cfg(feature = "deadlock_detection")]
+    id: detection::LockId,
+    name: Option<&'static str>,
+    inner: sync::Mutex<T>
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Mutex<T> {
+        Mutex::build(value, None)
+    }
+
+    /// Creates a mutex whose name is used in violation reports.
+    pub fn with_name(value: T, name: &'static str) -> Mutex<T> {
+        Mutex::build(value, Some(name))
+    }
+
+    fn build(value: T, name: Option<&'static str>) -> Mutex<T> {
+        Mutex {
+            #[cfg(feature = "deadlock_detection")]
+            id: detection::LockId::new(name),
+            name,
+            inner: sync::Mutex::new(value)
+        }
+    }
+
+    pub fn into_inner(self) -> LockResult<T> {
+        let Mutex { inner, .. } = self;
+        inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Acquires the lock, checking lock order first when detection is on.
+    ///
+    /// # Panics
+    ///
+    /// With `deadlock_detection` enabled and panicking on violations (the
+    /// default), panics instead of blocking when the acquisition inverts an
+    /// order seen before, or when this thread already holds the lock.
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        #[cfg(feature = "deadlock_detection")]
+        detection::before_lock(self.id.0);
+
+        let result = self.inner.lock();
+
+        #[cfg(feature = "deadlock_detection")]
+        detection::acquired(self.id.0);
+
+        match result {
+            Ok(guard) => Ok(self.guard(guard)),
+            Err(poisoned) => Err(PoisonError::new(self.guard(poisoned.into_inner())))
+        }
+    }
+
+    /// Acquires the lock without blocking. A `try_lock` can't deadlock, so it
+    /// doesn't add ordering edges, but a held guard still counts as held.
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+        let result = match self.inner.try_lock() {
+            Ok(guard) => Ok(self.guard(guard)),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                Err(TryLockError::Poisoned(PoisonError::new(self.guard(poisoned.into_inner()))))
+            }
+            Err(TryLockError::WouldBlock) => return Err(TryLockError::WouldBlock)
+        };
+
+        #[cfg(feature = "deadlock_detection")]
+        detection::acquired(self.id.0);
+
+        result
+    }
+
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        self.inner.get_mut()
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    fn guard<'a>(&'a self, inner: sync::MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        MutexGuard {
+            #[cfg(feature = "deadlock_detection")]
+            id: self.id.0,
+            inner
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Mutex<T> {
+        Mutex::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mutex")
+            .field("name", &self.name)
+            .field("inner", &&self.inner)
+            .finish()
+    }
+}
+
+/// Guard returned by [`Mutex::lock`]. Releases the lock on drop.
+pub struct MutexGuard<'a, T: ?Sized> {
+    #[cfg(feature = "deadlock_detection")]
+    id: usize,
+    inner: sync::MutexGuard<'a, T>
+}
+
+impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "deadlock_detection")]
+        detection::released(self.id);
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.inner, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Display> fmt::Display for MutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.inner, f)
+    }
+}
+
+#[cfg(feature = "deadlock_detection")]
+mod detection {
+    use std::backtrace::Backtrace;
+    use std::cell::RefCell;
+    use std::collections::{ HashMap, HashSet };
+    use std::fmt;
+    use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+    use std::sync::{ Arc, Mutex, MutexGuard, OnceLock };
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+    static PANIC_ON_VIOLATION: AtomicBool = AtomicBool::new(true);
+
+    struct Held {
+        id: usize,
+        at: Arc<Backtrace>
+    }
+
+    thread_local! {
+        static HELD: RefCell<Vec<Held>> = const { RefCell::new(Vec::new()) };
+        // Backtrace of the `lock` call in progress, taken before blocking.
+        static PENDING: RefCell<Option<Arc<Backtrace>>> = const { RefCell::new(None) };
+    }
+
+    /// Where an ordering edge `from -> to` was first seen.
+    struct Edge {
+        from_at: Arc<Backtrace>,
+        to_at: Arc<Backtrace>
+    }
+
+    #[derive(Default)]
+    struct LockGraph {
+        names: HashMap<usize, &'static str>,
+        edges: HashMap<usize, HashMap<usize, Edge>>,
+        violations: Vec<Violation>
+    }
+
+    impl LockGraph {
+        /// Finds a path `from ->* to`, returning the nodes along it.
+        fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+            let mut stack: Vec<Vec<usize>> = vec![vec![from]];
+            let mut seen: HashSet<usize> = HashSet::new();
+
+            while let Some(path) = stack.pop() {
+                let last: usize = *path.last().unwrap();
+                if last == to {
+                    return Some(path);
+                }
+                if !seen.insert(last) {
+                    continue;
+                }
+                if let Some(next) = self.edges.get(&last) {
+                    for &node in next.keys() {
+                        let mut extended: Vec<usize> = path.clone();
+                        extended.push(node);
+                        stack.push(extended);
+                    }
+                }
+            }
+
+            None
+        }
+
+        fn label(&self, id: usize) -> String {
+            match self.names.get(&id) {
+                Some(name) => format!("`{}` (#{})", name, id),
+                None => format!("#{}", id)
+            }
+        }
+    }
+
+    fn graph() -> MutexGuard<'static, LockGraph> {
+        static GRAPH: OnceLock<Mutex<LockGraph>> = OnceLock::new();
+        GRAPH
+            .get_or_init(|| Mutex::new(LockGraph::default()))
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// A detected lock-order inversion.
+    #[derive(Clone)]
+    pub struct Violation {
+        report: Arc<String>
+    }
+
+    impl Violation {
+        pub fn report(&self) -> &str {
+            &self.report
+        }
+    }
+
+    impl fmt::Debug for Violation {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.report)
+        }
+    }
+
+    impl fmt::Display for Violation {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.report)
+        }
+    }
+
+    /// Chooses between panicking (the default) and only recording violations
+    /// for [`take_violations`] plus a message on stderr.
+    pub fn set_panic_on_violation(panic: bool) {
+        PANIC_ON_VIOLATION.store(panic, Ordering::SeqCst);
+    }
+
+    /// Drains the violations recorded so far.
+    pub fn take_violations() -> Vec<Violation> {
+        std::mem::take(&mut graph().violations)
+    }
+
+    /// A mutex's node in the lock graph, removed when the mutex is dropped.
+    pub(super) struct LockId(pub(super) usize);
+
+    impl LockId {
+        pub(super) fn new(name: Option<&'static str>) -> LockId {
+            let id: usize = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            if let Some(name) = name {
+                graph().names.insert(id, name);
+            }
+            LockId(id)
+        }
+    }
+
+    impl Drop for LockId {
+        fn drop(&mut self) {
+            let mut graph = graph();
+            graph.names.remove(&self.0);
+            graph.edges.remove(&self.0);
+            for next in graph.edges.values_mut() {
+                next.remove(&self.0);
+            }
+        }
+    }
+
+    pub(super) fn before_lock(id: usize) {
+        let at: Arc<Backtrace> = Arc::new(Backtrace::force_capture());
+        let mut report: Option<String> = None;
+
+        HELD.with(|held| {
+            let held = held.borrow();
+            if held.is_empty() {
+                return;
+            }
+
+            let mut graph = graph();
+
+            for lock in held.iter() {
+                if lock.id == id {
+                    report = Some(format!(
+                        "Lock {} is already held by this thread.\n\nFirst acquired at:\n{}\n\nAcquired again at:\n{}",
+                        graph.label(id), lock.at, at
+                    ));
+                    break;
+                }
+
+                if let Some(path) = graph.path(id, lock.id) {
+                    report = Some(inversion_report(&graph, lock, id, &at, &path));
+                    break;
+                }
+
+                graph.edges.entry(lock.id).or_default().entry(id).or_insert_with(|| Edge {
+                    from_at: Arc::clone(&lock.at),
+                    to_at: Arc::clone(&at)
+                });
+            }
+
+            if let Some(report) = &report {
+                graph.violations.push(Violation { report: Arc::new(report.clone()) });
+            }
+        });
+
+        if let Some(report) = report {
+            if PANIC_ON_VIOLATION.load(Ordering::SeqCst) {
+                panic!("{}", report);
+            }
+            eprintln!("{}", report);
+        }
+
+        PENDING.with(|pending| *pending.borrow_mut() = Some(at));
+    }
+
+    pub(super) fn acquired(id: usize) {
+        let at: Arc<Backtrace> = PENDING
+            .with(|pending| pending.borrow_mut().take())
+            .unwrap_or_else(|| Arc::new(Backtrace::force_capture()));
+
+        HELD.with(|held| held.borrow_mut().push(Held { id, at }));
+    }
+
+    pub(super) fn released(id: usize) {
+        HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(position) = held.iter().rposition(|lock| lock.id == id) {
+                held.remove(position);
+            }
+        });
+    }
+
+    fn inversion_report(
+        graph: &LockGraph,
+        held: &Held,
+        acquiring: usize,
+        at: &Backtrace,
+        path: &[usize]
+    ) -> String {
+        let mut report: String = format!(
+            "Potential deadlock: lock order inversion between {} and {}.\n\n\
+             This thread holds {}, acquired at:\n{}\n\nand is now acquiring {} at:\n{}\n",
+            graph.label(held.id),
+            graph.label(acquiring),
+            graph.label(held.id),
+            held.at,
+            graph.label(acquiring),
+            at
+        );
+
+        for pair in path.windows(2) {
+            let edge: &Edge = &graph.edges[&pair[0]][&pair[1]];
+            report.push_str(&format!(
+                "\nEarlier, {} was held, acquired at:\n{}\n\nwhile acquiring {} at:\n{}\n",
+                graph.label(pair[0]),
+                edge.from_at,
+                graph.label(pair[1]),
+                edge.to_at
+            ));
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_behaves_like_std_mutex() {
+        let counter: Arc<Mutex<u32>> = Arc::new(Mutex::with_name(0, "counter"));
+        let mut handles = vec![];
+
+        for _ in 0..10 {
+            let counter = Arc::clone(&counter);
+            handles.push(thread::spawn(move || {
+                *counter.lock().unwrap() += 1;
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(counter.name(), Some("counter"));
+        {
+            let _guard = counter.lock().unwrap();
+            assert!(counter.try_lock().is_err());
+        }
+        let counter: Mutex<u32> = Arc::try_unwrap(counter).unwrap();
+        assert_eq!(counter.into_inner().unwrap(), 10);
+    }
+
+    #[test]
+    fn test_consistent_order_is_fine() {
+        let first: Mutex<u32> = Mutex::new(1);
+        let second: Mutex<u32> = Mutex::new(2);
+
+        for _ in 0..2 {
+            let a = first.lock().unwrap();
+            let b = second.lock().unwrap();
+            assert_eq!(*a + *b, 3);
+        }
+    }
+
+    #[cfg(feature = "deadlock_detection")]
+    #[test]
+    fn test_inversion_panics_with_both_orders() {
+        let first: Mutex<u32> = Mutex::with_name(1, "first");
+        let second: Mutex<u32> = Mutex::with_name(2, "second");
+
+        {
+            let _a = first.lock().unwrap();
+            let _b = second.lock().unwrap();
+        }
+
+        let result = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _b = second.lock().unwrap();
+                    let _a = first.lock().unwrap();
+                })
+                .join()
+        });
+
+        let payload = result.unwrap_err();
+        let report: &String = payload.downcast_ref::<String>().unwrap();
+        assert!(report.contains("lock order inversion between `second`"));
+        assert!(report.contains("Earlier, `first`"));
+    }
+
+    #[cfg(feature = "deadlock_detection")]
+    #[test]
+    fn test_relock_on_same_thread_panics() {
+        let lock: Mutex<u32> = Mutex::with_name(0, "relocked");
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _first = lock.lock().unwrap();
+            let _second = lock.lock().unwrap();
+        }));
+
+        assert!(result.is_err());
+        let violations: Vec<Violation> = take_violations();
+        assert!(violations.iter().any(|v| v.report().contains("`relocked`")));
+    }
+}
@@ -0,0 +1,732 @@
+//! A small single-threaded async runtime built only on `std`.
+//!
+//! [`Executor::block_on`] drives a future to completion on the current
+//! thread, along with any tasks started through [`spawn`]. Timers come from
+//! [`sleep`], and [`channel`] gives an unbounded channel whose receiving end
+//! can be awaited. Wakers are thread-safe, so plain threads can feed a
+//! [`Sender`] and wake the executor.
+
+use std::cell::{ Cell, RefCell };
+use std::cmp::{ Ordering, Reverse };
+use std::collections::{ BinaryHeap, HashMap, VecDeque };
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{ Rc, Weak };
+use std::sync::mpsc::SendError;
+use std::sync::{ Arc, Condvar, Mutex, MutexGuard };
+use std::task::{ Context, Poll, Wake, Waker };
+use std::time::{ Duration, Instant };
+
+type LocalTask = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Task id used for the future passed to `block_on`.
+const MAIN_TASK: usize = usize::MAX;
+
+/// Ids of tasks that have been woken, shared with every waker.
+#[derive(Default)]
+struct ReadyQueue {
+    ids: Mutex<VecDeque<usize>>,
+    condvar: Condvar
+}
+
+impl ReadyQueue {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<usize>> {
+        self.ids.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn push(&self, id: usize) {
+        self.lock().push_back(id);
+        self.condvar.notify_one();
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    queue: Arc<ReadyQueue>
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.queue.push(self.id);
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.queue.push(self.id);
+    }
+}
+
+struct Timer {
+    deadline: Instant,
+    seq: u64,
+    waker: Waker
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Timer) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Timer) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    fn cmp(&self, other: &Timer) -> Ordering {
+        (self.deadline, self.seq).cmp(&(other.deadline, other.seq))
+    }
+}
+
+/// A spawned task and the one waker handed to every poll of it, so futures
+/// that compare wakers with `will_wake` see the same one each time.
+struct Task {
+    /// `None` while the task is being polled.
+    future: Option<LocalTask>,
+    waker: Waker
+}
+
+struct Inner {
+    tasks: RefCell<HashMap<usize, Task>>,
+    next_id: Cell<usize>,
+    queue: Arc<ReadyQueue>,
+    timers: RefCell<BinaryHeap<Reverse<Timer>>>,
+    next_timer: Cell<u64>
+}
+
+impl Inner {
+    fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static
+    {
+        let state: Rc<RefCell<JoinState<F::Output>>> = Rc::new(RefCell::new(JoinState {
+            result: None,
+            waker: None
+        }));
+
+        let task_state: Rc<RefCell<JoinState<F::Output>>> = Rc::clone(&state);
+        let task: LocalTask = Box::pin(async move {
+            let output = future.await;
+            let mut state = task_state.borrow_mut();
+            state.result = Some(output);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        let id: usize = self.next_id.get();
+        self.next_id.set(id + 1);
+        let waker: Waker = Waker::from(Arc::new(TaskWaker {
+            id,
+            queue: Arc::clone(&self.queue)
+        }));
+        self.tasks.borrow_mut().insert(id, Task { future: Some(task), waker });
+        self.queue.push(id);
+
+        JoinHandle { state }
+    }
+
+    fn add_timer(&self, deadline: Instant, waker: Waker) {
+        let seq: u64 = self.next_timer.get();
+        self.next_timer.set(seq + 1);
+        self.timers.borrow_mut().push(Reverse(Timer { deadline, seq, waker }));
+    }
+
+    /// Wakes expired timers and returns the next deadline still pending.
+    fn fire_timers(&self) -> Option<Instant> {
+        let now: Instant = Instant::now();
+        let mut timers = self.timers.borrow_mut();
+
+        while let Some(Reverse(timer)) = timers.peek() {
+            if timer.deadline > now {
+                return Some(timer.deadline);
+            }
+            let Reverse(timer) = timers.pop().unwrap();
+            timer.waker.wake();
+        }
+
+        None
+    }
+
+    fn poll_task(&self, id: usize) {
+        // Take the task out while polling so it can spawn without a
+        // `RefCell` conflict.
+        let (task, waker): (Option<LocalTask>, Waker) = match self.tasks.borrow_mut().get_mut(&id) {
+            Some(slot) => (slot.future.take(), slot.waker.clone()),
+            None => return
+        };
+        let mut task: LocalTask = match task {
+            Some(task) => task,
+            None => return
+        };
+
+        let mut cx: Context<'_> = Context::from_waker(&waker);
+
+        match task.as_mut().poll(&mut cx) {
+            Poll::Ready(()) => {
+                self.tasks.borrow_mut().remove(&id);
+            }
+            Poll::Pending => {
+                if let Some(slot) = self.tasks.borrow_mut().get_mut(&id) {
+                    slot.future = Some(task);
+                }
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Weak<Inner>>> = const { RefCell::new(None) };
+}
+
+fn current() -> Rc<Inner> {
+    CURRENT
+        .with(|current| current.borrow().as_ref().and_then(Weak::upgrade))
+        .expect("This must be called from inside Executor::block_on")
+}
+
+/// A single-threaded executor. Futures don't need to be `Send`.
+pub struct Executor {
+    inner: Rc<Inner>
+}
+
+impl Executor {
+    pub fn new() -> Executor {
+        Executor {
+            inner: Rc::new(Inner {
+                tasks: RefCell::new(HashMap::new()),
+                next_id: Cell::new(0),
+                queue: Arc::new(ReadyQueue::default()),
+                timers: RefCell::new(BinaryHeap::new()),
+                next_timer: Cell::new(0)
+            })
+        }
+    }
+
+    /// Queues `future` as a task. It starts running on the next `block_on`.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static
+    {
+        self.inner.spawn(future)
+    }
+
+    /// Runs `future` and the spawned tasks until `future` completes. Tasks
+    /// that are still pending afterwards resume on the next `block_on`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside another `block_on` on this thread, and
+    /// lets panics from tasks propagate.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            assert!(
+                current.as_ref().and_then(Weak::upgrade).is_none(),
+                "Executor::block_on can't be nested"
+            );
+            *current = Some(Rc::downgrade(&self.inner));
+        });
+        let _reset = ResetCurrent;
+
+        let mut future = Box::pin(future);
+        let waker: Waker = Waker::from(Arc::new(TaskWaker {
+            id: MAIN_TASK,
+            queue: Arc::clone(&self.inner.queue)
+        }));
+        self.inner.queue.push(MAIN_TASK);
+
+        loop {
+            let next_deadline: Option<Instant> = self.inner.fire_timers();
+
+            let ready: Vec<usize> = {
+                let mut ids = self.inner.queue.lock();
+                if ids.is_empty() {
+                    ids = match next_deadline {
+                        Some(deadline) => {
+                            let timeout: Duration = deadline.saturating_duration_since(Instant::now());
+                            self.inner.queue.condvar
+                                .wait_timeout(ids, timeout)
+                                .unwrap_or_else(|p| p.into_inner())
+                                .0
+                        }
+                        None => self.inner.queue.condvar.wait(ids).unwrap_or_else(|p| p.into_inner())
+                    };
+                }
+                ids.drain(..).collect()
+            };
+
+            for (index, &id) in ready.iter().enumerate() {
+                if id == MAIN_TASK {
+                    let mut cx: Context<'_> = Context::from_waker(&waker);
+                    if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                        // Tasks woken alongside the main one still need their
+                        // poll, on the next `block_on`.
+                        self.inner.queue.lock().extend(ready[index + 1..].iter().filter(|&&id| id != MAIN_TASK));
+                        return output;
+                    }
+                } else {
+                    self.inner.poll_task(id);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Executor {
+        Executor::new()
+    }
+}
+
+impl fmt::Debug for Executor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Executor")
+            .field("tasks", &self.inner.tasks.borrow().len())
+            .finish()
+    }
+}
+
+struct ResetCurrent;
+
+impl Drop for ResetCurrent {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = None);
+    }
+}
+
+/// Spawns a task on the executor running on this thread.
+///
+/// # Panics
+///
+/// Panics if called outside [`Executor::block_on`].
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static
+{
+    current().spawn(future)
+}
+
+struct JoinState<T> {
+    result: Option<T>,
+    waker: Option<Waker>
+}
+
+/// Future resolving to the output of a spawned task.
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>
+}
+
+impl<T> JoinHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().result.is_some()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle").field("finished", &self.is_finished()).finish()
+    }
+}
+
+/// Future returned by [`sleep`].
+#[derive(Debug)]
+pub struct Sleep {
+    /// `None` when the duration is too long for an `Instant`, which never
+    /// comes.
+    deadline: Option<Instant>,
+    /// The waker the executor's timer will wake, so re-polls don't queue
+    /// another timer each time.
+    registered: Option<Waker>
+}
+
+/// Completes after `duration` without blocking the executor thread.
+///
+/// # Panics
+///
+/// Polling it outside [`Executor::block_on`] panics.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep { deadline: Instant::now().checked_add(duration), registered: None }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let deadline: Instant = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Poll::Pending
+        };
+        if Instant::now() >= deadline {
+            return Poll::Ready(());
+        }
+
+        let registered: bool = matches!(&self.registered, Some(waker) if waker.will_wake(cx.waker()));
+        if !registered {
+            current().add_timer(deadline, cx.waker().clone());
+            self.registered = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// Future returned by [`join_all`].
+pub struct JoinAll<F: Future> {
+    futures: Vec<Option<Pin<Box<F>>>>,
+    outputs: Vec<Option<F::Output>>
+}
+
+/// Waits for every future and returns their outputs in the same order.
+pub fn join_all<I>(futures: I) -> JoinAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future
+{
+    let futures: Vec<Option<Pin<Box<I::Item>>>> = futures.into_iter().map(|f| Some(Box::pin(f))).collect();
+    let outputs = futures.iter().map(|_| None).collect();
+
+    JoinAll { futures, outputs }
+}
+
+// The futures are boxed and the outputs are never pinned, so moving a
+// `JoinAll` is fine whatever `F` is.
+impl<F: Future> Unpin for JoinAll<F> {}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Vec<F::Output>> {
+        let this = self.get_mut();
+        let mut done: bool = true;
+
+        for (slot, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if let Some(future) = slot {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => {
+                        *output = Some(value);
+                        *slot = None;
+                    }
+                    Poll::Pending => done = false
+                }
+            }
+        }
+
+        if done {
+            Poll::Ready(this.outputs.iter_mut().map(|output| output.take().unwrap()).collect())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<F: Future> fmt::Debug for JoinAll<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pending: usize = self.futures.iter().filter(|slot| slot.is_some()).count();
+        f.debug_struct("JoinAll").field("pending", &pending).finish()
+    }
+}
+
+struct ChannelState<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receiver_alive: bool,
+    waker: Option<Waker>
+}
+
+fn lock_channel<T>(state: &Mutex<ChannelState<T>>) -> MutexGuard<'_, ChannelState<T>> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Creates an unbounded channel whose [`Receiver::recv`] is a future.
+///
+/// The [`Sender`] is `Send`, so ordinary threads can feed async tasks.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let state: Arc<Mutex<ChannelState<T>>> = Arc::new(Mutex::new(ChannelState {
+        queue: VecDeque::new(),
+        senders: 1,
+        receiver_alive: true,
+        waker: None
+    }));
+
+    (Sender { state: Arc::clone(&state) }, Receiver { state })
+}
+
+pub struct Sender<T> {
+    state: Arc<Mutex<ChannelState<T>>>
+}
+
+impl<T> Sender<T> {
+    /// Queues `value` and wakes the receiver. Never blocks.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = lock_channel(&self.state);
+        if !state.receiver_alive {
+            return Err(SendError(value));
+        }
+
+        state.queue.push_back(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        lock_channel(&self.state).senders += 1;
+        Sender { state: Arc::clone(&self.state) }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = lock_channel(&self.state);
+        state.senders -= 1;
+        if state.senders == 0 {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sender { .. }")
+    }
+}
+
+pub struct Receiver<T> {
+    state: Arc<Mutex<ChannelState<T>>>
+}
+
+impl<T> Receiver<T> {
+    /// Resolves to the next message, or `None` once every sender is gone
+    /// and the queue is empty.
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { receiver: self }
+    }
+
+    pub fn try_recv(&mut self) -> Option<T> {
+        lock_channel(&self.state).queue.pop_front()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        lock_channel(&self.state).receiver_alive = false;
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Receiver { .. }")
+    }
+}
+
+/// Future returned by [`Receiver::recv`].
+#[derive(Debug)]
+pub struct Recv<'a, T> {
+    receiver: &'a mut Receiver<T>
+}
+
+impl<'a, T> Future for Recv<'a, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = lock_channel(&self.receiver.state);
+
+        if let Some(value) = state.queue.pop_front() {
+            Poll::Ready(Some(value))
+        } else if state.senders == 0 {
+            Poll::Ready(None)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_block_on_with_spawned_tasks() {
+        let executor: Executor = Executor::new();
+        let log: Rc<RefCell<Vec<u32>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let early_log = Rc::clone(&log);
+        let early: JoinHandle<u32> = executor.spawn(async move {
+            early_log.borrow_mut().push(1);
+            10
+        });
+
+        let total: u32 = executor.block_on(async {
+            let inner_log = Rc::clone(&log);
+            let nested: JoinHandle<u32> = spawn(async move {
+                inner_log.borrow_mut().push(2);
+                20
+            });
+            early.await + nested.await
+        });
+
+        assert_eq!(total, 30);
+        assert_eq!(*log.borrow(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_sleep_orders_tasks() {
+        let executor: Executor = Executor::new();
+        let start: Instant = Instant::now();
+
+        let order: Vec<u64> = executor.block_on(async {
+            let (tx, mut rx) = channel::<u64>();
+            let mut handles = vec![];
+
+            for delay in [30u64, 10, 20].iter().copied() {
+                let tx = tx.clone();
+                handles.push(spawn(async move {
+                    sleep(Duration::from_millis(delay)).await;
+                    tx.send(delay).unwrap();
+                }));
+            }
+            drop(tx);
+
+            join_all(handles).await;
+            let mut order: Vec<u64> = vec![];
+            while let Some(delay) = rx.recv().await {
+                order.push(delay);
+            }
+            order
+        });
+
+        assert_eq!(order, vec![10, 20, 30]);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_thread_wakes_receiver() {
+        let executor: Executor = Executor::new();
+        let (tx, mut rx) = channel::<String>();
+
+        let producer = thread::spawn(move || {
+            for word in ["hello", "from", "a", "thread"].iter() {
+                thread::sleep(Duration::from_millis(1));
+                tx.send(word.to_string()).unwrap();
+            }
+        });
+
+        let words: Vec<String> = executor.block_on(async move {
+            let mut words: Vec<String> = vec![];
+            while let Some(word) = rx.recv().await {
+                words.push(word);
+            }
+            words
+        });
+
+        producer.join().unwrap();
+        assert_eq!(words.join(" "), "hello from a thread");
+    }
+
+    #[test]
+    fn test_join_all_keeps_order() {
+        let executor: Executor = Executor::new();
+
+        let results: Vec<u64> = executor.block_on(join_all((1..=3u64).rev().map(|n| async move {
+            sleep(Duration::from_millis(n * 5)).await;
+            n
+        })));
+
+        assert_eq!(results, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_sleep_registers_one_timer_per_waker() {
+        let executor: Executor = Executor::new();
+        let inner: Rc<Inner> = Rc::clone(&executor.inner);
+
+        executor.block_on(async move {
+            let mut nap: Pin<Box<Sleep>> = Box::pin(sleep(Duration::from_millis(5)));
+            std::future::poll_fn(|cx| {
+                for _ in 0..10 {
+                    assert!(nap.as_mut().poll(cx).is_pending());
+                }
+                Poll::Ready(())
+            })
+            .await;
+            assert_eq!(inner.timers.borrow().len(), 1);
+            nap.await;
+        });
+
+        let mut forever: Pin<Box<Sleep>> = Box::pin(sleep(Duration::MAX));
+        let mut cx: Context<'_> = Context::from_waker(Waker::noop());
+        assert!(forever.as_mut().poll(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn test_spawned_task_keeps_its_waker() {
+        let executor: Executor = Executor::new();
+
+        let handle: JoinHandle<usize> = executor.spawn(async {
+            let mut nap: Pin<Box<Sleep>> = Box::pin(sleep(Duration::from_millis(5)));
+            let mut polls: u32 = 0;
+            // Each self-wake sends the task back through the executor, which
+            // polls it again with its stored waker.
+            std::future::poll_fn(|cx| {
+                polls += 1;
+                if polls > 10 {
+                    return Poll::Ready(());
+                }
+                assert!(nap.as_mut().poll(cx).is_pending());
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await;
+            let timers: usize = current().timers.borrow().len();
+            nap.await;
+            timers
+        });
+
+        assert_eq!(executor.block_on(handle), 1);
+    }
+
+    #[test]
+    fn test_tasks_woken_with_the_main_task_run_later() {
+        let executor: Executor = Executor::new();
+        let mut spawned: Option<JoinHandle<u32>> = None;
+
+        // The main task wakes itself and then spawns, so both are in the
+        // same batch, with the main task first and finishing.
+        let handle: JoinHandle<u32> = executor.block_on(std::future::poll_fn(|cx| match spawned.take() {
+            Some(handle) => Poll::Ready(handle),
+            None => {
+                cx.waker().wake_by_ref();
+                spawned = Some(spawn(async { 7 }));
+                Poll::Pending
+            }
+        }));
+
+        assert_eq!(executor.block_on(handle), 7);
+    }
+}
@@ -0,0 +1,14 @@
+pub mod actor;
+pub mod broadcast;
+pub mod cancel;
+pub mod channel;
+pub mod deadlock;
+pub mod executor;
+pub mod lockfree;
+pub mod model;
+pub mod par_iter;
+pub mod primitives;
+pub mod sharded;
+pub mod thread_pool;
+
+pub use crate::thread_pool::{ JobHandle, ThreadPool };
@@ -0,0 +1,688 @@
+//! Lock-free queues for paths where `std::sync::mpsc` is too slow.
+//!
+//! Neither queue blocks: `push` and `pop` return straight away, and a
+//! consumer that wants to wait has to spin or back off on its own.
+//!
+//! - [`spsc`] is a lock-free bounded ring buffer for exactly one producer
+//!   and one consumer.
+//! - [`mpsc`] is a lock-free unbounded linked queue for any number of
+//!   producers and one consumer.
+
+use std::ops::Deref;
+
+/// Keeps the producer and consumer indices on separate cache lines so the
+/// two sides don't invalidate each other's line on every operation.
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct Padded<T>(T);
+
+impl<T> Deref for Padded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+pub mod spsc {
+    //! Bounded single-producer/single-consumer ring buffer.
+    //!
+    //! ```
+    //! use concurrency::lockfree::spsc;
+    //!
+    //! let (mut producer, mut consumer) = spsc::channel::<u32>(2);
+    //! assert_eq!(producer.push(1), Ok(()));
+    //! assert_eq!(producer.push(2), Ok(()));
+    //! assert_eq!(producer.push(3), Err(3));
+    //! assert_eq!(consumer.pop(), Some(1));
+    //! ```
+
+    use super::Padded;
+    use std::cell::UnsafeCell;
+    use std::fmt;
+    use std::mem::MaybeUninit;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use std::sync::Arc;
+
+    struct Ring<T> {
+        buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+        /// Next slot to read. Only the consumer writes it.
+        head: Padded<AtomicUsize>,
+        /// Next slot to write. Only the producer writes it.
+        tail: Padded<AtomicUsize>
+    }
+
+    // Slots are only touched by whichever side owns them according to
+    // `head` and `tail`, so sharing the ring only needs the values to be
+    // sendable.
+    unsafe impl<T: Send> Send for Ring<T> {}
+    unsafe impl<T: Send> Sync for Ring<T> {}
+
+    impl<T> Ring<T> {
+        fn slot(&self, index: usize) -> *mut MaybeUninit<T> {
+            self.buffer[index % self.buffer.len()].get()
+        }
+    }
+
+    impl<T> Drop for Ring<T> {
+        fn drop(&mut self) {
+            let head: usize = *self.head.0.get_mut();
+            let tail: usize = *self.tail.0.get_mut();
+            for index in head..tail {
+                // SAFETY: slots in head..tail were written and not yet read.
+                unsafe { (*self.slot(index)).assume_init_drop() };
+            }
+        }
+    }
+
+    /// Creates a ring buffer that holds up to `capacity` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+        assert!(capacity > 0, "An SPSC ring needs room for at least one value");
+
+        let ring: Arc<Ring<T>> = Arc::new(Ring {
+            buffer: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+            head: Padded(AtomicUsize::new(0)),
+            tail: Padded(AtomicUsize::new(0))
+        });
+
+        (
+            Producer { ring: Arc::clone(&ring), tail: 0, cached_head: 0 },
+            Consumer { ring, head: 0, cached_tail: 0 }
+        )
+    }
+
+    /// The writing half. Not `Clone`: there is only ever one producer.
+    pub struct Producer<T> {
+        ring: Arc<Ring<T>>,
+        tail: usize,
+        /// Last `head` seen, so a push only reloads it when the ring looks full.
+        cached_head: usize
+    }
+
+    impl<T> Producer<T> {
+        /// Appends `value`, or hands it back if the ring is full.
+        pub fn push(&mut self, value: T) -> Result<(), T> {
+            let capacity: usize = self.ring.buffer.len();
+
+            if self.tail - self.cached_head == capacity {
+                // Acquire pairs with the consumer's Release store of `head`:
+                // once we see a slot as free, the consumer has finished
+                // moving the old value out of it.
+                self.cached_head = self.ring.head.load(Ordering::Acquire);
+                if self.tail - self.cached_head == capacity {
+                    return Err(value);
+                }
+            }
+
+            // SAFETY: the slot lies outside head..tail, so the consumer
+            // won't look at it until `tail` is published below.
+            unsafe { (*self.ring.slot(self.tail)).write(value) };
+            self.tail += 1;
+            // Release publishes the write above to the consumer's Acquire
+            // load of `tail`.
+            self.ring.tail.store(self.tail, Ordering::Release);
+            Ok(())
+        }
+
+        pub fn len(&self) -> usize {
+            self.tail - self.ring.head.load(Ordering::Acquire)
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        pub fn capacity(&self) -> usize {
+            self.ring.buffer.len()
+        }
+    }
+
+    impl<T> fmt::Debug for Producer<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Producer")
+                .field("len", &self.len())
+                .field("capacity", &self.capacity())
+                .finish()
+        }
+    }
+
+    /// The reading half.
+    pub struct Consumer<T> {
+        ring: Arc<Ring<T>>,
+        head: usize,
+        /// Last `tail` seen, so a pop only reloads it when the ring looks empty.
+        cached_tail: usize
+    }
+
+    impl<T> Consumer<T> {
+        /// Takes the oldest value, or `None` if the ring is empty.
+        pub fn pop(&mut self) -> Option<T> {
+            if self.head == self.cached_tail {
+                // Acquire pairs with the producer's Release store of `tail`,
+                // so the value in the slot is fully written before we read it.
+                self.cached_tail = self.ring.tail.load(Ordering::Acquire);
+                if self.head == self.cached_tail {
+                    return None;
+                }
+            }
+
+            // SAFETY: the slot is inside head..tail, so it holds a value the
+            // producer won't touch until `head` moves past it.
+            let value: T = unsafe { (*self.ring.slot(self.head)).assume_init_read() };
+            self.head += 1;
+            // Release hands the now empty slot back to the producer.
+            self.ring.head.store(self.head, Ordering::Release);
+            Some(value)
+        }
+
+        pub fn len(&self) -> usize {
+            self.ring.tail.load(Ordering::Acquire) - self.head
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// Whether the producer has been dropped. Values it pushed before
+        /// that can still be popped.
+        pub fn is_abandoned(&self) -> bool {
+            Arc::strong_count(&self.ring) == 1
+        }
+    }
+
+    impl<T> fmt::Debug for Consumer<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Consumer").field("len", &self.len()).finish()
+        }
+    }
+}
+
+pub mod mpsc {
+    //! Unbounded multi-producer/single-consumer Michael-Scott queue.
+    //!
+    //! The queue is a linked list with a dummy head node and separate `head`
+    //! and `tail` pointers. Producers link in a node with a CAS on the tail
+    //! node's `next` and then swing `tail` forward, helping any producer
+    //! that linked a node but hasn't swung `tail` yet, so a stalled producer
+    //! never holds up the others or the consumer. A value is in the queue
+    //! from the moment its node is linked.
+    //!
+    //! The consumer frees nodes as it goes while producers may still be
+    //! reading the node they think is the tail, so every producer publishes
+    //! that node in a hazard pointer first. The consumer only frees a node
+    //! once no hazard pointer names it, and keeps it on a retired list until
+    //! then.
+    //!
+    //! ```
+    //! use concurrency::lockfree::mpsc;
+    //!
+    //! let (producer, mut consumer) = mpsc::queue::<&str>();
+    //! let other = producer.clone();
+    //! producer.push("a");
+    //! other.push("b");
+    //! assert_eq!(consumer.pop(), Some("a"));
+    //! assert_eq!(consumer.pop(), Some("b"));
+    //! assert_eq!(consumer.pop(), None);
+    //! ```
+
+    use super::Padded;
+    use std::cell::UnsafeCell;
+    use std::fmt;
+    use std::marker::PhantomData;
+    use std::ptr;
+    use std::sync::atomic::{ AtomicBool, AtomicPtr, Ordering };
+    use std::sync::Arc;
+
+    /// How many retired nodes the consumer collects before it scans the
+    /// hazard pointers for ones it can free.
+    const SCAN_THRESHOLD: usize = 64;
+
+    struct Node<T> {
+        next: AtomicPtr<Node<T>>,
+        value: Option<T>
+    }
+
+    impl<T> Node<T> {
+        fn new(value: Option<T>) -> *mut Node<T> {
+            Box::into_raw(Box::new(Node {
+                next: AtomicPtr::new(ptr::null_mut()),
+                value
+            }))
+        }
+    }
+
+    /// One producer's hazard pointer. Slots are never freed while the queue
+    /// is alive; a dropped producer's slot is reused by the next clone.
+    struct HazardSlot<T> {
+        node: AtomicPtr<Node<T>>,
+        active: AtomicBool,
+        next: AtomicPtr<HazardSlot<T>>
+    }
+
+    struct Inner<T> {
+        /// The dummy node. Only the consumer reads or writes it.
+        head: Padded<AtomicPtr<Node<T>>>,
+        /// The last node, or one just before it while a push is finishing.
+        tail: Padded<AtomicPtr<Node<T>>>,
+        hazards: AtomicPtr<HazardSlot<T>>,
+        /// Nodes unlinked by the consumer that a producer may still be
+        /// reading. Only the consumer touches it, and `drop` once nobody
+        /// else can.
+        retired: UnsafeCell<Vec<*mut Node<T>>>,
+        _marker: PhantomData<T>
+    }
+
+    // Values move from producers to the consumer, so `T: Send` is all the
+    // queue needs, the same as `std::sync::mpsc::Sender`.
+    unsafe impl<T: Send> Send for Inner<T> {}
+    unsafe impl<T: Send> Sync for Inner<T> {}
+
+    impl<T> Inner<T> {
+        /// Claims a free hazard slot, or adds a new one.
+        fn acquire_slot(&self) -> *const HazardSlot<T> {
+            let mut slot: *mut HazardSlot<T> = self.hazards.load(Ordering::Acquire);
+            while !slot.is_null() {
+                // SAFETY: slots live as long as the queue.
+                let current: &HazardSlot<T> = unsafe { &*slot };
+                if current.active.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+                    return slot;
+                }
+                slot = current.next.load(Ordering::Acquire);
+            }
+
+            let slot: *mut HazardSlot<T> = Box::into_raw(Box::new(HazardSlot {
+                node: AtomicPtr::new(ptr::null_mut()),
+                active: AtomicBool::new(true),
+                next: AtomicPtr::new(ptr::null_mut())
+            }));
+            let mut head: *mut HazardSlot<T> = self.hazards.load(Ordering::Relaxed);
+            loop {
+                // SAFETY: the slot isn't shared until the CAS succeeds.
+                unsafe { (*slot).next.store(head, Ordering::Relaxed) };
+                match self.hazards.compare_exchange_weak(head, slot, Ordering::Release, Ordering::Relaxed) {
+                    Ok(_) => return slot,
+                    Err(current) => head = current
+                }
+            }
+        }
+
+        /// Frees every retired node that no hazard pointer names.
+        ///
+        /// # Safety
+        ///
+        /// Only the consumer may call this.
+        unsafe fn scan(&self) {
+            let mut protected: Vec<*mut Node<T>> = Vec::new();
+            let mut slot: *mut HazardSlot<T> = self.hazards.load(Ordering::Acquire);
+            while !slot.is_null() {
+                // SeqCst pairs with the producer's SeqCst store of its
+                // hazard and load of `tail`: a producer that saw a node as
+                // the tail before it was unlinked published its hazard early
+                // enough for this load to see it.
+                protected.push((*slot).node.load(Ordering::SeqCst));
+                slot = (*slot).next.load(Ordering::Acquire);
+            }
+
+            (*self.retired.get()).retain(|&node| {
+                if protected.contains(&node) {
+                    return true;
+                }
+                drop(Box::from_raw(node));
+                false
+            });
+        }
+    }
+
+    impl<T> Drop for Inner<T> {
+        fn drop(&mut self) {
+            let mut node: *mut Node<T> = *self.head.0.get_mut();
+            while !node.is_null() {
+                // SAFETY: every node from head onwards is still owned by the
+                // queue, and nobody else can reach them any more.
+                let boxed: Box<Node<T>> = unsafe { Box::from_raw(node) };
+                node = boxed.next.load(Ordering::Relaxed);
+            }
+
+            for &node in self.retired.get_mut().iter() {
+                // SAFETY: retired nodes are unlinked and every producer is
+                // gone, so nothing names them any more.
+                drop(unsafe { Box::from_raw(node) });
+            }
+
+            let mut slot: *mut HazardSlot<T> = *self.hazards.get_mut();
+            while !slot.is_null() {
+                // SAFETY: as above, the slots are only reachable from here.
+                let boxed: Box<HazardSlot<T>> = unsafe { Box::from_raw(slot) };
+                slot = boxed.next.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn queue<T>() -> (Producer<T>, Consumer<T>) {
+        let dummy: *mut Node<T> = Node::new(None);
+        let inner: Arc<Inner<T>> = Arc::new(Inner {
+            head: Padded(AtomicPtr::new(dummy)),
+            tail: Padded(AtomicPtr::new(dummy)),
+            hazards: AtomicPtr::new(ptr::null_mut()),
+            retired: UnsafeCell::new(Vec::new()),
+            _marker: PhantomData
+        });
+
+        (Producer::new(Arc::clone(&inner)), Consumer { inner })
+    }
+
+    /// The writing half. Each producer owns a hazard pointer, so a producer
+    /// can move between threads but not be shared by them; clone it for
+    /// every thread that pushes.
+    pub struct Producer<T> {
+        inner: Arc<Inner<T>>,
+        slot: *const HazardSlot<T>
+    }
+
+    // Not `Sync`: two threads pushing through one producer would overwrite
+    // each other's hazard pointer.
+    unsafe impl<T: Send> Send for Producer<T> {}
+
+    impl<T> Producer<T> {
+        fn new(inner: Arc<Inner<T>>) -> Producer<T> {
+            let slot: *const HazardSlot<T> = inner.acquire_slot();
+            Producer { inner, slot }
+        }
+
+        pub fn push(&self, value: T) {
+            let node: *mut Node<T> = Node::new(Some(value));
+            // SAFETY: the slot is ours until we drop, and lives as long as
+            // `inner`.
+            let hazard: &AtomicPtr<Node<T>> = unsafe { &(*self.slot).node };
+
+            loop {
+                let tail: *mut Node<T> = self.inner.tail.load(Ordering::SeqCst);
+                hazard.store(tail, Ordering::SeqCst);
+                // The consumer only unlinks a node once `tail` has moved past
+                // it, so if `tail` still names it after the hazard is up, the
+                // consumer's next scan will see the hazard and keep it.
+                if self.inner.tail.load(Ordering::SeqCst) != tail {
+                    continue;
+                }
+
+                // SAFETY: `tail` is protected by the hazard pointer.
+                // Acquire pairs with the Release CAS that linked `next`.
+                let next: *mut Node<T> = unsafe { (*tail).next.load(Ordering::Acquire) };
+                if !next.is_null() {
+                    // Another push linked its node but hasn't moved `tail`
+                    // yet. Move it for them rather than wait.
+                    let _ = self.inner.tail.compare_exchange(tail, next, Ordering::SeqCst, Ordering::Relaxed);
+                    continue;
+                }
+
+                // SAFETY: as above. Release publishes the node's value to
+                // the consumer's Acquire load of `next`.
+                let linked: bool = unsafe {
+                    (*tail).next
+                        .compare_exchange(ptr::null_mut(), node, Ordering::Release, Ordering::Relaxed)
+                        .is_ok()
+                };
+                if linked {
+                    // Failing just means someone has already helped.
+                    let _ = self.inner.tail.compare_exchange(tail, node, Ordering::SeqCst, Ordering::Relaxed);
+                    break;
+                }
+            }
+
+            hazard.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+
+    impl<T> Clone for Producer<T> {
+        fn clone(&self) -> Producer<T> {
+            Producer::new(Arc::clone(&self.inner))
+        }
+    }
+
+    impl<T> Drop for Producer<T> {
+        fn drop(&mut self) {
+            // SAFETY: the slot lives as long as `inner`, which we still hold.
+            unsafe {
+                (*self.slot).node.store(ptr::null_mut(), Ordering::Release);
+                (*self.slot).active.store(false, Ordering::Release);
+            }
+        }
+    }
+
+    impl<T> fmt::Debug for Producer<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Producer").finish_non_exhaustive()
+        }
+    }
+
+    /// The reading half. There is only one, so it isn't `Clone`.
+    pub struct Consumer<T> {
+        inner: Arc<Inner<T>>
+    }
+
+    impl<T> Consumer<T> {
+        /// Takes the oldest value, or `None` if the queue is empty.
+        pub fn pop(&mut self) -> Option<T> {
+            let head: *mut Node<T> = self.inner.head.load(Ordering::Relaxed);
+            // SAFETY: `head` is the dummy, which only we can unlink.
+            // Acquire pairs with the producer's Release CAS of `next`.
+            let next: *mut Node<T> = unsafe { (*head).next.load(Ordering::Acquire) };
+            if next.is_null() {
+                return None;
+            }
+
+            // `tail` may still be on the dummy if the producer that linked
+            // `next` hasn't moved it yet. Move it ourselves so `tail` is past
+            // `head` before `head` is retired; either way it's moved after
+            // this.
+            let _ = self.inner.tail.compare_exchange(head, next, Ordering::SeqCst, Ordering::SeqCst);
+
+            self.inner.head.store(next, Ordering::Relaxed);
+            // SAFETY: `next` is the new dummy, and producers never touch a
+            // linked node's value. `head` is unlinked, so it goes on the
+            // retired list, which only we use.
+            unsafe {
+                let value: Option<T> = (*next).value.take();
+                let retired: &mut Vec<*mut Node<T>> = &mut *self.inner.retired.get();
+                retired.push(head);
+                if retired.len() >= SCAN_THRESHOLD {
+                    self.inner.scan();
+                }
+                value
+            }
+        }
+
+        pub fn is_empty(&self) -> bool {
+            let head: *mut Node<T> = self.inner.head.load(Ordering::Relaxed);
+            // SAFETY: as in `pop`.
+            unsafe { (*head).next.load(Ordering::Acquire).is_null() }
+        }
+
+        /// Whether every producer has been dropped. Values pushed before
+        /// that can still be popped.
+        pub fn is_abandoned(&self) -> bool {
+            Arc::strong_count(&self.inner) == 1
+        }
+
+        /// Retired nodes waiting to be freed, and hazard slots allocated.
+        #[cfg(test)]
+        pub(super) fn reclamation(&self) -> (usize, usize) {
+            // SAFETY: we're the consumer.
+            let retired: usize = unsafe { (*self.inner.retired.get()).len() };
+            let mut slots: usize = 0;
+            let mut slot: *mut HazardSlot<T> = self.inner.hazards.load(Ordering::Acquire);
+            while !slot.is_null() {
+                slots += 1;
+                // SAFETY: slots live as long as the queue.
+                slot = unsafe { (*slot).next.load(Ordering::Acquire) };
+            }
+            (retired, slots)
+        }
+    }
+
+    impl<T> fmt::Debug for Consumer<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Consumer").field("is_empty", &self.is_empty()).finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ mpsc, spsc };
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use std::sync::Arc;
+    use std::thread;
+
+    const MESSAGES: usize = 100_000;
+
+    #[test]
+    fn test_spsc_full_and_empty() {
+        let (mut producer, mut consumer) = spsc::channel::<u32>(3);
+        assert_eq!(consumer.pop(), None);
+
+        for i in 0..3 {
+            assert_eq!(producer.push(i), Ok(()));
+        }
+        assert_eq!(producer.push(3), Err(3));
+        assert_eq!(consumer.len(), 3);
+
+        assert_eq!(consumer.pop(), Some(0));
+        assert_eq!(producer.push(3), Ok(()));
+        assert_eq!((1..4).map(|_| consumer.pop().unwrap()).collect::<Vec<u32>>(), vec![1, 2, 3]);
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn test_spsc_across_threads_keeps_order() {
+        // Boxed values make a missing Release/Acquire pair show up as a read
+        // of a half-written heap pointer rather than just a wrong number.
+        let (mut producer, mut consumer) = spsc::channel::<Box<usize>>(64);
+
+        let writer = thread::spawn(move || {
+            for i in 0..MESSAGES {
+                let mut value: Box<usize> = Box::new(i);
+                while let Err(returned) = producer.push(value) {
+                    value = returned;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut expected: usize = 0;
+        while expected < MESSAGES {
+            match consumer.pop() {
+                Some(value) => {
+                    assert_eq!(*value, expected);
+                    expected += 1;
+                }
+                None => thread::yield_now()
+            }
+        }
+
+        writer.join().unwrap();
+        assert!(consumer.is_abandoned());
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn test_spsc_drops_unread_values() {
+        let drops: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let (mut producer, mut consumer) = spsc::channel::<Counted>(4);
+        for _ in 0..4 {
+            assert!(producer.push(Counted(Arc::clone(&drops))).is_ok());
+        }
+        drop(consumer.pop());
+        drop(producer);
+        drop(consumer);
+
+        assert_eq!(drops.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_mpsc_keeps_per_producer_order() {
+        const PRODUCERS: usize = 4;
+        let (producer, mut consumer) = mpsc::queue::<(usize, Box<usize>)>();
+
+        let handles: Vec<thread::JoinHandle<()>> = (0..PRODUCERS)
+            .map(|id| {
+                let producer = producer.clone();
+                thread::spawn(move || {
+                    for i in 0..MESSAGES {
+                        producer.push((id, Box::new(i)));
+                    }
+                })
+            })
+            .collect();
+        drop(producer);
+
+        // The queue promises FIFO per producer only. Any total order between
+        // producers is fine, but each producer's values have to arrive in
+        // sequence, which linking at the tail guarantees because a producer's
+        // later push can only be linked after its earlier one.
+        let mut next: Vec<usize> = vec![0; PRODUCERS];
+        let mut received: usize = 0;
+        while received < PRODUCERS * MESSAGES {
+            match consumer.pop() {
+                Some((id, value)) => {
+                    assert_eq!(*value, next[id]);
+                    next[id] += 1;
+                    received += 1;
+                }
+                None => thread::yield_now()
+            }
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(consumer.is_abandoned());
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn test_mpsc_reclaims_nodes_and_hazard_slots() {
+        let (producer, mut consumer) = mpsc::queue::<usize>();
+
+        for i in 0..1000 {
+            // Each clone takes a hazard slot and hands it back on drop.
+            let other = producer.clone();
+            other.push(i);
+            drop(other);
+            assert_eq!(consumer.pop(), Some(i));
+        }
+
+        let (retired, slots) = consumer.reclamation();
+        // Nothing is hazarded between pushes, so every scan frees the lot.
+        assert!(retired < 64);
+        assert_eq!(slots, 2);
+    }
+
+    #[test]
+    fn test_mpsc_frees_remaining_nodes() {
+        let value: Arc<()> = Arc::new(());
+        let (producer, mut consumer) = mpsc::queue::<Arc<()>>();
+
+        for _ in 0..10 {
+            producer.push(Arc::clone(&value));
+        }
+        assert!(consumer.pop().is_some());
+        assert!(!consumer.is_empty());
+
+        drop(producer);
+        drop(consumer);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
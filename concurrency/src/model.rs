@@ -0,0 +1,848 @@
+//! A deterministic scheduler for testing threaded code, in the spirit of
+//! `loom`.
+//!
+//! [`check`] runs a closure over and over. Threads started with
+//! [`thread::spawn`] are real OS threads, but only one of them runs at a time:
+//! every operation on the instrumented [`sync`] types is a point where the
+//! scheduler may switch to another thread. Each run takes a different path
+//! through those choices, depth first, so an assertion that holds under
+//! `check` holds for every interleaving that was explored, not just the ones
+//! the OS happened to pick.
+//!
+//! ```
+//! use concurrency::model::{ self, sync::{ Arc, Mutex }, thread };
+//!
+//! model::check(|| {
+//!     let counter: Arc<Mutex<u32>> = Arc::new(Mutex::new(0));
+//!     let handles: Vec<thread::JoinHandle<()>> = (0..2)
+//!         .map(|_| {
+//!             let counter = Arc::clone(&counter);
+//!             thread::spawn(move || *counter.lock() += 1)
+//!         })
+//!         .collect();
+//!
+//!     for handle in handles {
+//!         handle.join();
+//!     }
+//!     assert_eq!(*counter.lock(), 2);
+//! });
+//! ```
+//!
+//! A failed assertion, a panic or a deadlock in any interleaving makes
+//! `check` panic with the schedule that triggered it.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::{ Arc, Condvar, Mutex as StdMutex, MutexGuard as StdMutexGuard };
+use std::thread as std_thread;
+
+/// Something a model thread can block on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resource {
+    Lock(usize),
+    Channel(usize),
+    Join(usize)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Runnable,
+    Blocked(Resource),
+    Finished
+}
+
+/// A scheduling choice made during a run, replayed on the next one.
+#[derive(Debug, Clone, Copy)]
+struct Decision {
+    chosen: usize,
+    options: usize
+}
+
+/// Payload used to unwind model threads once a run has been aborted.
+struct Abort;
+
+static NEXT_OBJECT_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn next_object_id() -> usize {
+    NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+struct ExecState {
+    threads: Vec<Status>,
+    active: usize,
+    path: Vec<Decision>,
+    position: usize,
+    preemptions: usize,
+    preemption_bound: Option<usize>,
+    lock_owners: HashMap<usize, usize>,
+    trace: Vec<usize>,
+    failure: Option<String>,
+    exited: usize,
+    os_threads: Vec<std_thread::JoinHandle<()>>
+}
+
+struct Execution {
+    state: StdMutex<ExecState>,
+    condvar: Condvar
+}
+
+type State<'a> = StdMutexGuard<'a, ExecState>;
+
+impl Execution {
+    fn lock(&self) -> State<'_> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Picks the next thread to run and hands control to it.
+    fn schedule(&self, state: &mut State<'_>, me: usize) {
+        let me_runnable: bool = state.threads[me] == Status::Runnable;
+        let mut runnable: Vec<usize> = Vec::new();
+        if me_runnable {
+            runnable.push(me);
+        }
+        runnable.extend(
+            (0..state.threads.len()).filter(|&id| id != me && state.threads[id] == Status::Runnable)
+        );
+
+        if runnable.is_empty() {
+            if state.threads.iter().any(|status| *status != Status::Finished) {
+                let blocked: Vec<String> = state.threads
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, status)| **status != Status::Finished)
+                    .map(|(id, status)| format!("thread {} {:?}", id, status))
+                    .collect();
+                self.abort(state, format!("deadlock: {}", blocked.join(", ")));
+            }
+            return;
+        }
+
+        let bounded: bool = state.preemption_bound.is_some_and(|bound| state.preemptions >= bound);
+        if me_runnable && bounded {
+            runnable.truncate(1);
+        }
+
+        let choice: usize = if runnable.len() == 1 {
+            0
+        } else {
+            let position: usize = state.position;
+            state.position += 1;
+            match state.path.get(position) {
+                Some(decision) => {
+                    debug_assert_eq!(decision.options, runnable.len(), "The model closure isn't deterministic");
+                    decision.chosen
+                }
+                None => {
+                    state.path.push(Decision { chosen: 0, options: runnable.len() });
+                    0
+                }
+            }
+        };
+
+        let next: usize = runnable[choice];
+        if me_runnable && next != me {
+            state.preemptions += 1;
+        }
+        if state.trace.last() != Some(&next) {
+            state.trace.push(next);
+        }
+        state.active = next;
+        self.condvar.notify_all();
+    }
+
+    /// Blocks the OS thread until the scheduler hands control to `me`.
+    fn wait_turn<'a>(&'a self, mut state: State<'a>, me: usize) -> State<'a> {
+        loop {
+            if state.failure.is_some() {
+                drop(state);
+                panic::resume_unwind(Box::new(Abort));
+            }
+            if state.active == me {
+                return state;
+            }
+            state = self.condvar.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+    }
+
+    fn yield_now(&self, me: usize) {
+        let mut state = self.lock();
+        self.schedule(&mut state, me);
+        drop(self.wait_turn(state, me));
+    }
+
+    /// Parks `me` until something wakes `resource`.
+    fn block_on<'a>(&'a self, mut state: State<'a>, me: usize, resource: Resource) -> State<'a> {
+        state.threads[me] = Status::Blocked(resource);
+        self.schedule(&mut state, me);
+        self.wait_turn(state, me)
+    }
+
+    fn wake(&self, state: &mut State<'_>, resource: Resource) {
+        for status in state.threads.iter_mut() {
+            if *status == Status::Blocked(resource) {
+                *status = Status::Runnable;
+            }
+        }
+    }
+
+    fn abort(&self, state: &mut State<'_>, message: String) {
+        if state.failure.is_none() {
+            state.failure = Some(message);
+        }
+        self.condvar.notify_all();
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<(Arc<Execution>, usize)>> = const { RefCell::new(None) };
+}
+
+fn current() -> (Arc<Execution>, usize) {
+    CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("Model types can only be used inside model::check")
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("non-string panic payload")
+    }
+}
+
+/// Starts model thread `id` on a new OS thread.
+fn start_thread<F>(execution: &Arc<Execution>, state: &mut State<'_>, id: usize, f: F)
+where
+    F: FnOnce() + Send + 'static
+{
+    let thread_execution: Arc<Execution> = Arc::clone(execution);
+
+    let handle = std_thread::spawn(move || {
+        CURRENT.with(|current| *current.borrow_mut() = Some((Arc::clone(&thread_execution), id)));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            drop(thread_execution.wait_turn(thread_execution.lock(), id));
+            f();
+        }));
+
+        let execution: &Execution = &thread_execution;
+        let mut state = execution.lock();
+        state.threads[id] = Status::Finished;
+        match result {
+            Ok(()) => {
+                execution.wake(&mut state, Resource::Join(id));
+                if state.failure.is_none() {
+                    execution.schedule(&mut state, id);
+                }
+            }
+            Err(payload) => {
+                if !payload.is::<Abort>() {
+                    let message: String = format!("thread {} panicked: {}", id, payload_message(&*payload));
+                    execution.abort(&mut state, message);
+                }
+            }
+        }
+        state.exited += 1;
+        execution.condvar.notify_all();
+        drop(state);
+
+        CURRENT.with(|current| *current.borrow_mut() = None);
+    });
+
+    state.os_threads.push(handle);
+}
+
+/// Result of a successful [`Builder::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explored {
+    pub iterations: usize,
+    /// `false` if `max_iterations` stopped the search before every
+    /// interleaving within the preemption bound was tried.
+    pub complete: bool
+}
+
+/// Configures how much of the schedule space [`Builder::check`] explores.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    /// Upper limit on the number of runs.
+    pub max_iterations: usize,
+    /// Maximum number of times per run the scheduler may switch away from a
+    /// thread that could have kept running. Most bugs need only two or
+    /// three; `None` explores everything.
+    pub preemption_bound: Option<usize>
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder {
+            max_iterations: 100_000,
+            preemption_bound: Some(3)
+        }
+    }
+
+    /// Runs `f` under every explored schedule.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the failing schedule if `f` panics or deadlocks in any of
+    /// them.
+    pub fn check<F>(&self, f: F) -> Explored
+    where
+        F: Fn() + Send + Sync + 'static
+    {
+        let f: Arc<F> = Arc::new(f);
+        let mut path: Vec<Decision> = Vec::new();
+
+        for iteration in 1..=self.max_iterations {
+            let execution: Arc<Execution> = Arc::new(Execution {
+                state: StdMutex::new(ExecState {
+                    threads: vec![Status::Runnable],
+                    active: 0,
+                    path,
+                    position: 0,
+                    preemptions: 0,
+                    preemption_bound: self.preemption_bound,
+                    lock_owners: HashMap::new(),
+                    trace: vec![0],
+                    failure: None,
+                    exited: 0,
+                    os_threads: Vec::new()
+                }),
+                condvar: Condvar::new()
+            });
+
+            {
+                let f: Arc<F> = Arc::clone(&f);
+                let mut state = execution.lock();
+                start_thread(&execution, &mut state, 0, move || f());
+            }
+
+            let (failure, trace, finished_path, handles) = {
+                let mut state = execution.lock();
+                while state.exited < state.threads.len() {
+                    state = execution.condvar.wait(state).unwrap_or_else(|p| p.into_inner());
+                }
+                (
+                    state.failure.take(),
+                    std::mem::take(&mut state.trace),
+                    std::mem::take(&mut state.path),
+                    std::mem::take(&mut state.os_threads)
+                )
+            };
+
+            for handle in handles {
+                let _ = handle.join();
+            }
+
+            if let Some(failure) = failure {
+                panic!("model check failed on iteration {}: {}\nschedule: {:?}", iteration, failure, trace);
+            }
+
+            path = finished_path;
+            while let Some(last) = path.last() {
+                if last.chosen + 1 < last.options {
+                    break;
+                }
+                path.pop();
+            }
+
+            match path.last_mut() {
+                Some(last) => last.chosen += 1,
+                None => return Explored { iterations: iteration, complete: true }
+            }
+        }
+
+        Explored {
+            iterations: self.max_iterations,
+            complete: false
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// Runs `f` under every interleaving reachable with the default [`Builder`].
+pub fn check<F>(f: F) -> Explored
+where
+    F: Fn() + Send + Sync + 'static
+{
+    Builder::new().check(f)
+}
+
+pub mod thread {
+    //! Model replacements for `std::thread::spawn` and friends.
+
+    use super::{ current, start_thread, Resource, Status };
+    use std::fmt;
+    use std::sync::{ Arc, Mutex };
+
+    pub struct JoinHandle<T> {
+        id: usize,
+        result: Arc<Mutex<Option<T>>>
+    }
+
+    impl<T> JoinHandle<T> {
+        /// Waits for the thread to finish. A panic in the thread fails the
+        /// whole check, so there is no error case here.
+        pub fn join(self) -> T {
+            let (execution, me) = current();
+            let mut state = execution.lock();
+            while state.threads[self.id] != Status::Finished {
+                state = execution.block_on(state, me, Resource::Join(self.id));
+            }
+            drop(state);
+
+            let value = self.result.lock().unwrap().take();
+            value.expect("A finished model thread always leaves its result")
+        }
+    }
+
+    impl<T> fmt::Debug for JoinHandle<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("JoinHandle").field("id", &self.id).finish()
+        }
+    }
+
+    /// Spawns a model thread. The scheduler may run it straight away or
+    /// keep running the caller.
+    pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static
+    {
+        let (execution, me) = current();
+        let result: Arc<Mutex<Option<T>>> = Arc::new(Mutex::new(None));
+
+        let id: usize = {
+            let mut state = execution.lock();
+            let id: usize = state.threads.len();
+            state.threads.push(Status::Runnable);
+
+            let slot: Arc<Mutex<Option<T>>> = Arc::clone(&result);
+            start_thread(&execution, &mut state, id, move || {
+                let value: T = f();
+                *slot.lock().unwrap() = Some(value);
+            });
+            id
+        };
+
+        execution.yield_now(me);
+        JoinHandle { id, result }
+    }
+
+    /// Lets the scheduler switch threads here.
+    pub fn yield_now() {
+        let (execution, me) = current();
+        execution.yield_now(me);
+    }
+}
+
+pub mod sync {
+    //! Model replacements for `std::sync` types. Every operation is a point
+    //! where the scheduler may switch threads.
+
+    use super::{ current, next_object_id, Resource };
+    use std::fmt;
+    use std::ops::{ Deref, DerefMut };
+    use std::sync::{ Mutex as StdMutex, MutexGuard as StdMutexGuard };
+    use std::thread as std_thread;
+
+    /// `Arc` needs no instrumentation of its own.
+    pub use std::sync::Arc;
+
+    /// A mutex whose `lock` and unlock are scheduling points. Poisoning is
+    /// not modelled, since any panic already fails the check.
+    pub struct Mutex<T> {
+        id: usize,
+        data: StdMutex<T>
+    }
+
+    impl<T> Mutex<T> {
+        pub fn new(value: T) -> Mutex<T> {
+            Mutex {
+                id: next_object_id(),
+                data: StdMutex::new(value)
+            }
+        }
+
+        pub fn lock(&self) -> MutexGuard<'_, T> {
+            let (execution, me) = current();
+            execution.yield_now(me);
+
+            let mut state = execution.lock();
+            while state.lock_owners.contains_key(&self.id) {
+                state = execution.block_on(state, me, Resource::Lock(self.id));
+            }
+            state.lock_owners.insert(self.id, me);
+            drop(state);
+
+            MutexGuard {
+                mutex: self,
+                data: Some(self.data.lock().unwrap_or_else(|p| p.into_inner()))
+            }
+        }
+
+        pub fn into_inner(self) -> T {
+            self.data.into_inner().unwrap_or_else(|p| p.into_inner())
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for Mutex<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Mutex").field("id", &self.id).finish()
+        }
+    }
+
+    pub struct MutexGuard<'a, T> {
+        mutex: &'a Mutex<T>,
+        data: Option<StdMutexGuard<'a, T>>
+    }
+
+    impl<'a, T> Deref for MutexGuard<'a, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            self.data.as_ref().unwrap()
+        }
+    }
+
+    impl<'a, T> DerefMut for MutexGuard<'a, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            self.data.as_mut().unwrap()
+        }
+    }
+
+    impl<'a, T> Drop for MutexGuard<'a, T> {
+        fn drop(&mut self) {
+            self.data = None;
+
+            let (execution, me) = current();
+            let mut state = execution.lock();
+            state.lock_owners.remove(&self.mutex.id);
+            execution.wake(&mut state, Resource::Lock(self.mutex.id));
+            drop(state);
+
+            // Switching threads while unwinding could panic again.
+            if !std_thread::panicking() {
+                execution.yield_now(me);
+            }
+        }
+    }
+
+    impl<'a, T: fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Debug::fmt(&**self, f)
+        }
+    }
+
+    pub mod atomic {
+        //! Atomics where every access is a scheduling point, so a
+        //! read-modify-write split into a `load` and a `store` can be
+        //! interleaved with other threads.
+
+        use super::super::current;
+        use std::sync::atomic::{ self, Ordering };
+
+        #[derive(Debug, Default)]
+        pub struct AtomicUsize {
+            value: atomic::AtomicUsize
+        }
+
+        impl AtomicUsize {
+            pub fn new(value: usize) -> AtomicUsize {
+                AtomicUsize { value: atomic::AtomicUsize::new(value) }
+            }
+
+            pub fn load(&self, ordering: Ordering) -> usize {
+                yield_point();
+                self.value.load(ordering)
+            }
+
+            pub fn store(&self, value: usize, ordering: Ordering) {
+                yield_point();
+                self.value.store(value, ordering);
+            }
+
+            pub fn fetch_add(&self, value: usize, ordering: Ordering) -> usize {
+                yield_point();
+                self.value.fetch_add(value, ordering)
+            }
+
+            pub fn into_inner(self) -> usize {
+                self.value.into_inner()
+            }
+        }
+
+        fn yield_point() {
+            let (execution, me) = current();
+            execution.yield_now(me);
+        }
+    }
+
+    pub mod mpsc {
+        //! An unbounded model channel. `send` and `recv` are scheduling
+        //! points and `recv` blocks in the model until a message arrives.
+
+        use super::super::{ current, next_object_id, Resource };
+        use std::collections::VecDeque;
+        use std::fmt;
+        use std::sync::mpsc::{ RecvError, SendError };
+        use std::sync::{ Arc, Mutex };
+        use std::thread as std_thread;
+
+        struct Channel<T> {
+            id: usize,
+            queue: Mutex<VecDeque<T>>,
+            senders: Mutex<usize>,
+            receiver_alive: Mutex<bool>
+        }
+
+        pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+            let channel: Arc<Channel<T>> = Arc::new(Channel {
+                id: next_object_id(),
+                queue: Mutex::new(VecDeque::new()),
+                senders: Mutex::new(1),
+                receiver_alive: Mutex::new(true)
+            });
+
+            (Sender { channel: Arc::clone(&channel) }, Receiver { channel })
+        }
+
+        pub struct Sender<T> {
+            channel: Arc<Channel<T>>
+        }
+
+        impl<T> Sender<T> {
+            pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+                let (execution, me) = current();
+                execution.yield_now(me);
+
+                if !*self.channel.receiver_alive.lock().unwrap() {
+                    return Err(SendError(value));
+                }
+                self.channel.queue.lock().unwrap().push_back(value);
+
+                let mut state = execution.lock();
+                execution.wake(&mut state, Resource::Channel(self.channel.id));
+                Ok(())
+            }
+        }
+
+        impl<T> Clone for Sender<T> {
+            fn clone(&self) -> Sender<T> {
+                *self.channel.senders.lock().unwrap() += 1;
+                Sender { channel: Arc::clone(&self.channel) }
+            }
+        }
+
+        impl<T> Drop for Sender<T> {
+            fn drop(&mut self) {
+                let mut senders = self.channel.senders.lock().unwrap_or_else(|p| p.into_inner());
+                *senders -= 1;
+                if *senders == 0 && !std_thread::panicking() {
+                    let (execution, _) = current();
+                    let mut state = execution.lock();
+                    execution.wake(&mut state, Resource::Channel(self.channel.id));
+                }
+            }
+        }
+
+        impl<T> fmt::Debug for Sender<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("Sender").field("id", &self.channel.id).finish()
+            }
+        }
+
+        pub struct Receiver<T> {
+            channel: Arc<Channel<T>>
+        }
+
+        impl<T> Receiver<T> {
+            pub fn recv(&self) -> Result<T, RecvError> {
+                let (execution, me) = current();
+                execution.yield_now(me);
+
+                let mut state = execution.lock();
+                loop {
+                    if let Some(value) = self.channel.queue.lock().unwrap().pop_front() {
+                        return Ok(value);
+                    }
+                    if *self.channel.senders.lock().unwrap() == 0 {
+                        return Err(RecvError);
+                    }
+                    state = execution.block_on(state, me, Resource::Channel(self.channel.id));
+                }
+            }
+        }
+
+        impl<T> Drop for Receiver<T> {
+            fn drop(&mut self) {
+                *self.channel.receiver_alive.lock().unwrap_or_else(|p| p.into_inner()) = false;
+            }
+        }
+
+        impl<T> fmt::Debug for Receiver<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("Receiver").field("id", &self.channel.id).finish()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Execution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Execution")
+            .field("threads", &state.threads)
+            .field("active", &state.active)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sync::atomic::AtomicUsize;
+    use super::sync::{ mpsc, Arc, Mutex };
+    use super::*;
+
+    #[test]
+    fn test_explores_both_orders() {
+        let seen: Arc<StdMutex<Vec<Vec<u32>>>> = Arc::new(StdMutex::new(Vec::new()));
+        let record = Arc::clone(&seen);
+
+        let explored: Explored = check(move || {
+            let log: Arc<Mutex<Vec<u32>>> = Arc::new(Mutex::new(Vec::new()));
+            let other = Arc::clone(&log);
+            let handle = thread::spawn(move || other.lock().push(1));
+            log.lock().push(2);
+            handle.join();
+
+            let order: Vec<u32> = log.lock().clone();
+            let mut seen = record.lock().unwrap();
+            if !seen.contains(&order) {
+                seen.push(order);
+            }
+        });
+
+        assert!(explored.complete);
+        let mut seen: Vec<Vec<u32>> = seen.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, vec![vec![1, 2], vec![2, 1]]);
+    }
+
+    #[test]
+    fn test_mutex_counter_always_reaches_ten() {
+        // The counter from src/bin/mutexes.rs. Eleven threads have far too
+        // many schedules to try them all, so bound the search.
+        let builder: Builder = Builder {
+            max_iterations: 2_000,
+            preemption_bound: Some(2)
+        };
+        let explored: Explored = builder.check(|| {
+            let counter: Arc<Mutex<u32>> = Arc::new(Mutex::new(0));
+            let mut handles = vec![];
+
+            for _ in 0..10 {
+                let counter = Arc::clone(&counter);
+                let handle = thread::spawn(move || {
+                    let mut num = counter.lock();
+                    *num += 1;
+                });
+                handles.push(handle);
+            }
+
+            for handle in handles {
+                handle.join();
+            }
+
+            assert_eq!(*counter.lock(), 10);
+        });
+
+        assert!(explored.iterations > 1);
+    }
+
+    #[test]
+    fn test_finds_lost_update() {
+        let result = panic::catch_unwind(|| {
+            check(|| {
+                let counter: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+                let handles: Vec<thread::JoinHandle<()>> = (0..2)
+                    .map(|_| {
+                        let counter = Arc::clone(&counter);
+                        thread::spawn(move || {
+                            // Not atomic: another thread can run between the two.
+                            let value: usize = counter.load(Ordering::SeqCst);
+                            counter.store(value + 1, Ordering::SeqCst);
+                        })
+                    })
+                    .collect();
+
+                for handle in handles {
+                    handle.join();
+                }
+                assert_eq!(counter.load(Ordering::SeqCst), 2);
+            });
+        });
+
+        let message: String = payload_message(&*result.unwrap_err());
+        assert!(message.contains("model check failed"), "{}", message);
+    }
+
+    #[test]
+    fn test_detects_deadlock() {
+        let result = panic::catch_unwind(|| {
+            check(|| {
+                let first: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+                let second: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+
+                let (a, b) = (Arc::clone(&first), Arc::clone(&second));
+                let handle = thread::spawn(move || {
+                    let _b = b.lock();
+                    let _a = a.lock();
+                });
+
+                {
+                    let _a = first.lock();
+                    let _b = second.lock();
+                }
+                handle.join();
+            });
+        });
+
+        let message: String = payload_message(&*result.unwrap_err());
+        assert!(message.contains("deadlock"), "{}", message);
+    }
+
+    #[test]
+    fn test_channel_delivers_everything() {
+        let explored: Explored = check(|| {
+            let (tx, rx) = mpsc::channel::<u32>();
+            let tx_clone = tx.clone();
+
+            let first = thread::spawn(move || tx.send(1).unwrap());
+            let second = thread::spawn(move || tx_clone.send(2).unwrap());
+
+            let mut received: Vec<u32> = vec![rx.recv().unwrap(), rx.recv().unwrap()];
+            received.sort();
+            assert_eq!(received, vec![1, 2]);
+
+            first.join();
+            second.join();
+            assert!(rx.recv().is_err());
+        });
+
+        assert!(explored.complete);
+        assert!(explored.iterations > 1);
+    }
+}
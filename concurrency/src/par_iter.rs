@@ -0,0 +1,239 @@
+use std::any::Any;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::Mutex;
+use std::thread;
+
+/// Number of threads to use when the caller has no better idea.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// Applies `f` to every item on up to `threads` scoped threads and returns
+/// the results in the same order as `items`.
+///
+/// The slice is borrowed, not moved, so the caller keeps ownership. If any
+/// call panics, the remaining work is abandoned and the first panic is
+/// resumed on the calling thread.
+///
+/// ```
+/// use concurrency::par_iter::par_map;
+///
+/// let vector: Vec<u32> = vec![1, 2, 3, 4, 5];
+/// let squares: Vec<u32> = par_map(&vector, 2, |num| num * num);
+/// assert_eq!(squares, vec![1, 4, 9, 16, 25]);
+/// assert_eq!(vector.len(), 5);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `threads` is zero, or re-raises a panic from `f`.
+pub fn par_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync
+{
+    let chunks: Vec<Vec<R>> = run_chunks(items, threads, |chunk, stop| {
+        let mut results: Vec<R> = Vec::with_capacity(chunk.len());
+        for item in chunk {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            results.push(f(item));
+        }
+        results
+    });
+
+    chunks.into_iter().flatten().collect()
+}
+
+/// Calls `f` on every item on up to `threads` scoped threads.
+///
+/// # Panics
+///
+/// Panics if `threads` is zero, or re-raises a panic from `f`.
+pub fn par_for_each<T, F>(items: &[T], threads: usize, f: F)
+where
+    T: Sync,
+    F: Fn(&T) + Sync
+{
+    run_chunks(items, threads, |chunk, stop| {
+        for item in chunk {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            f(item);
+        }
+    });
+}
+
+/// Folds each chunk of `items` starting from `identity()`, then combines the
+/// chunk results from left to right.
+///
+/// Chunks are combined in slice order, so `combine` only needs to be
+/// associative, not commutative.
+///
+/// ```
+/// use concurrency::par_iter::par_reduce;
+///
+/// let words: Vec<&str> = vec!["a", "b", "c", "d"];
+/// let joined: String = par_reduce(
+///     &words,
+///     3,
+///     String::new,
+///     |mut acc, word| { acc.push_str(word); acc },
+///     |left, right| left + &right
+/// );
+/// assert_eq!(joined, "abcd");
+/// ```
+///
+/// # Panics
+///
+/// Panics if `threads` is zero, or re-raises a panic from any closure.
+pub fn par_reduce<T, R, I, F, C>(items: &[T], threads: usize, identity: I, fold: F, combine: C) -> R
+where
+    T: Sync,
+    R: Send,
+    I: Fn() -> R + Sync,
+    F: Fn(R, &T) -> R + Sync,
+    C: Fn(R, R) -> R
+{
+    let partials: Vec<R> = run_chunks(items, threads, |chunk, stop| {
+        let mut acc: R = identity();
+        for item in chunk {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            acc = fold(acc, item);
+        }
+        acc
+    });
+
+    partials.into_iter().fold(identity(), combine)
+}
+
+/// Splits `items` into at most `threads` contiguous chunks, runs `work` on
+/// each in a scoped thread and returns the results in chunk order.
+fn run_chunks<T, R, W>(items: &[T], threads: usize, work: W) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&[T], &AtomicBool) -> R + Sync
+{
+    assert!(threads > 0, "Parallel iteration needs at least one thread");
+
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let chunk_size: usize = items.len().div_ceil(threads);
+    let stop: AtomicBool = AtomicBool::new(false);
+    let first_panic: Mutex<Option<Box<dyn Any + Send>>> = Mutex::new(None);
+
+    let results: Vec<Option<R>> = thread::scope(|scope| {
+        let handles: Vec<thread::ScopedJoinHandle<'_, Option<R>>> = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let work = &work;
+                let stop = &stop;
+                let first_panic = &first_panic;
+
+                scope.spawn(move || {
+                    match panic::catch_unwind(AssertUnwindSafe(|| work(chunk, stop))) {
+                        Ok(result) => Some(result),
+                        Err(payload) => {
+                            stop.store(true, Ordering::Relaxed);
+                            let mut first = first_panic.lock().unwrap_or_else(|p| p.into_inner());
+                            if first.is_none() {
+                                *first = Some(payload);
+                            }
+                            None
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Chunk panics are caught inside the thread"))
+            .collect()
+    });
+
+    if let Some(payload) = first_panic.into_inner().unwrap_or_else(|p| p.into_inner()) {
+        panic::resume_unwind(payload);
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.expect("Every chunk finished when nothing panicked"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_par_map_preserves_order() {
+        let items: Vec<u64> = (0..1000).collect();
+
+        for threads in 1..=8 {
+            let doubled: Vec<u64> = par_map(&items, threads, |item| item * 2);
+            assert_eq!(doubled, items.iter().map(|item| item * 2).collect::<Vec<u64>>());
+        }
+
+        let empty: Vec<u64> = Vec::new();
+        assert!(par_map(&empty, 4, |item| *item).is_empty());
+    }
+
+    #[test]
+    fn test_par_for_each_visits_everything() {
+        let items: Vec<usize> = (1..=100).collect();
+        let total: AtomicUsize = AtomicUsize::new(0);
+
+        par_for_each(&items, 7, |item| {
+            total.fetch_add(*item, Ordering::SeqCst);
+        });
+
+        assert_eq!(total.into_inner(), 5050);
+    }
+
+    #[test]
+    fn test_par_reduce_keeps_chunk_order() {
+        let digits: Vec<u32> = (0..10).collect();
+        let text: String = par_reduce(
+            &digits,
+            4,
+            String::new,
+            |mut acc, digit| {
+                acc.push_str(&digit.to_string());
+                acc
+            },
+            |left, right| left + &right
+        );
+        assert_eq!(text, "0123456789");
+
+        let sum: u32 = par_reduce(&digits, 3, || 0, |acc, digit| acc + digit, |a, b| a + b);
+        assert_eq!(sum, 45);
+    }
+
+    #[test]
+    fn test_panic_is_propagated() {
+        let items: Vec<u32> = (0..100).collect();
+
+        let result = panic::catch_unwind(|| {
+            par_map(&items, 4, |&item| {
+                if item == 42 {
+                    panic!("Bad item {}", item);
+                }
+                item
+            })
+        });
+
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<String>().map(String::as_str), Some("Bad item 42"));
+    }
+}
@@ -0,0 +1,645 @@
+//! Blocking synchronisation primitives built from `std::sync::Mutex` and
+//! `Condvar`: a FIFO [`Semaphore`], a reusable [`Barrier`], a
+//! [`CountDownLatch`] and a writer-preferring [`RwLock`].
+//!
+//! None of these poison: a panic while holding a permit or guard releases it
+//! like a normal drop.
+
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::{ Deref, DerefMut };
+use std::sync::{ Condvar, Mutex, MutexGuard };
+use std::time::{ Duration, Instant };
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct SemaphoreState {
+    permits: usize,
+    queue: VecDeque<u64>,
+    next_ticket: u64
+}
+
+/// A counting semaphore that hands out permits in arrival order.
+pub struct Semaphore {
+    state: Mutex<SemaphoreState>,
+    condvar: Condvar
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            state: Mutex::new(SemaphoreState {
+                permits,
+                queue: VecDeque::new(),
+                next_ticket: 0
+            }),
+            condvar: Condvar::new()
+        }
+    }
+
+    /// Waits for a permit. Threads are served first come, first served.
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        self.acquire_until(None).expect("Waiting without a deadline always succeeds")
+    }
+
+    /// Takes a permit only if one is free and nobody is queued for it.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let mut state = lock(&self.state);
+        if state.permits > 0 && state.queue.is_empty() {
+            state.permits -= 1;
+            Some(SemaphorePermit { semaphore: self })
+        } else {
+            None
+        }
+    }
+
+    /// Like `acquire`, but gives up after `timeout`. A timeout too long to
+    /// add to `Instant::now()` waits like `acquire`.
+    pub fn acquire_timeout(&self, timeout: Duration) -> Option<SemaphorePermit<'_>> {
+        self.acquire_until(Instant::now().checked_add(timeout))
+    }
+
+    pub fn available_permits(&self) -> usize {
+        lock(&self.state).permits
+    }
+
+    pub fn add_permits(&self, permits: usize) {
+        lock(&self.state).permits += permits;
+        self.condvar.notify_all();
+    }
+
+    fn acquire_until(&self, deadline: Option<Instant>) -> Option<SemaphorePermit<'_>> {
+        let mut state = lock(&self.state);
+        let ticket: u64 = state.next_ticket;
+        state.next_ticket += 1;
+        state.queue.push_back(ticket);
+
+        loop {
+            if state.permits > 0 && state.queue.front() == Some(&ticket) {
+                state.permits -= 1;
+                state.queue.pop_front();
+                drop(state);
+                // The next thread in line may be able to go too.
+                self.condvar.notify_all();
+                return Some(SemaphorePermit { semaphore: self });
+            }
+
+            state = match deadline {
+                None => self.condvar.wait(state).unwrap_or_else(|p| p.into_inner()),
+                Some(deadline) => {
+                    let now: Instant = Instant::now();
+                    if now >= deadline {
+                        state.queue.retain(|&queued| queued != ticket);
+                        drop(state);
+                        // We may have been blocking the head of the queue.
+                        self.condvar.notify_all();
+                        return None;
+                    }
+                    self.condvar
+                        .wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|p| p.into_inner())
+                        .0
+                }
+            };
+        }
+    }
+
+    fn release(&self) {
+        lock(&self.state).permits += 1;
+        self.condvar.notify_all();
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = lock(&self.state);
+        f.debug_struct("Semaphore")
+            .field("permits", &state.permits)
+            .field("waiting", &state.queue.len())
+            .finish()
+    }
+}
+
+/// A permit from a [`Semaphore`], returned when dropped.
+#[derive(Debug)]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore
+}
+
+impl<'a> Drop for SemaphorePermit<'a> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+struct BarrierState {
+    arrived: usize,
+    generation: u64
+}
+
+/// A barrier for `parties` threads that can be reused round after round.
+///
+/// Each round is a generation. Waiters sleep until the generation they
+/// arrived in has ended, so a fast thread that races into the next round
+/// can't be mistaken for a late arrival in the previous one.
+pub struct Barrier {
+    parties: usize,
+    state: Mutex<BarrierState>,
+    condvar: Condvar
+}
+
+/// Returned by [`Barrier::wait`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    /// The generation that this wait completed.
+    pub generation: u64,
+    leader: bool
+}
+
+impl BarrierWaitResult {
+    /// `true` for exactly one thread per generation: the last to arrive.
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+}
+
+impl Barrier {
+    /// # Panics
+    ///
+    /// Panics if `parties` is zero.
+    pub fn new(parties: usize) -> Barrier {
+        assert!(parties > 0, "A barrier needs at least one party");
+
+        Barrier {
+            parties,
+            state: Mutex::new(BarrierState { arrived: 0, generation: 0 }),
+            condvar: Condvar::new()
+        }
+    }
+
+    pub fn wait(&self) -> BarrierWaitResult {
+        let mut state = lock(&self.state);
+        let generation: u64 = state.generation;
+        state.arrived += 1;
+
+        if state.arrived == self.parties {
+            state.arrived = 0;
+            state.generation += 1;
+            drop(state);
+            self.condvar.notify_all();
+            return BarrierWaitResult { generation, leader: true };
+        }
+
+        while state.generation == generation {
+            state = self.condvar.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+
+        BarrierWaitResult { generation, leader: false }
+    }
+
+    /// Number of completed rounds.
+    pub fn generation(&self) -> u64 {
+        lock(&self.state).generation
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = lock(&self.state);
+        f.debug_struct("Barrier")
+            .field("parties", &self.parties)
+            .field("arrived", &state.arrived)
+            .field("generation", &state.generation)
+            .finish()
+    }
+}
+
+/// Lets threads wait until `count` events have happened. Single use.
+pub struct CountDownLatch {
+    count: Mutex<usize>,
+    condvar: Condvar
+}
+
+impl CountDownLatch {
+    pub fn new(count: usize) -> CountDownLatch {
+        CountDownLatch {
+            count: Mutex::new(count),
+            condvar: Condvar::new()
+        }
+    }
+
+    /// Decrements the count, releasing the waiters when it reaches zero.
+    /// Counting down past zero does nothing.
+    pub fn count_down(&self) {
+        let mut count = lock(&self.count);
+        if *count > 0 {
+            *count -= 1;
+            if *count == 0 {
+                drop(count);
+                self.condvar.notify_all();
+            }
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        *lock(&self.count)
+    }
+
+    pub fn wait(&self) {
+        let mut count = lock(&self.count);
+        while *count > 0 {
+            count = self.condvar.wait(count).unwrap_or_else(|p| p.into_inner());
+        }
+    }
+
+    /// Returns `false` if the timeout passed before the count reached zero.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let count = lock(&self.count);
+        let (count, _) = self.condvar
+            .wait_timeout_while(count, timeout, |count| *count > 0)
+            .unwrap_or_else(|p| p.into_inner());
+        *count == 0
+    }
+}
+
+impl fmt::Debug for CountDownLatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountDownLatch").field("count", &self.count()).finish()
+    }
+}
+
+#[derive(Default)]
+struct RwState {
+    readers: usize,
+    writer: bool,
+    waiting_writers: usize
+}
+
+/// A reader-writer lock that prefers writers.
+///
+/// Once a writer is waiting, new readers queue behind it, so a steady
+/// stream of readers can't starve writers the way a reader-preferring lock
+/// would.
+pub struct RwLock<T: ?Sized> {
+    state: Mutex<RwState>,
+    readers: Condvar,
+    writers: Condvar,
+    data: UnsafeCell<T>
+}
+
+// Safety: access to `data` is coordinated through `state` exactly like
+// `std::sync::RwLock`: many `&T` at once, or one `&mut T`.
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub fn new(value: T) -> RwLock<T> {
+        RwLock {
+            state: Mutex::new(RwState::default()),
+            readers: Condvar::new(),
+            writers: Condvar::new(),
+            data: UnsafeCell::new(value)
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Waits until no writer holds or is waiting for the lock.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let mut state = lock(&self.state);
+        while state.writer || state.waiting_writers > 0 {
+            state = self.readers.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+        state.readers += 1;
+
+        RwLockReadGuard { lock: self }
+    }
+
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut state = lock(&self.state);
+        if state.writer || state.waiting_writers > 0 {
+            return None;
+        }
+        state.readers += 1;
+
+        Some(RwLockReadGuard { lock: self })
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let mut state = lock(&self.state);
+        state.waiting_writers += 1;
+        while state.writer || state.readers > 0 {
+            state = self.writers.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+        state.waiting_writers -= 1;
+        state.writer = true;
+
+        RwLockWriteGuard { lock: self }
+    }
+
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let mut state = lock(&self.state);
+        if state.writer || state.readers > 0 {
+            return None;
+        }
+        state.writer = true;
+
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    fn unlock_read(&self) {
+        let mut state = lock(&self.state);
+        state.readers -= 1;
+        if state.readers == 0 && state.waiting_writers > 0 {
+            drop(state);
+            self.writers.notify_one();
+        }
+    }
+
+    fn unlock_write(&self) {
+        let mut state = lock(&self.state);
+        state.writer = false;
+        let writers_waiting: bool = state.waiting_writers > 0;
+        drop(state);
+
+        if writers_waiting {
+            self.writers.notify_one();
+        } else {
+            self.readers.notify_all();
+        }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> RwLock<T> {
+        RwLock::new(T::default())
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = lock(&self.state);
+        f.debug_struct("RwLock")
+            .field("readers", &state.readers)
+            .field("writer", &state.writer)
+            .field("waiting_writers", &state.waiting_writers)
+            .finish()
+    }
+}
+
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>
+}
+
+impl<'a, T: ?Sized> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: the guard counts as a reader, so no writer can exist.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_read();
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>
+}
+
+impl<'a, T: ?Sized> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: the guard is the only writer and no readers exist.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: as above, and `&mut self` makes this borrow unique.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_write();
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_semaphore_limits_concurrency() {
+        let semaphore: Arc<Semaphore> = Arc::new(Semaphore::new(3));
+        let inside: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let max_inside: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut handles = vec![];
+
+        for _ in 0..32 {
+            let semaphore = Arc::clone(&semaphore);
+            let inside = Arc::clone(&inside);
+            let max_inside = Arc::clone(&max_inside);
+            handles.push(thread::spawn(move || {
+                for _ in 0..200 {
+                    let _permit = semaphore.acquire();
+                    let now: usize = inside.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_inside.fetch_max(now, Ordering::SeqCst);
+                    inside.fetch_sub(1, Ordering::SeqCst);
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(max_inside.load(Ordering::SeqCst) <= 3);
+        assert_eq!(semaphore.available_permits(), 3);
+    }
+
+    #[test]
+    fn test_semaphore_is_fifo() {
+        let semaphore: Arc<Semaphore> = Arc::new(Semaphore::new(1));
+        let order: Arc<Mutex<Vec<usize>>> = Arc::new(Mutex::new(vec![]));
+        let held = semaphore.acquire();
+        let mut handles = vec![];
+
+        for id in 0..5 {
+            let waiter = Arc::clone(&semaphore);
+            let order = Arc::clone(&order);
+            handles.push(thread::spawn(move || {
+                let _permit = waiter.acquire();
+                order.lock().unwrap().push(id);
+            }));
+            // Wait until this thread is queued before starting the next.
+            while lock(&semaphore.state).queue.len() < id + 1 {
+                thread::yield_now();
+            }
+        }
+
+        assert!(semaphore.try_acquire().is_none());
+        assert!(semaphore.acquire_timeout(Duration::from_millis(5)).is_none());
+        drop(held);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+        assert!(semaphore.acquire_timeout(Duration::MAX).is_some());
+    }
+
+    #[test]
+    fn test_barrier_generations() {
+        const PARTIES: usize = 16;
+        const ROUNDS: usize = 100;
+
+        let barrier: Arc<Barrier> = Arc::new(Barrier::new(PARTIES));
+        let counter: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let leaders: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut handles = vec![];
+
+        for _ in 0..PARTIES {
+            let barrier = Arc::clone(&barrier);
+            let counter = Arc::clone(&counter);
+            let leaders = Arc::clone(&leaders);
+            handles.push(thread::spawn(move || {
+                for round in 0..ROUNDS {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let result: BarrierWaitResult = barrier.wait();
+                    assert_eq!(result.generation, (round * 2) as u64);
+                    assert!(counter.load(Ordering::SeqCst) >= (round + 1) * PARTIES);
+                    if result.is_leader() {
+                        leaders.fetch_add(1, Ordering::SeqCst);
+                    }
+                    barrier.wait();
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(leaders.load(Ordering::SeqCst), ROUNDS);
+        assert_eq!(barrier.generation(), (ROUNDS * 2) as u64);
+    }
+
+    #[test]
+    fn test_latch_releases_every_waiter() {
+        let latch: Arc<CountDownLatch> = Arc::new(CountDownLatch::new(20));
+        let released: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut handles = vec![];
+
+        for _ in 0..10 {
+            let latch = Arc::clone(&latch);
+            let released = Arc::clone(&released);
+            handles.push(thread::spawn(move || {
+                latch.wait();
+                released.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+
+        assert!(!latch.wait_timeout(Duration::from_millis(5)));
+        for _ in 0..20 {
+            let latch = Arc::clone(&latch);
+            handles.push(thread::spawn(move || latch.count_down()));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(released.load(Ordering::SeqCst), 10);
+        assert_eq!(latch.count(), 0);
+        latch.count_down();
+        assert!(latch.wait_timeout(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_rwlock_prefers_writers() {
+        let lock: Arc<RwLock<u32>> = Arc::new(RwLock::new(0));
+        let reader = lock.read();
+
+        let writer_lock = Arc::clone(&lock);
+        let writer = thread::spawn(move || {
+            *writer_lock.write() += 1;
+        });
+        while lock.state.lock().unwrap().waiting_writers == 0 {
+            thread::yield_now();
+        }
+
+        // A reader arriving now has to wait behind the queued writer.
+        assert!(lock.try_read().is_none());
+        drop(reader);
+        writer.join().unwrap();
+        assert_eq!(*lock.read(), 1);
+    }
+
+    #[test]
+    fn test_rwlock_under_contention() {
+        let lock: Arc<RwLock<(u64, u64)>> = Arc::new(RwLock::new((0, 0)));
+        let mut handles = vec![];
+
+        for _ in 0..8 {
+            let lock = Arc::clone(&lock);
+            handles.push(thread::spawn(move || {
+                for _ in 0..1000 {
+                    let mut pair = lock.write();
+                    pair.0 += 1;
+                    pair.1 += 1;
+                }
+            }));
+        }
+
+        for _ in 0..8 {
+            let lock = Arc::clone(&lock);
+            handles.push(thread::spawn(move || {
+                for _ in 0..1000 {
+                    let pair = lock.read();
+                    assert_eq!(pair.0, pair.1);
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let lock: RwLock<(u64, u64)> = Arc::try_unwrap(lock).unwrap();
+        assert_eq!(lock.into_inner(), (8000, 8000));
+    }
+}
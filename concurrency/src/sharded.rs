@@ -0,0 +1,328 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{ BuildHasher, Hash };
+use std::sync::atomic::{ AtomicU64, AtomicUsize, Ordering };
+use std::sync::{ Mutex, MutexGuard };
+use std::thread;
+
+/// Default shard count: a few per core so unrelated writers rarely collide.
+fn default_shards() -> usize {
+    let cores: usize = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    (cores * 4).next_power_of_two()
+}
+
+/// Keeps each shard on its own cache line so neighbouring shards don't
+/// bounce the same line between cores.
+#[repr(align(64))]
+#[derive(Default)]
+struct Padded<T>(T);
+
+type Shard<K, V> = Padded<Mutex<HashMap<K, V>>>;
+
+static NEXT_THREAD_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_SLOT: usize = NEXT_THREAD_SLOT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A counter split into per-thread shards.
+///
+/// Writers on different threads land on different shards, so increments
+/// don't serialise behind one lock the way `Arc<Mutex<u32>>` does. Reading
+/// the total sums every shard.
+pub struct ShardedCounter {
+    shards: Box<[Padded<AtomicU64>]>
+}
+
+impl ShardedCounter {
+    pub fn new() -> ShardedCounter {
+        ShardedCounter::with_shards(default_shards())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> ShardedCounter {
+        assert!(shards > 0, "A sharded counter needs at least one shard");
+
+        ShardedCounter {
+            shards: (0..shards).map(|_| Padded::default()).collect()
+        }
+    }
+
+    pub fn add(&self, amount: u64) {
+        let slot: usize = THREAD_SLOT.with(|slot| *slot);
+        self.shards[slot % self.shards.len()].0.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Sum of all shards. Concurrent `add`s may or may not be included.
+    pub fn get(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.0.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Resets the counter, returning the total it held.
+    pub fn reset(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.0.swap(0, Ordering::Relaxed)).sum()
+    }
+}
+
+impl Default for ShardedCounter {
+    fn default() -> ShardedCounter {
+        ShardedCounter::new()
+    }
+}
+
+impl fmt::Debug for ShardedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedCounter")
+            .field("shards", &self.shards.len())
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+/// A `HashMap` split into lock-striped shards.
+///
+/// Each key hashes to one shard guarded by its own `Mutex`, so threads
+/// working on different keys mostly take different locks. Values can't be
+/// borrowed out past the lock, so reads either clone or run a closure.
+pub struct ShardedMap<K, V, S = RandomState> {
+    shards: Box<[Shard<K, V>]>,
+    hasher: S
+}
+
+impl<K: Hash + Eq, V> ShardedMap<K, V, RandomState> {
+    pub fn new() -> ShardedMap<K, V, RandomState> {
+        ShardedMap::with_shards_and_hasher(default_shards(), RandomState::new())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> ShardedMap<K, V, RandomState> {
+        ShardedMap::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> ShardedMap<K, V, S> {
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> ShardedMap<K, V, S> {
+        assert!(shards > 0, "A sharded map needs at least one shard");
+
+        ShardedMap {
+            shards: (0..shards).map(|_| Padded(Mutex::new(HashMap::new()))).collect(),
+            hasher
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.shard(&key).insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.shard(key).remove(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.shard(key).contains_key(key)
+    }
+
+    /// Returns a clone of the value for `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone
+    {
+        self.shard(key).get(key).cloned()
+    }
+
+    /// Runs `f` on the value for `key` while its shard is locked.
+    pub fn get_with<Q, F, R>(&self, key: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V) -> R
+    {
+        self.shard(key).get(key).map(f)
+    }
+
+    /// The sharded version of `entry(key).or_insert(default)`: inserts
+    /// `default` if `key` is missing, then runs `f` on the value.
+    ///
+    /// ```
+    /// use concurrency::sharded::ShardedMap;
+    ///
+    /// let map: ShardedMap<&str, i32> = ShardedMap::new();
+    /// for word in "hello world wonderful world".split_whitespace() {
+    ///     map.update_or_insert(word, 0, |count| *count += 1);
+    /// }
+    /// assert_eq!(map.get("world"), Some(2));
+    /// ```
+    pub fn update_or_insert<F, R>(&self, key: K, default: V, f: F) -> R
+    where
+        F: FnOnce(&mut V) -> R
+    {
+        let mut shard = self.shard(&key);
+        f(shard.entry(key).or_insert(default))
+    }
+
+    /// Total number of entries. Shards are locked one at a time, so the
+    /// result is only exact when no other thread is writing.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| lock(&shard.0).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| lock(&shard.0).is_empty())
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            lock(&shard.0).clear();
+        }
+    }
+
+    /// Calls `f` on every entry, one shard at a time.
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        for shard in self.shards.iter() {
+            for (key, value) in lock(&shard.0).iter() {
+                f(key, value);
+            }
+        }
+    }
+
+    /// Copies every entry into a plain `HashMap`.
+    pub fn snapshot(&self) -> HashMap<K, V>
+    where
+        K: Clone,
+        V: Clone
+    {
+        let mut snapshot: HashMap<K, V> = HashMap::new();
+        self.for_each(|key, value| {
+            snapshot.insert(key.clone(), value.clone());
+        });
+        snapshot
+    }
+
+    /// Consumes the map and merges the shards into one `HashMap`.
+    pub fn into_inner(self) -> HashMap<K, V> {
+        let mut merged: HashMap<K, V> = HashMap::new();
+        for shard in self.shards.into_vec() {
+            merged.extend(shard.0.into_inner().unwrap_or_else(|p| p.into_inner()));
+        }
+        merged
+    }
+
+    fn shard<Q>(&self, key: &Q) -> MutexGuard<'_, HashMap<K, V>>
+    where
+        Q: Hash + ?Sized
+    {
+        let index: usize = (self.hasher.hash_one(key) % self.shards.len() as u64) as usize;
+        lock(&self.shards[index].0)
+    }
+}
+
+impl<K: Hash + Eq, V> Default for ShardedMap<K, V, RandomState> {
+    fn default() -> ShardedMap<K, V, RandomState> {
+        ShardedMap::new()
+    }
+}
+
+impl<K, V, S> fmt::Debug for ShardedMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedMap").field("shards", &self.shards.len()).finish()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic in a user closure can poison a shard; the map itself is still
+    // consistent, so keep serving it.
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_counter_reaches_total() {
+        let counter: Arc<ShardedCounter> = Arc::new(ShardedCounter::with_shards(4));
+        let mut handles = vec![];
+
+        for _ in 0..10 {
+            let counter = Arc::clone(&counter);
+            handles.push(thread::spawn(move || {
+                for _ in 0..1000 {
+                    counter.increment();
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(counter.get(), 10_000);
+        assert_eq!(counter.reset(), 10_000);
+        assert_eq!(counter.get(), 0);
+    }
+
+    #[test]
+    fn test_map_concurrent_updates() {
+        let map: Arc<ShardedMap<u32, u32>> = Arc::new(ShardedMap::with_shards(8));
+        let mut handles = vec![];
+
+        for _ in 0..8 {
+            let map = Arc::clone(&map);
+            handles.push(thread::spawn(move || {
+                for key in 0..100 {
+                    map.update_or_insert(key, 0, |value| *value += 1);
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 100);
+        assert!(map.snapshot().values().all(|&value| value == 8));
+    }
+
+    #[test]
+    fn test_map_basic_operations() {
+        let map: ShardedMap<String, i32> = ShardedMap::with_shards(2);
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(String::from("RCB"), 23), None);
+        assert_eq!(map.insert(String::from("RCB"), 24), Some(23));
+        map.insert(String::from("SRH"), 22);
+
+        assert_eq!(map.get("RCB"), Some(24));
+        assert_eq!(map.get_with("SRH", |score| score * 2), Some(44));
+        assert!(map.contains_key("SRH"));
+        assert_eq!(map.remove("SRH"), Some(22));
+        assert_eq!(map.get("SRH"), None);
+
+        let inner: HashMap<String, i32> = map.into_inner();
+        assert_eq!(inner.len(), 1);
+    }
+}
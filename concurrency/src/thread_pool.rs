@@ -0,0 +1,197 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::mpsc;
+use std::sync::{ Arc, Mutex };
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of worker threads pulling jobs off a shared `mpsc` queue.
+///
+/// Dropping the pool closes the queue, lets the workers finish every job
+/// that was already submitted, and joins them.
+pub struct ThreadPool {
+    workers: Vec<Worker>,
+    sender: Option<mpsc::Sender<Job>>
+}
+
+impl ThreadPool {
+    /// Creates a pool with `size` workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(size: usize) -> ThreadPool {
+        assert!(size > 0, "A thread pool needs at least one worker");
+
+        let (sender, receiver) = mpsc::channel();
+        let receiver: Arc<Mutex<mpsc::Receiver<Job>>> = Arc::new(Mutex::new(receiver));
+
+        let mut workers: Vec<Worker> = Vec::with_capacity(size);
+        for id in 0..size {
+            workers.push(Worker::new(id, Arc::clone(&receiver)));
+        }
+
+        ThreadPool {
+            workers,
+            sender: Some(sender)
+        }
+    }
+
+    /// Number of worker threads in the pool.
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queues `f` to run on one of the workers.
+    ///
+    /// A panic inside `f` is caught on the worker and handed back through the
+    /// returned handle, so the worker stays alive for the next job.
+    pub fn execute<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static
+    {
+        let (result_tx, result_rx) = mpsc::channel();
+
+        let job: Job = Box::new(move || {
+            let result: thread::Result<T> = panic::catch_unwind(AssertUnwindSafe(f));
+            // The caller may have dropped the handle; that's fine.
+            let _ = result_tx.send(result);
+        });
+
+        self.sender
+            .as_ref()
+            .expect("The pool's sender is only taken on drop")
+            .send(job)
+            .expect("Workers only exit after the sender is dropped");
+
+        JobHandle { receiver: result_rx }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel makes every worker's `recv` fail once the queue
+        // has been drained.
+        drop(self.sender.take());
+
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                thread.join().unwrap();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadPool")
+            .field("size", &self.workers.len())
+            .finish()
+    }
+}
+
+/// Handle to the result of a job submitted with [`ThreadPool::execute`].
+#[derive(Debug)]
+pub struct JobHandle<T> {
+    receiver: mpsc::Receiver<thread::Result<T>>
+}
+
+impl<T> JobHandle<T> {
+    /// Blocks until the job has run, mirroring `thread::JoinHandle::join`:
+    /// `Err` carries the job's panic payload.
+    pub fn join(self) -> thread::Result<T> {
+        match self.receiver.recv() {
+            Ok(result) => result,
+            Err(_) => Err(Box::new("The job was dropped before it ran") as Box<dyn Any + Send>)
+        }
+    }
+}
+
+struct Worker {
+    id: usize,
+    thread: Option<thread::JoinHandle<()>>
+}
+
+impl Worker {
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
+        let thread = thread::Builder::new()
+            .name(format!("pool-worker-{}", id))
+            .spawn(move || loop {
+                // The guard is dropped at the end of this statement, so the
+                // lock isn't held while the job runs.
+                let message = receiver.lock().unwrap().recv();
+
+                match message {
+                    Ok(job) => job(),
+                    Err(_) => break
+                }
+            })
+            .expect("Failed to spawn a pool worker");
+
+        Worker {
+            id,
+            thread: Some(thread)
+        }
+    }
+}
+
+impl fmt::Debug for Worker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker").field("id", &self.id).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use std::time::Duration;
+
+    #[test]
+    fn test_execute_returns_result() {
+        let pool: ThreadPool = ThreadPool::new(4);
+
+        let handles: Vec<JobHandle<u32>> = (0..8).map(|i| pool.execute(move || i * 2)).collect();
+        let results: Vec<u32> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10, 12, 14]);
+    }
+
+    #[test]
+    fn test_panicking_job_does_not_kill_worker() {
+        let pool: ThreadPool = ThreadPool::new(1);
+
+        let failed = pool.execute(|| -> u32 { panic!("Job failed") });
+        let payload = failed.join().unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"Job failed"));
+
+        assert_eq!(pool.execute(|| 5).join().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_drop_runs_queued_jobs() {
+        let counter: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        {
+            let pool: ThreadPool = ThreadPool::new(2);
+            for _ in 0..10 {
+                let counter = Arc::clone(&counter);
+                pool.execute(move || {
+                    thread::sleep(Duration::from_millis(5));
+                    counter.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        }
+
+        assert_eq!(counter.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one worker")]
+    fn test_zero_workers() {
+        ThreadPool::new(0);
+    }
+}
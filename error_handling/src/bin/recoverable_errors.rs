@@ -1,14 +1,12 @@
+use error_handling::error::{ AppError, ResultExt };
+use error_handling::file::OpenPolicy;
 use std::fs::File;
-use std::io::{
-    ErrorKind,
-    Error,
-    Read
-};
+use std::io::Read;
 use std::net::IpAddr;
 
 fn main() {
     {
-        let _file: File = match File::open("hello.txt") {
+        /*let _file: File = match File::open("hello.txt") {
             Ok(file) => file,
             Err(error) => match error.kind() {
                 ErrorKind::NotFound => match File::create("hello.txt") {
@@ -17,6 +15,11 @@ fn main() {
                 },
                 other_error => panic!("Problem opening the file {:?}", other_error)
             }
+        };*/
+
+        let _file: File = match OpenPolicy::open_or_create().open("hello.txt") {
+            Ok(file) => file,
+            Err(error) => panic!("{}", error)
         };
     }
 
@@ -34,7 +37,7 @@ fn main() {
     }
 }
 
-fn _read_username_from_file(file_name: &str) -> Result<String, Error> {
+fn _read_username_from_file(file_name: &str) -> Result<String, AppError> {
     /*let f: Result<File, Error> = File::open(file_name);
 
     let mut f: File = match f {
@@ -54,7 +57,13 @@ fn _read_username_from_file(file_name: &str) -> Result<String, Error> {
     f.read_to_string(&mut s)?;
     Ok(s)*/
 
-    let mut s: String = String::new();
+    /*let mut s: String = String::new();
     File::open(file_name)?.read_to_string(&mut s)?;
+    Ok(s)*/
+
+    let mut s: String = String::new();
+    File::open(file_name)
+        .and_then(|mut file| file.read_to_string(&mut s))
+        .with_context(|| format!("while reading user file {}", file_name))?;
     Ok(s)
 }
\ No newline at end of file
@@ -0,0 +1,261 @@
+//! One error type for the whole application. Every error has a [`Kind`] to
+//! branch on, a message for users, a chain of context describing what was
+//! being done when it happened, and the underlying cause as its `source()`.
+//!
+//! ```
+//! use error_handling::error::{ AppError, Kind, ResultExt };
+//! use std::fs;
+//!
+//! fn read_port(path: &str) -> Result<u16, AppError> {
+//!     let text: String = fs::read_to_string(path)
+//!         .with_context(|| format!("while reading config file {}", path))?;
+//!     let port: u16 = text.trim().parse().context("while parsing the port")?;
+//!     Ok(port)
+//! }
+//!
+//! let error: AppError = read_port("no-such-file.conf").unwrap_err();
+//! assert_eq!(error.kind(), Kind::NotFound);
+//! assert_eq!(error.to_string(), "while reading config file no-such-file.conf: file not found");
+//! ```
+//!
+//! `{}` renders one line for users; `{:?}` adds the kind and the full chain
+//! of causes for logs.
+
+use std::char::ParseCharError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::AddrParseError;
+use std::num::{ ParseFloatError, ParseIntError };
+use std::str::ParseBoolError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    NotFound,
+    /// Something that should have been new was already there.
+    AlreadyExists,
+    Permission,
+    /// Input that couldn't be understood.
+    Parse,
+    /// Settings that are missing or contradict each other.
+    Config,
+    /// Any other I/O failure.
+    Io
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            Kind::NotFound => "not found",
+            Kind::AlreadyExists => "already exists",
+            Kind::Permission => "permission denied",
+            Kind::Parse => "parse error",
+            Kind::Config => "configuration error",
+            Kind::Io => "I/O error"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+pub struct AppError {
+    kind: Kind,
+    message: String,
+    /// Innermost first.
+    context: Vec<String>,
+    source: Option<Box<dyn Error + Send + Sync + 'static>>
+}
+
+impl AppError {
+    pub fn new<M: Into<String>>(kind: Kind, message: M) -> AppError {
+        AppError { kind, message: message.into(), context: Vec::new(), source: None }
+    }
+
+    pub fn with_source<M, E>(kind: Kind, message: M, source: E) -> AppError
+    where
+        M: Into<String>,
+        E: Error + Send + Sync + 'static
+    {
+        AppError { source: Some(Box::new(source)), ..AppError::new(kind, message) }
+    }
+
+    pub fn config<M: Into<String>>(message: M) -> AppError {
+        AppError::new(Kind::Config, message)
+    }
+
+    pub fn parse<M: Into<String>>(message: M) -> AppError {
+        AppError::new(Kind::Parse, message)
+    }
+
+    /// Adds a description of what was being done, such as "while reading
+    /// user file users.txt". Each call wraps the ones before it.
+    pub fn context<C: Into<String>>(mut self, context: C) -> AppError {
+        self.context.push(context.into());
+        self
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The context, outermost first, as it's displayed.
+    pub fn context_chain(&self) -> impl Iterator<Item = &str> {
+        self.context.iter().rev().map(String::as_str)
+    }
+}
+
+/// The line shown to users: the context, outermost first, then the message.
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for context in self.context_chain() {
+            write!(f, "{}: ", context)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The report for logs: the kind and message, each piece of context on its
+/// own line, then every error in the `source()` chain.
+impl fmt::Debug for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)?;
+        for context in self.context_chain() {
+            write!(f, "\n  {}", context)?;
+        }
+
+        let mut source: Option<&(dyn Error + 'static)> = self.source();
+        if source.is_some() {
+            write!(f, "\nCaused by:")?;
+        }
+        while let Some(error) = source {
+            write!(f, "\n  {}", error)?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(error: io::Error) -> AppError {
+        let (kind, message) = match error.kind() {
+            io::ErrorKind::NotFound => (Kind::NotFound, String::from("file not found")),
+            io::ErrorKind::AlreadyExists => (Kind::AlreadyExists, String::from("file already exists")),
+            io::ErrorKind::PermissionDenied => (Kind::Permission, String::from("permission denied")),
+            _ => (Kind::Io, error.to_string())
+        };
+        AppError::with_source(kind, message, error)
+    }
+}
+
+macro_rules! impl_from_parse_error {
+    ($($error:ty),*) => {
+        $(
+            impl From<$error> for AppError {
+                fn from(error: $error) -> AppError {
+                    AppError::with_source(Kind::Parse, error.to_string(), error)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_parse_error!(ParseIntError, ParseFloatError, ParseBoolError, ParseCharError, AddrParseError);
+
+/// Adds context to any `Result` whose error converts into an [`AppError`].
+pub trait ResultExt<T> {
+    fn context<C: Into<String>>(self, context: C) -> AppResult<T>;
+
+    /// Like `context`, but only builds the message if there's an error.
+    fn with_context<C, F>(self, context: F) -> AppResult<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+impl<T, E: Into<AppError>> ResultExt<T> for Result<T, E> {
+    fn context<C: Into<String>>(self, context: C) -> AppResult<T> {
+        self.map_err(|error| error.into().context(context))
+    }
+
+    fn with_context<C, F>(self, context: F) -> AppResult<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C
+    {
+        self.map_err(|error| error.into().context(context()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn read_number(path: &str) -> AppResult<i64> {
+        let text: String = fs::read_to_string(path).with_context(|| format!("while reading user file {}", path))?;
+        text.trim().parse::<i64>().context("while parsing the user id")
+    }
+
+    #[test]
+    fn test_io_kinds() {
+        let error: AppError = read_number("/definitely/not/here.txt").unwrap_err();
+        assert_eq!(error.kind(), Kind::NotFound);
+        assert_eq!(error.message(), "file not found");
+        let source: &io::Error = error.source().unwrap().downcast_ref().unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::NotFound);
+
+        let denied: AppError = io::Error::new(io::ErrorKind::PermissionDenied, "nope").into();
+        assert_eq!(denied.kind(), Kind::Permission);
+        let other: AppError = io::Error::other("disk on fire").into();
+        assert_eq!((other.kind(), other.message()), (Kind::Io, "disk on fire"));
+    }
+
+    #[test]
+    fn test_parse_conversions() {
+        let path: std::path::PathBuf = std::env::temp_dir().join("error-handling-user-id.txt");
+        fs::write(&path, "forty two").unwrap();
+        let error: AppError = read_number(path.to_str().unwrap()).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), Kind::Parse);
+        assert_eq!(error.to_string(), "while parsing the user id: invalid digit found in string");
+        assert!(error.source().unwrap().is::<ParseIntError>());
+
+        let ip: Result<std::net::IpAddr, AppError> = "home".parse::<std::net::IpAddr>().map_err(AppError::from);
+        assert_eq!(ip.unwrap_err().kind(), Kind::Parse);
+    }
+
+    #[test]
+    fn test_context_chains_outermost_first() {
+        let error: AppError = AppError::config("missing 'port'")
+            .context("while loading settings.toml")
+            .context("while starting the server");
+        assert_eq!(error.to_string(), "while starting the server: while loading settings.toml: missing 'port'");
+        assert_eq!(error.context_chain().collect::<Vec<_>>(), vec!["while starting the server", "while loading settings.toml"]);
+        assert!(error.source().is_none());
+
+        let wrapped: AppResult<()> = Err::<(), AppError>(error).context("while handling a request");
+        assert_eq!(wrapped.unwrap_err().context_chain().count(), 3);
+    }
+
+    #[test]
+    fn test_debug_report_lists_causes() {
+        let inner: AppError = AppError::with_source(Kind::Parse, "bad header", "x".parse::<u8>().unwrap_err());
+        let outer: AppError = AppError::with_source(Kind::Config, "bad settings", inner).context("while loading");
+        assert_eq!(
+            format!("{:?}", outer),
+            "Config: bad settings\n  while loading\nCaused by:\n  bad header\n  invalid digit found in string"
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Opening files by policy instead of matching on `ErrorKind` at every call
+//! site.
+//!
+//! ```no_run
+//! use error_handling::file::{ self, OpenPolicy };
+//! use std::fs::File;
+//! use std::io::Write;
+//!
+//! # fn main() -> Result<(), error_handling::error::AppError> {
+//! // Open hello.txt, creating it if it isn't there.
+//! let _hello: File = OpenPolicy::open_or_create().open("hello.txt")?;
+//!
+//! // Append to a log, creating logs/ on the way.
+//! let mut log: File = OpenPolicy { create_parent_dirs: true, ..OpenPolicy::append() }.open("logs/app.log")?;
+//! writeln!(log, "started")?;
+//!
+//! // Replace settings.toml so readers see the old file or the new one, never half of each.
+//! file::write_atomic("settings.toml", b"port = 8080\n")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Failures come back as [`AppError`]s whose context names the path.
+
+use crate::error::{ AppError, AppResult, Kind, ResultExt };
+use std::fs::{ self, File, OpenOptions };
+use std::io::{ self, Write };
+use std::path::{ Path, PathBuf };
+use std::process;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+/// What to do when the file is already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfExists {
+    /// Open it as it is.
+    Open,
+    /// Empty it.
+    Truncate,
+    /// Open it with every write going to the end.
+    Append,
+    Fail
+}
+
+/// What to do when the file isn't there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfMissing {
+    Create,
+    Fail
+}
+
+/// How to open a file. Start from one of the presets and override fields
+/// with struct update syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenPolicy {
+    pub if_exists: IfExists,
+    pub if_missing: IfMissing,
+    /// Create any missing directories above the file before creating it.
+    pub create_parent_dirs: bool
+}
+
+impl OpenPolicy {
+    /// Open the file for reading and writing, creating it if it's missing.
+    pub fn open_or_create() -> OpenPolicy {
+        OpenPolicy { if_exists: IfExists::Open, if_missing: IfMissing::Create, create_parent_dirs: false }
+    }
+
+    /// Open an existing file only.
+    pub fn open_existing() -> OpenPolicy {
+        OpenPolicy { if_missing: IfMissing::Fail, ..OpenPolicy::open_or_create() }
+    }
+
+    /// Create a new file, failing if one is already there.
+    pub fn create_new() -> OpenPolicy {
+        OpenPolicy { if_exists: IfExists::Fail, ..OpenPolicy::open_or_create() }
+    }
+
+    /// Create the file or empty the existing one, like `File::create`.
+    pub fn truncate() -> OpenPolicy {
+        OpenPolicy { if_exists: IfExists::Truncate, ..OpenPolicy::open_or_create() }
+    }
+
+    /// Create the file or append to the existing one.
+    pub fn append() -> OpenPolicy {
+        OpenPolicy { if_exists: IfExists::Append, ..OpenPolicy::open_or_create() }
+    }
+
+    /// Opens `path` for reading and writing according to the policy.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> AppResult<File> {
+        let path: &Path = path.as_ref();
+        self.check()?;
+
+        let mut options: OpenOptions = OpenOptions::new();
+        options.read(true);
+        match self.if_exists {
+            IfExists::Open => options.write(true),
+            IfExists::Truncate => options.write(true).truncate(true),
+            IfExists::Append => options.append(true),
+            IfExists::Fail => options.write(true)
+        };
+        match (self.if_missing, self.if_exists) {
+            (IfMissing::Create, IfExists::Fail) => options.create_new(true),
+            (IfMissing::Create, _) => options.create(true),
+            (IfMissing::Fail, _) => &mut options
+        };
+
+        if self.if_missing == IfMissing::Create && self.create_parent_dirs {
+            create_parent_dirs(path)?;
+        }
+        options.open(path).with_context(|| format!("while opening {}", path.display()))
+    }
+
+    /// Starts an [`AtomicFile`] that replaces `path` when committed.
+    ///
+    /// The policy is checked now, and `Fail` is checked again at commit,
+    /// which links the new file into place instead of renaming it so that
+    /// a file created in the meantime is never overwritten. `Append` starts
+    /// the temporary file with a copy of the existing contents; `Open` and
+    /// `Truncate` both start empty, since the whole file gets replaced.
+    pub fn open_atomic<P: AsRef<Path>>(&self, path: P) -> AppResult<AtomicFile> {
+        let path: &Path = path.as_ref();
+        self.check()?;
+
+        let exists: bool = path.exists();
+        let context = || format!("while starting an atomic write to {}", path.display());
+        if exists && self.if_exists == IfExists::Fail {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists)).with_context(context);
+        }
+        if !exists && self.if_missing == IfMissing::Fail {
+            return Err(io::Error::from(io::ErrorKind::NotFound)).with_context(context);
+        }
+        if self.create_parent_dirs {
+            create_parent_dirs(path)?;
+        }
+
+        let mut atomic: AtomicFile = AtomicFile::create(path)?;
+        atomic.replace = self.if_exists != IfExists::Fail;
+        if exists && self.if_exists == IfExists::Append {
+            let mut existing: File = File::open(path).with_context(context)?;
+            io::copy(&mut existing, &mut atomic.file).with_context(context)?;
+        }
+        Ok(atomic)
+    }
+
+    fn check(&self) -> AppResult<()> {
+        if self.if_exists == IfExists::Fail && self.if_missing == IfMissing::Fail {
+            return Err(AppError::config("the open policy refuses the file whether or not it exists"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for OpenPolicy {
+    fn default() -> OpenPolicy {
+        OpenPolicy::open_or_create()
+    }
+}
+
+fn create_parent_dirs(path: &Path) -> AppResult<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            fs::create_dir_all(parent).with_context(|| format!("while creating directory {}", parent.display()))
+        }
+        _ => Ok(())
+    }
+}
+
+/// Tells temporary files from concurrent writers in the same process apart.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A file that's written under a temporary name next to its destination and
+/// renamed into place by [`commit`](AtomicFile::commit), so other readers
+/// only ever see the old contents or the complete new ones. Dropping it
+/// without committing deletes the temporary file and leaves the destination
+/// alone.
+#[derive(Debug)]
+pub struct AtomicFile {
+    file: File,
+    temp_path: PathBuf,
+    path: PathBuf,
+    /// Whether `commit` may overwrite a file that's already at `path`.
+    replace: bool,
+    committed: bool
+}
+
+impl AtomicFile {
+    /// Starts replacing `path`. Its directory must exist, since the
+    /// temporary file has to be on the same file system for the rename to
+    /// be atomic.
+    pub fn create<P: AsRef<Path>>(path: P) -> AppResult<AtomicFile> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let name: String = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => return Err(AppError::new(Kind::Io, format!("{} isn't a file path", path.display())))
+        };
+
+        let temp_name: String = format!(".{}.{}-{}.tmp", name, process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed));
+        let temp_path: PathBuf = path.with_file_name(temp_name);
+        let file: File = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+            .with_context(|| format!("while creating a temporary file for {}", path.display()))?;
+
+        Ok(AtomicFile { file, temp_path, path, replace: true, committed: false })
+    }
+
+    /// Where the file will end up.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flushes the new contents to disk and renames them over the
+    /// destination. A file opened with [`IfExists::Fail`] is hard-linked into
+    /// place instead, which fails with [`Kind::AlreadyExists`] if something
+    /// else has created the destination since, and needs a file system that
+    /// supports hard links.
+    pub fn commit(mut self) -> AppResult<()> {
+        let path: &Path = &self.path;
+        let context = || format!("while replacing {}", path.display());
+        self.file.flush().with_context(context)?;
+        self.file.sync_all().with_context(context)?;
+        if self.replace {
+            fs::rename(&self.temp_path, path).with_context(context)?;
+        } else {
+            fs::hard_link(&self.temp_path, path).with_context(context)?;
+            // The new contents are in place, so a leftover temporary name is
+            // only clutter.
+            let _ = fs::remove_file(&self.temp_path);
+        }
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Replaces the contents of `path` atomically.
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> AppResult<()> {
+    let mut file: AtomicFile = AtomicFile::create(path)?;
+    let path: &Path = &file.path;
+    file.file.write_all(contents.as_ref()).with_context(|| format!("while writing {}", path.display()))?;
+    file.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{ Read, Seek, SeekFrom };
+
+    /// A fresh, empty directory for one test.
+    fn scratch(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("error-handling-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_open_or_create_keeps_contents() {
+        let dir: PathBuf = scratch("open-or-create");
+        let path: PathBuf = dir.join("hello.txt");
+
+        let mut file: File = OpenPolicy::open_or_create().open(&path).unwrap();
+        file.write_all(b"hello").unwrap();
+        drop(file);
+
+        let mut file: File = OpenPolicy::open_or_create().open(&path).unwrap();
+        let mut contents: String = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_existence_policies() {
+        let dir: PathBuf = scratch("existence");
+        let path: PathBuf = dir.join("data.txt");
+
+        let missing: AppError = OpenPolicy::open_existing().open(&path).unwrap_err();
+        assert_eq!(missing.kind(), Kind::NotFound);
+        assert!(missing.to_string().contains("data.txt"));
+
+        OpenPolicy::create_new().open(&path).unwrap().write_all(b"0123456789").unwrap();
+        assert_eq!(OpenPolicy::create_new().open(&path).unwrap_err().kind(), Kind::AlreadyExists);
+
+        let mut appended: File = OpenPolicy::append().open(&path).unwrap();
+        appended.seek(SeekFrom::Start(0)).unwrap();
+        appended.write_all(b"!").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0123456789!");
+
+        OpenPolicy::truncate().open(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"");
+
+        let refuse_all: OpenPolicy = OpenPolicy { if_missing: IfMissing::Fail, ..OpenPolicy::create_new() };
+        assert_eq!(refuse_all.open(&path).unwrap_err().kind(), Kind::Config);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_parent_dirs() {
+        let dir: PathBuf = scratch("parents");
+        let path: PathBuf = dir.join("logs").join("2024").join("app.log");
+
+        assert_eq!(OpenPolicy::append().open(&path).unwrap_err().kind(), Kind::NotFound);
+        OpenPolicy { create_parent_dirs: true, ..OpenPolicy::append() }.open(&path).unwrap();
+        assert!(path.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_atomic_write() {
+        let dir: PathBuf = scratch("atomic");
+        let path: PathBuf = dir.join("settings.toml");
+        fs::write(&path, "port = 80\n").unwrap();
+
+        let mut pending: AtomicFile = AtomicFile::create(&path).unwrap();
+        pending.write_all(b"port = 8080\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 80\n");
+        pending.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 8080\n");
+
+        let mut abandoned: AtomicFile = AtomicFile::create(&path).unwrap();
+        abandoned.write_all(b"half writ").unwrap();
+        drop(abandoned);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 8080\n");
+        assert_eq!(entries(&dir), vec!["settings.toml"]);
+
+        write_atomic(&path, "port = 1\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "port = 1\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_atomic_policies() {
+        let dir: PathBuf = scratch("open-atomic");
+        let path: PathBuf = dir.join("nested").join("log.txt");
+
+        let mut first: AtomicFile = OpenPolicy { create_parent_dirs: true, ..OpenPolicy::append() }.open_atomic(&path).unwrap();
+        first.write_all(b"one\n").unwrap();
+        first.commit().unwrap();
+
+        let mut second: AtomicFile = OpenPolicy::append().open_atomic(&path).unwrap();
+        second.write_all(b"two\n").unwrap();
+        second.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+
+        assert_eq!(OpenPolicy::create_new().open_atomic(&path).unwrap_err().kind(), Kind::AlreadyExists);
+
+        // Someone else creates the file between opening and committing.
+        let fresh: PathBuf = dir.join("fresh.txt");
+        let mut late: AtomicFile = OpenPolicy::create_new().open_atomic(&fresh).unwrap();
+        late.write_all(b"mine").unwrap();
+        fs::write(&fresh, "theirs").unwrap();
+        assert_eq!(late.commit().unwrap_err().kind(), Kind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&fresh).unwrap(), "theirs");
+        assert_eq!(entries(&dir), vec!["fresh.txt", "nested"]);
+
+        let mut new: AtomicFile = OpenPolicy::create_new().open_atomic(dir.join("new.txt")).unwrap();
+        new.write_all(b"new").unwrap();
+        new.commit().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("new.txt")).unwrap(), "new");
+        assert_eq!(entries(&dir), vec!["fresh.txt", "nested", "new.txt"]);
+
+        assert_eq!(OpenPolicy::open_existing().open_atomic(dir.join("nope")).unwrap_err().kind(), Kind::NotFound);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
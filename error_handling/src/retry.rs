@@ -0,0 +1,381 @@
+//! Retrying operations that fail for passing reasons, such as an interrupted
+//! system call or a busy non-blocking socket.
+//!
+//! ```
+//! use error_handling::retry::{ self, RetryPolicy };
+//! use std::io;
+//! use std::time::Duration;
+//!
+//! let mut calls: u32 = 0;
+//! let policy: RetryPolicy = RetryPolicy { max_attempts: 4, ..RetryPolicy::fixed(Duration::from_millis(1)) };
+//! let result = policy.run(
+//!     || {
+//!         calls += 1;
+//!         if calls < 3 { Err(io::Error::from(io::ErrorKind::Interrupted)) } else { Ok(calls) }
+//!     },
+//!     retry::is_transient
+//! );
+//! assert_eq!(result.unwrap(), 3);
+//! ```
+//!
+//! When the policy gives up, the [`RetryError`] keeps every attempt's error
+//! and says why it stopped.
+
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::fmt;
+use std::hash::{ BuildHasher, Hasher };
+use std::io;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// How long to wait before each retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    Fixed(Duration),
+    /// `initial`, then doubling after every failure up to `max`.
+    Exponential { initial: Duration, max: Duration },
+    /// A random delay between zero and what `Exponential` would wait, so
+    /// clients that failed together don't all retry together ("full
+    /// jitter").
+    Jittered { initial: Duration, max: Duration }
+}
+
+impl Backoff {
+    /// The delay before retry number `retry`, counting from zero. `random`
+    /// should be in `0.0..1.0` and is only used by `Jittered`.
+    pub fn delay(&self, retry: u32, random: f64) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => exponential(initial, max, retry),
+            Backoff::Jittered { initial, max } => exponential(initial, max, retry).mul_f64(random.clamp(0.0, 1.0))
+        }
+    }
+}
+
+fn exponential(initial: Duration, max: Duration, retry: u32) -> Duration {
+    let factor: u32 = 1u32.checked_shl(retry).unwrap_or(u32::MAX);
+    initial.checked_mul(factor).map_or(max, |delay| delay.min(max))
+}
+
+/// Where retries get the time from and how they wait, so tests can run
+/// without sleeping.
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&mut self, duration: Duration);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// When to retry. Start from one of the presets and override fields with
+/// struct update syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub backoff: Backoff,
+    /// Attempts in total, including the first.
+    pub max_attempts: u32,
+    /// Stop rather than start a wait that would end after this long since
+    /// the first attempt began.
+    pub deadline: Option<Duration>
+}
+
+impl RetryPolicy {
+    pub fn fixed(delay: Duration) -> RetryPolicy {
+        RetryPolicy { backoff: Backoff::Fixed(delay), max_attempts: 3, deadline: None }
+    }
+
+    pub fn exponential(initial: Duration, max: Duration) -> RetryPolicy {
+        RetryPolicy { backoff: Backoff::Exponential { initial, max }, max_attempts: 5, deadline: None }
+    }
+
+    pub fn jittered(initial: Duration, max: Duration) -> RetryPolicy {
+        RetryPolicy { backoff: Backoff::Jittered { initial, max }, max_attempts: 5, deadline: None }
+    }
+
+    /// Calls `operation` until it succeeds, fails with an error `retryable`
+    /// rejects, or the policy runs out of attempts or time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_attempts` is 0.
+    pub fn run<T, E, F, R>(&self, operation: F, retryable: R) -> Result<T, RetryError<E>>
+    where
+        F: FnMut() -> Result<T, E>,
+        R: FnMut(&E) -> bool
+    {
+        self.run_with_clock(&mut SystemClock, operation, retryable)
+    }
+
+    pub fn run_with_clock<C, T, E, F, R>(&self, clock: &mut C, mut operation: F, mut retryable: R) -> Result<T, RetryError<E>>
+    where
+        C: Clock,
+        F: FnMut() -> Result<T, E>,
+        R: FnMut(&E) -> bool
+    {
+        assert!(self.max_attempts > 0, "A retry policy needs at least one attempt");
+        let start: Instant = clock.now();
+        let mut random: Random = Random::new();
+        let mut attempts: Vec<Attempt<E>> = Vec::new();
+
+        loop {
+            let error: E = match operation() {
+                Ok(value) => return Ok(value),
+                Err(error) => error
+            };
+            let elapsed: Duration = clock.now().saturating_duration_since(start);
+
+            let reason: Option<GaveUp> = if !retryable(&error) {
+                Some(GaveUp::NotRetryable)
+            } else if attempts.len() + 1 >= self.max_attempts as usize {
+                Some(GaveUp::MaxAttempts)
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                attempts.push(Attempt { error, elapsed, delay: None });
+                return Err(RetryError { attempts, reason });
+            }
+
+            let delay: Duration = self.backoff.delay(attempts.len() as u32, random.next_f64());
+            if self.deadline.is_some_and(|deadline| elapsed.checked_add(delay).is_none_or(|end| end > deadline)) {
+                attempts.push(Attempt { error, elapsed, delay: None });
+                return Err(RetryError { attempts, reason: GaveUp::Deadline });
+            }
+
+            attempts.push(Attempt { error, elapsed, delay: Some(delay) });
+            clock.sleep(delay);
+        }
+    }
+}
+
+/// Errors worth retrying because the same call may well succeed next time.
+pub fn is_transient(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+/// xorshift seeded from the standard library's per-process random hash
+/// keys; plenty for spreading out retries.
+struct Random(u64);
+
+impl Random {
+    fn new() -> Random {
+        let seed: u64 = RandomState::new().build_hasher().finish();
+        Random(seed | 1)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GaveUp {
+    NotRetryable,
+    MaxAttempts,
+    Deadline
+}
+
+impl fmt::Display for GaveUp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason: &str = match self {
+            GaveUp::NotRetryable => "the error isn't retryable",
+            GaveUp::MaxAttempts => "out of attempts",
+            GaveUp::Deadline => "out of time"
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// One failed attempt.
+#[derive(Debug)]
+pub struct Attempt<E> {
+    pub error: E,
+    /// Time from the start of the first attempt to this failure.
+    pub elapsed: Duration,
+    /// How long was waited before the next attempt; `None` for the last.
+    pub delay: Option<Duration>
+}
+
+/// Every failed attempt, oldest first, and why retrying stopped.
+#[derive(Debug)]
+pub struct RetryError<E> {
+    attempts: Vec<Attempt<E>>,
+    reason: GaveUp
+}
+
+impl<E> RetryError<E> {
+    pub fn attempts(&self) -> &[Attempt<E>] {
+        &self.attempts
+    }
+
+    pub fn reason(&self) -> GaveUp {
+        self.reason
+    }
+
+    pub fn last(&self) -> &E {
+        &self.attempts.last().expect("a retry error always has an attempt").error
+    }
+
+    pub fn into_last(mut self) -> E {
+        self.attempts.pop().expect("a retry error always has an attempt").error
+    }
+
+    pub fn into_errors(self) -> Vec<E> {
+        self.attempts.into_iter().map(|attempt| attempt.error).collect()
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural: &str = if self.attempts.len() == 1 { "" } else { "s" };
+        write!(f, "gave up after {} attempt{} ({}): {}", self.attempts.len(), plural, self.reason, self.last())
+    }
+}
+
+impl<E: Error + 'static> Error for RetryError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.last())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeClock {
+        now: Instant,
+        slept: Vec<Duration>
+    }
+
+    impl FakeClock {
+        fn new() -> FakeClock {
+            FakeClock { now: Instant::now(), slept: Vec::new() }
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.now
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.now += duration;
+            self.slept.push(duration);
+        }
+    }
+
+    fn millis(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Fails with each kind in turn, then succeeds.
+    fn failing(kinds: Vec<io::ErrorKind>) -> impl FnMut() -> io::Result<usize> {
+        let mut calls: usize = 0;
+        move || {
+            calls += 1;
+            match kinds.get(calls - 1) {
+                Some(&kind) => Err(io::Error::new(kind, format!("failure {}", calls))),
+                None => Ok(calls)
+            }
+        }
+    }
+
+    #[test]
+    fn test_backoff_delays() {
+        let exponential: Backoff = Backoff::Exponential { initial: millis(10), max: millis(50) };
+        let delays: Vec<Duration> = (0..5).map(|retry| exponential.delay(retry, 0.5)).collect();
+        assert_eq!(delays, vec![millis(10), millis(20), millis(40), millis(50), millis(50)]);
+        assert_eq!(exponential.delay(100, 0.0), millis(50));
+
+        assert_eq!(Backoff::Fixed(millis(7)).delay(9, 0.9), millis(7));
+        let jittered: Backoff = Backoff::Jittered { initial: millis(10), max: millis(50) };
+        assert_eq!(jittered.delay(2, 0.25), millis(10));
+        assert_eq!(jittered.delay(2, 0.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_retries_transient_errors_until_success() {
+        let mut clock: FakeClock = FakeClock::new();
+        let policy: RetryPolicy = RetryPolicy::exponential(millis(10), millis(100));
+        let kinds: Vec<io::ErrorKind> = vec![io::ErrorKind::Interrupted, io::ErrorKind::WouldBlock];
+
+        let result: Result<usize, RetryError<io::Error>> = policy.run_with_clock(&mut clock, failing(kinds), is_transient);
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(clock.slept, vec![millis(10), millis(20)]);
+    }
+
+    #[test]
+    fn test_stops_on_non_retryable_error() {
+        let mut clock: FakeClock = FakeClock::new();
+        let kinds: Vec<io::ErrorKind> = vec![io::ErrorKind::Interrupted, io::ErrorKind::NotFound, io::ErrorKind::Interrupted];
+
+        let error: RetryError<io::Error> = RetryPolicy::fixed(millis(5)).run_with_clock(&mut clock, failing(kinds), is_transient).unwrap_err();
+        assert_eq!(error.reason(), GaveUp::NotRetryable);
+        assert_eq!(error.attempts().len(), 2);
+        assert_eq!(error.attempts()[0].delay, Some(millis(5)));
+        assert_eq!(error.attempts()[1].elapsed, millis(5));
+        assert_eq!(error.last().kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "gave up after 2 attempts (the error isn't retryable): failure 2");
+    }
+
+    #[test]
+    fn test_reports_every_attempt_when_out_of_attempts() {
+        let mut clock: FakeClock = FakeClock::new();
+        let policy: RetryPolicy = RetryPolicy { max_attempts: 3, ..RetryPolicy::jittered(millis(10), millis(40)) };
+
+        let error: RetryError<io::Error> = policy
+            .run_with_clock(&mut clock, failing(vec![io::ErrorKind::TimedOut; 5]), is_transient)
+            .unwrap_err();
+        assert_eq!(error.reason(), GaveUp::MaxAttempts);
+        assert_eq!(clock.slept.len(), 2);
+        assert!(clock.slept[0] <= millis(10) && clock.slept[1] <= millis(20));
+        assert!(error.source().is_some());
+
+        let messages: Vec<String> = error.into_errors().iter().map(io::Error::to_string).collect();
+        assert_eq!(messages, vec!["failure 1", "failure 2", "failure 3"]);
+    }
+
+    #[test]
+    fn test_deadline_stops_before_waiting_past_it() {
+        let mut clock: FakeClock = FakeClock::new();
+        let policy: RetryPolicy = RetryPolicy {
+            max_attempts: 100,
+            deadline: Some(millis(100)),
+            ..RetryPolicy::exponential(millis(20), millis(1000))
+        };
+
+        let error: RetryError<&str> = policy.run_with_clock(&mut clock, || Err::<(), &str>("busy"), |_| true).unwrap_err();
+        assert_eq!(error.reason(), GaveUp::Deadline);
+        // 20 + 40 fit in 100ms; another 80 would not.
+        assert_eq!(clock.slept, vec![millis(20), millis(40)]);
+        assert_eq!(error.into_last(), "busy");
+
+        // A wait too long to add up is past any deadline.
+        let mut clock: FakeClock = FakeClock::new();
+        let forever: RetryPolicy = RetryPolicy { deadline: Some(millis(100)), ..RetryPolicy::fixed(Duration::MAX) };
+        let error: RetryError<&str> = forever.run_with_clock(&mut clock, || Err::<(), &str>("busy"), |_| true).unwrap_err();
+        assert_eq!(error.reason(), GaveUp::Deadline);
+        assert!(clock.slept.is_empty());
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&io::Error::from(io::ErrorKind::Interrupted)));
+        assert!(is_transient(&io::Error::from(io::ErrorKind::WouldBlock)));
+        assert!(!is_transient(&io::Error::from(io::ErrorKind::PermissionDenied)));
+    }
+}